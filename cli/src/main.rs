@@ -4,22 +4,34 @@ extern crate log;
 use relative_lempel_ziv::memory_usage::MemoryUsage;
 use relative_lempel_ziv::RelativeLempelZiv;
 use simplelog::*;
+use std::env;
+use std::ffi::OsString;
 use std::fs;
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::BufReader;
-use std::path::{Display, PathBuf};
+use std::io::BufWriter;
+use std::io::{self, Read};
+use std::path::{Display, Path, PathBuf};
+use std::str::FromStr;
 use std::time::{Duration, Instant};
 
 // CLI tools
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use console::style;
+use flate2::read::GzDecoder;
 use indicatif::HumanBytes;
 use structopt::StructOpt;
 
-// Todo: Other arguments, like outputting the encoded data
-// Which probably needs some form of serialization
 // Docs @ https://docs.rs/structopt/0.3.20/structopt/
+#[derive(StructOpt)]
+enum Cli {
+    /// Compresses a file or directory, which is also what happens without a subcommand
+    Compress(CliInput),
+    /// Decompresses a file written by `compress --output`
+    Decompress(DecompressInput),
+}
+
 #[derive(StructOpt)]
 struct CliInput {
     /// The path to the file (or directory if the is-dir flag is set) to compress data from
@@ -36,23 +48,151 @@ struct CliInput {
     #[structopt(short = "d", long)]
     is_dir: bool,
 
-    /// INCOMPLETE: Output compressed data to file (needs serde Serialize trait first)
-    #[structopt(short = "o", long = "output")]
-    _output: Option<PathBuf>,
+    /// Writes the compressed data to this file, which can be decompressed again with `decompress`
+    #[structopt(short = "o", long, parse(from_os_str))]
+    output: Option<PathBuf>,
 
     /// If you want to manually tell the cli which reference strings to take
     #[structopt(short = "i", default_value = "0")]
     i: Vec<usize>,
 
+    /// Encodes against the reference string in this file, reading the input one string at a time
+    /// instead of all of it into memory. The strategy, `-i` and `chars` are not used then, since
+    /// bytes missing from the reference are added as they show up
+    #[structopt(short, long, parse(from_os_str), conflicts_with_all = &["compare", "verify"])]
+    reference: Option<PathBuf>,
+
     /// The characters that the reference string must include, is appended at the end of the reference string to ensure all chars are present.
     #[structopt(short, long)]
     chars: String,
+
+    /// Encodes the input with every strategy and prints a table comparing them, instead of the usual output
+    #[structopt(long)]
+    compare: bool,
+
+    /// Writes the decompressed strings to this path, which is a directory for the `dir` output format
+    #[structopt(long, parse(from_os_str))]
+    decompressed: Option<PathBuf>,
+
+    /// How the decompressed strings are written: `lines` is one string per line, `dir` is one file per
+    /// string named after it, and `fasta` is a FASTA file with the names as headers
+    #[structopt(long, default_value = "lines", possible_values = &["lines", "dir", "fasta"])]
+    output_format: OutputFormat,
+
+    /// Only logs warnings and errors to the terminal
+    #[structopt(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Logs more to the terminal, once for debug and twice (-vv) for trace. The log file always gets everything
+    #[structopt(short, long, parse(from_occurrences))]
+    verbose: u8,
+
+    /// Reads every input file as gzip compressed. Files ending in `.gz` always are
+    #[structopt(long)]
+    gzip: bool,
+
+    /// Decodes every string again and checks it against the input, failing on the first mismatch
+    #[structopt(long)]
+    verify: bool,
+}
+
+#[derive(StructOpt)]
+struct DecompressInput {
+    /// The compressed file to decompress
+    #[structopt(parse(from_os_str))]
+    path: PathBuf,
+
+    /// Where the decompressed strings are written, which is a directory for the `dir` output format
+    #[structopt(short = "o", long, parse(from_os_str))]
+    output: PathBuf,
+
+    /// How the decompressed strings are written, see `compress --output-format`. Defaults to `dir`
+    /// if the strings have names, so a compressed directory comes back as the same files
+    #[structopt(long, possible_values = &["lines", "dir", "fasta"])]
+    output_format: Option<OutputFormat>,
+
+    /// Only logs warnings and errors to the terminal
+    #[structopt(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Logs more to the terminal, once for debug and twice (-vv) for trace
+    #[structopt(short, long, parse(from_occurrences))]
+    verbose: u8,
+}
+
+enum OutputFormat {
+    Lines,
+    Dir,
+    Fasta,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "lines" => Ok(OutputFormat::Lines),
+            "dir" => Ok(OutputFormat::Dir),
+            "fasta" => Ok(OutputFormat::Fasta),
+            _ => Err(format!("Unknown output format `{}`", s)),
+        }
+    }
+}
+
+// The strategies that can be given with `--strategy`, in the order
+// they are listed in the `--compare` table.
+const STRATEGIES: [usize; 2] = [1, 2];
+
+fn is_gzip(path: &Path, gzip: bool) -> bool {
+    gzip || path.extension().is_some_and(|e| e == "gz")
+}
+
+// Opens the file for reading, decompressing it on the fly if gzip is set
+fn open_input(path: &Path, gzip: bool) -> io::Result<Box<dyn BufRead>> {
+    let file = File::open(path)?;
+    if gzip {
+        Ok(Box::new(BufReader::new(GzDecoder::new(file))))
+    } else {
+        Ok(Box::new(BufReader::new(file)))
+    }
+}
+
+// Compressing was the only thing the cli could do before it got subcommands,
+// so `compress` is put in front of the arguments when there is no subcommand
+// to keep `cli <path> ...` working.
+fn with_default_subcommand(mut args: Vec<OsString>) -> Vec<OsString> {
+    const KNOWN: [&str; 7] = [
+        "compress",
+        "decompress",
+        "help",
+        "-h",
+        "--help",
+        "-V",
+        "--version",
+    ];
+    match args.get(1) {
+        Some(arg) if KNOWN.iter().any(|k| arg == k) => {}
+        _ => args.insert(1.min(args.len()), OsString::from("compress")),
+    }
+    args
 }
 
 // Example input: "../test_data/dna.50MB"
 fn main() -> Result<()> {
-    let args = CliInput::from_args();
-    init_logging();
+    match Cli::from_iter(with_default_subcommand(env::args_os().collect())) {
+        Cli::Compress(args) => compress(args),
+        Cli::Decompress(args) => decompress(args),
+    }
+}
+
+fn compress(args: CliInput) -> Result<()> {
+    init_logging(terminal_level(args.quiet, args.verbose));
+
+    if let Some(reference) = &args.reference {
+        let stopwatch = Instant::now();
+        let (encoded, total_size) = encode_streaming(&args, reference)?;
+        return write_output(&args, &encoded, total_size, stopwatch.elapsed());
+    }
 
     info!("Using {:?} as reference strings", &args.i);
 
@@ -62,21 +202,23 @@ fn main() -> Result<()> {
     if !args.is_dir {
         trace!("Loading single file into memory");
 
-        let file = File::open(&args.path)
+        let gzip = is_gzip(&args.path, args.gzip);
+        let buf_reader = open_input(&args.path, gzip)
             .with_context(|| format!("Could not read file `{}`", args.path.display()))?;
-        let file_metadata = file.metadata();
+        let file_metadata = fs::metadata(&args.path);
 
-        let buf_reader = BufReader::new(file);
         strings = buf_reader
             .lines()
-            .map(|l| (l.unwrap(), String::new()))
-            .collect();
+            .map(|l| l.map(|l| (l, String::new())))
+            .collect::<io::Result<_>>()
+            .with_context(|| format!("Could not read file `{}`", args.path.display()))?;
 
         // Uses the file's metadata for the file size if it exists, otherwise it has to
-        // calculate this using the len of every line.
+        // calculate this using the len of every line. The size of a compressed file
+        // says nothing about the strings, so the lines are used for those too.
         total_size = match file_metadata {
-            Ok(metadata) => metadata.len(),
-            Err(_) => strings.iter().fold(0, |acc, l| acc + l.0.len() as u64),
+            Ok(metadata) if !gzip => metadata.len(),
+            _ => strings.iter().fold(0, |acc, l| acc + l.0.len() as u64),
         };
     } else {
         trace!("Loading directory files into memory");
@@ -87,18 +229,9 @@ fn main() -> Result<()> {
         let mut tmp_strings = vec![];
         let mut size = 0;
         for dir_entry in dir {
-            let path = dir_entry?.path();
-            let file = File::open(&path)
-                .with_context(|| format!("Could not read file `{}`", path.display()))?;
-            let file_name = path.file_name().unwrap();
-            let file_metadata = file.metadata();
-            let file_string = fs::read_to_string(&path)?.replace(&['\n', '\r'][..], "");
-
-            size += match file_metadata {
-                Ok(metadata) => metadata.len(),
-                Err(_) => file_string.len() as u64,
-            };
-            tmp_strings.push((file_string, String::from(file_name.to_str().unwrap())));
+            let (file_string, file_name, file_size) = read_dir_file(&dir_entry?.path(), args.gzip)?;
+            size += file_size;
+            tmp_strings.push((file_string, file_name));
         }
 
         strings = tmp_strings;
@@ -108,37 +241,311 @@ fn main() -> Result<()> {
     let chars = if args.chars.is_empty() {
         None
     } else {
-        Some(args.chars)
+        Some(args.chars.clone())
     };
+
+    if args.compare {
+        let mut rows = Vec::with_capacity(STRATEGIES.len());
+        for &strategy in STRATEGIES.iter() {
+            let stopwatch = Instant::now();
+            let encoded = encode_with_strategy(strategy, &strings, &args.i, chars.clone());
+            let elapsed_time = stopwatch.elapsed();
+            rows.push((
+                strategy,
+                encoded.memory_footprint(Some(total_size as usize)),
+                elapsed_time,
+            ));
+        }
+        print!("{}", comparison_table(&rows));
+        return Ok(());
+    }
+
     let stopwatch = Instant::now();
-    let encoded = match args.strategy {
-        1 => {
-            let s = strings.iter().map(|t| &t.0).collect::<Vec<_>>();
-            RelativeLempelZiv::<u32>::encode(&s, Some(args.i), chars)
+    let encoded = encode_with_strategy(args.strategy, &strings, &args.i, chars);
+    write_output(&args, &encoded, total_size, stopwatch.elapsed())?;
+
+    if args.verify {
+        verify(&encoded, &strings)?;
+    }
+
+    // info!("Analysis data size: {}", analysis.list.len());
+    // let mut file = File::create("analysis.txt")?;
+    // file.write_all(format!("{}\n", analysis).as_bytes())?;
+
+    Ok(())
+}
+
+// Reads a file of a directory input as one string without its line
+// breaks, along with its name and its size on disk
+fn read_dir_file(path: &Path, gzip: bool) -> Result<(String, String, u64)> {
+    let gzip = is_gzip(path, gzip);
+    let mut file_string = String::new();
+    open_input(path, gzip)
+        .and_then(|mut reader| reader.read_to_string(&mut file_string))
+        .with_context(|| format!("Could not read file `{}`", path.display()))?;
+    let file_name = path.file_name().unwrap();
+    let file_metadata = fs::metadata(path);
+    let file_string = file_string.replace(&['\n', '\r'][..], "");
+
+    let size = match file_metadata {
+        Ok(metadata) if !gzip => metadata.len(),
+        _ => file_string.len() as u64,
+    };
+    Ok((file_string, String::from(file_name.to_str().unwrap()), size))
+}
+
+// Encodes the input against the reference file while it is being read,
+// so only one string of the input is in memory at a time
+fn encode_streaming(args: &CliInput, reference: &Path) -> Result<(RelativeLempelZiv<u32>, u64)> {
+    trace!("Streaming the input against `{}`", reference.display());
+    let (reference, _, _) = read_dir_file(reference, args.gzip)?;
+
+    let records: Box<dyn Iterator<Item = Result<(String, String, u64)>>> = if args.is_dir {
+        let dir = fs::read_dir(&args.path)
+            .with_context(|| format!("Could not read directory `{}`", args.path.display()))?;
+        let gzip = args.gzip;
+        Box::new(dir.map(move |entry| read_dir_file(&entry?.path(), gzip)))
+    } else {
+        let reader = open_input(&args.path, is_gzip(&args.path, args.gzip))
+            .with_context(|| format!("Could not read file `{}`", args.path.display()))?;
+        Box::new(reader.lines().map(|line| {
+            let line = line?;
+            let size = line.len() as u64;
+            Ok((line, String::new(), size))
+        }))
+    };
+
+    // The encoding only sees the strings, so the first error stops the
+    // iterator and is returned once the encoding is done
+    let mut error = None;
+    let mut total_size = 0;
+    let strings = records.scan(&mut error, |error, record| match record {
+        Ok((s, name, size)) => {
+            total_size += size;
+            Some((s, name))
         }
-        2 => RelativeLempelZiv::<u32>::encode_reference_merge(&strings, chars),
-        _ => panic!("Invalid strategy input"),
+        Err(e) => {
+            **error = Some(e);
+            None
+        }
+    });
+    let encoded = if args.is_dir {
+        RelativeLempelZiv::<u32>::encode_streaming_with_names(&reference, strings)
+    } else {
+        RelativeLempelZiv::<u32>::encode_streaming(&reference, strings.map(|(s, _)| s))
     };
-    let elapsed_time = stopwatch.elapsed();
+    if let Some(e) = error {
+        return Err(e);
+    }
+    Ok((encoded, total_size))
+}
 
+// Prints the compression, and writes the compressed and decompressed
+// data if they were asked for
+fn write_output(
+    args: &CliInput,
+    encoded: &RelativeLempelZiv<u32>,
+    total_size: u64,
+    elapsed_time: Duration,
+) -> Result<()> {
     let memory_size = encoded.memory_footprint(Some(total_size as usize));
 
     print_compression_data(args.path.display(), memory_size, elapsed_time);
 
+    if let Some(path) = &args.output {
+        save_encoded(encoded, path)?;
+    }
+
     let stopwatch = Instant::now();
-    // The `let _` is needed for the compiler to not throw
-    // away this computation since it is not "used"
-    let _ = encoded.decode();
+    match &args.decompressed {
+        Some(path) => write_decompressed(encoded, path, &args.output_format)?,
+        // The streamed input is not kept in memory, so neither are the
+        // decoded strings, which are timed one at a time instead
+        None if args.reference.is_some() => encoded
+            .decode_to_writer(b"\n", &mut io::sink())
+            .context("Could not decode the encoding")?,
+        None => {
+            // The `let _` is needed for the compiler to not throw
+            // away this computation since it is not "used"
+            let _ = encoded.decode();
+        }
+    }
     let decompressed_time = stopwatch.elapsed();
     print_decompression_time(decompressed_time);
+    Ok(())
+}
 
-    // info!("Analysis data size: {}", analysis.list.len());
-    // let mut file = File::create("analysis.txt")?;
-    // file.write_all(format!("{}\n", analysis).as_bytes())?;
+fn decompress(args: DecompressInput) -> Result<()> {
+    init_logging(terminal_level(args.quiet, args.verbose));
+
+    let stopwatch = Instant::now();
+    let encoded = load_encoded(&args.path)?;
+    let format = match args.output_format {
+        Some(format) => format,
+        None if encoded.names().iter().any(|n| !n.is_empty()) => OutputFormat::Dir,
+        None => OutputFormat::Lines,
+    };
+    write_decompressed(&encoded, &args.output, &format)?;
+    info!(
+        "Decompressed {} strings from `{}` to `{}`, taking {:?}",
+        encoded.len(),
+        args.path.display(),
+        args.output.display(),
+        stopwatch.elapsed()
+    );
+    Ok(())
+}
+
+// The compressed files are just the encoding run through bincode
+fn save_encoded(encoded: &RelativeLempelZiv<u32>, path: &Path) -> Result<()> {
+    trace!("Writing compressed data to `{}`", path.display());
+    let mut file = File::create(path)
+        .map(BufWriter::new)
+        .with_context(|| format!("Could not create file `{}`", path.display()))?;
+    bincode::serialize_into(&mut file, encoded)
+        .with_context(|| format!("Could not write compressed data to `{}`", path.display()))?;
+    file.flush()?;
+    Ok(())
+}
+
+fn load_encoded(path: &Path) -> Result<RelativeLempelZiv<u32>> {
+    trace!("Reading compressed data from `{}`", path.display());
+    let file = File::open(path)
+        .map(BufReader::new)
+        .with_context(|| format!("Could not read file `{}`", path.display()))?;
+    bincode::deserialize_from(file)
+        .with_context(|| format!("`{}` is not a compressed file", path.display()))
+}
+
+fn encode_with_strategy(
+    strategy: usize,
+    strings: &[(String, String)],
+    i: &[usize],
+    chars: Option<String>,
+) -> RelativeLempelZiv<u32> {
+    match strategy {
+        1 => RelativeLempelZiv::<u32>::from_collection_with_names(strings, Some(i.to_vec()), chars),
+        2 => RelativeLempelZiv::<u32>::encode_reference_merge(strings, chars),
+        _ => panic!("Invalid strategy input"),
+    }
+}
+
+// Checks that every string decodes to exactly what was read from the
+// input, and reports where the first one that does not goes wrong
+fn verify(encoded: &RelativeLempelZiv<u32>, strings: &[(String, String)]) -> Result<()> {
+    trace!("Verifying the decoded strings against the input");
+    if encoded.len() != strings.len() {
+        bail!(
+            "Verification failed: decoded {} strings from an input of {}",
+            encoded.len(),
+            strings.len()
+        );
+    }
+    for (i, (decoded, (original, _))) in encoded.iter_decoded().zip(strings.iter()).enumerate() {
+        if let Some(offset) = first_mismatch(decoded.as_bytes(), original.as_bytes()) {
+            bail!(
+                "Verification failed: string {} (`{}`) differs from the input at byte offset {}",
+                i,
+                record_name(encoded, i),
+                offset
+            );
+        }
+    }
+    info!("Verified all {} strings against the input", strings.len());
+    Ok(())
+}
+
+// The first offset where a and b differ, which is the length of the
+// shorter one if it is a prefix of the other
+fn first_mismatch(a: &[u8], b: &[u8]) -> Option<usize> {
+    match a.iter().zip(b.iter()).position(|(x, y)| x != y) {
+        Some(offset) => Some(offset),
+        None if a.len() != b.len() => Some(a.len().min(b.len())),
+        None => None,
+    }
+}
+
+// The name of the i'th string, or its index if it has no name
+fn record_name(encoded: &RelativeLempelZiv<u32>, i: usize) -> String {
+    match encoded.names().get(i) {
+        Some(name) if !name.is_empty() => name.clone(),
+        _ => i.to_string(),
+    }
+}
+
+fn write_decompressed(
+    encoded: &RelativeLempelZiv<u32>,
+    path: &Path,
+    format: &OutputFormat,
+) -> Result<()> {
+    trace!("Writing decompressed strings to `{}`", path.display());
+    let create = |path: &Path| {
+        File::create(path)
+            .map(BufWriter::new)
+            .with_context(|| format!("Could not create file `{}`", path.display()))
+    };
 
+    match format {
+        OutputFormat::Lines => {
+            let mut file = create(path)?;
+            encoded.decode_to_writer(b"\n", &mut file)?;
+            file.flush()?;
+        }
+        OutputFormat::Dir => {
+            fs::create_dir_all(path)
+                .with_context(|| format!("Could not create directory `{}`", path.display()))?;
+            for i in 0..encoded.len() {
+                // The names come from the compressed file, which should not
+                // get to write anywhere else than in the directory
+                let name = record_name(encoded, i);
+                if Path::new(&name).file_name() != Some(name.as_ref()) {
+                    bail!("`{}` can not be used as a file name", name);
+                }
+                let mut file = create(&path.join(name))?;
+                encoded.extract_to_writer(i as u32, &mut file)?;
+                file.flush()?;
+            }
+        }
+        OutputFormat::Fasta => {
+            let mut file = create(path)?;
+            for i in 0..encoded.len() {
+                writeln!(file, ">{}", record_name(encoded, i))?;
+                encoded.extract_to_writer(i as u32, &mut file)?;
+                writeln!(file)?;
+            }
+            file.flush()?;
+        }
+    }
     Ok(())
 }
 
+fn strategy_name(strategy: usize) -> &'static str {
+    match strategy {
+        1 => "reference",
+        2 => "merge",
+        _ => panic!("Invalid strategy input"),
+    }
+}
+
+// One row per strategy with the compression rate, the compressed size
+// and how long the encoding took.
+fn comparison_table(rows: &[(usize, MemoryUsage, Duration)]) -> String {
+    let mut table = format!(
+        "{:<12} {:>10} {:>14} {:>14}\n",
+        "strategy", "rate", "compressed", "encode time"
+    );
+    for (strategy, memory, time) in rows {
+        table.push_str(&format!(
+            "{:<12} {:>10.4} {:>14} {:>14}\n",
+            format!("{}. {}", strategy, strategy_name(*strategy)),
+            memory.compression_rate().unwrap(),
+            HumanBytes(memory.compressed_size() as u64).to_string(),
+            format!("{:.2?}", time)
+        ));
+    }
+    table
+}
+
 fn print_compression_data(path: Display, memory: MemoryUsage, time: Duration) {
     let compressed_size = memory.compressed_size();
     let compression_rate = memory.compression_rate().unwrap();
@@ -184,9 +591,19 @@ fn print_decompression_time(time: Duration) {
     info!("Decompression time took {:?}", time);
 }
 
-fn init_logging() {
+// The level of the terminal logger, which defaults to info
+fn terminal_level(quiet: bool, verbose: u8) -> LevelFilter {
+    match (quiet, verbose) {
+        (true, _) => LevelFilter::Warn,
+        (false, 0) => LevelFilter::Info,
+        (false, 1) => LevelFilter::Debug,
+        (false, _) => LevelFilter::Trace,
+    }
+}
+
+fn init_logging(terminal_level: LevelFilter) {
     CombinedLogger::init(vec![
-        TermLogger::new(LevelFilter::Trace, Config::default(), TerminalMode::Mixed),
+        TermLogger::new(terminal_level, Config::default(), TerminalMode::Mixed),
         WriteLogger::new(
             LevelFilter::Trace,
             Config::default(),
@@ -0,0 +1,340 @@
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+// Every test gets its own directory, since the cli writes
+// its log file into the current working directory.
+fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rlz_cli_{}_{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn run_cli(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_cli"))
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn write_input(dir: &Path) -> PathBuf {
+    let input = dir.join("input.txt");
+    let line = "ACGTTGCAACGTAGCTAGCTTTGACCAGTAGGACGTTGCAAGTC";
+    let contents = format!("{}\n{}A\nT{}\n{}\n", line, line, line, line);
+    fs::write(&input, contents).unwrap();
+    input
+}
+
+#[test]
+fn compare_prints_a_row_per_strategy() {
+    let dir = test_dir("compare");
+    let input = write_input(&dir);
+    let output = run_cli(&dir, &[input.to_str().unwrap(), "-c", "ACGT", "--compare"]);
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let rows = stdout
+        .lines()
+        .filter(|l| l.starts_with("1. reference") || l.starts_with("2. merge"))
+        .count();
+    assert_eq!(2, rows);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn quiet_suppresses_info_lines() {
+    let dir = test_dir("quiet");
+    let input = write_input(&dir);
+
+    let output = run_cli(&dir, &[input.to_str().unwrap(), "-c", "ACGT"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("Compression rate"));
+
+    let output = run_cli(&dir, &[input.to_str().unwrap(), "-c", "ACGT", "--quiet"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(!stdout.contains("[INFO]") && !stderr.contains("[INFO]"));
+
+    // The log file still gets everything
+    let log = fs::read_to_string(dir.join("rlz.log")).unwrap();
+    assert!(log.contains("Compression rate"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+// A directory with a file per string, so the strings get names
+fn write_input_dir(dir: &Path) -> PathBuf {
+    let input = dir.join("records");
+    fs::create_dir_all(&input).unwrap();
+    fs::write(input.join("first"), "ACGTTGCAACGTAGCTAGCT").unwrap();
+    fs::write(input.join("second"), "ACGTTGCAACGTAGCTAGCA").unwrap();
+    input
+}
+
+#[test]
+fn output_format_lines() {
+    let dir = test_dir("output_lines");
+    let input = write_input(&dir);
+    let out = dir.join("out.txt");
+    let output = run_cli(
+        &dir,
+        &[
+            input.to_str().unwrap(),
+            "-c",
+            "ACGT",
+            "--decompressed",
+            out.to_str().unwrap(),
+        ],
+    );
+    assert!(output.status.success());
+    assert_eq!(
+        fs::read_to_string(&input).unwrap(),
+        fs::read_to_string(&out).unwrap()
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn verify_reports_success() {
+    let dir = test_dir("verify");
+    let input = write_input(&dir);
+    let output = run_cli(&dir, &[input.to_str().unwrap(), "-c", "ACGT", "--verify"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("Verified all 4 strings"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn gzip_input() {
+    let dir = test_dir("gzip");
+    let input = write_input(&dir);
+    let contents = fs::read(&input).unwrap();
+    let gz = dir.join("input.txt.gz");
+    let mut encoder = GzEncoder::new(fs::File::create(&gz).unwrap(), Compression::default());
+    encoder.write_all(&contents).unwrap();
+    encoder.finish().unwrap();
+
+    let out = dir.join("out.txt");
+    let output = run_cli(
+        &dir,
+        &[
+            gz.to_str().unwrap(),
+            "-c",
+            "ACGT",
+            "--decompressed",
+            out.to_str().unwrap(),
+        ],
+    );
+    assert!(output.status.success());
+    assert_eq!(contents, fs::read(&out).unwrap());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn truncated_gzip_input() {
+    let dir = test_dir("gzip_truncated");
+    let input = write_input(&dir);
+    let mut encoder = GzEncoder::new(vec![], Compression::default());
+    encoder.write_all(&fs::read(&input).unwrap()).unwrap();
+    let compressed = encoder.finish().unwrap();
+    let gz = dir.join("input.txt.gz");
+    fs::write(&gz, &compressed[..compressed.len() / 2]).unwrap();
+
+    let output = run_cli(&dir, &[gz.to_str().unwrap(), "-c", "ACGT"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(stderr.contains("Could not read file"));
+    assert!(!stderr.contains("panicked"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn output_format_dir() {
+    let dir = test_dir("output_dir");
+    let input = write_input_dir(&dir);
+    let out = dir.join("out");
+    let output = run_cli(
+        &dir,
+        &[
+            input.to_str().unwrap(),
+            "-d",
+            "-c",
+            "ACGT",
+            "--decompressed",
+            out.to_str().unwrap(),
+            "--output-format",
+            "dir",
+        ],
+    );
+    assert!(output.status.success());
+    for name in &["first", "second"] {
+        assert_eq!(
+            fs::read_to_string(input.join(name)).unwrap(),
+            fs::read_to_string(out.join(name)).unwrap()
+        );
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn output_format_fasta() {
+    let dir = test_dir("output_fasta");
+    let input = write_input_dir(&dir);
+    let out = dir.join("out.fa");
+    let output = run_cli(
+        &dir,
+        &[
+            input.to_str().unwrap(),
+            "-d",
+            "-c",
+            "ACGT",
+            "--decompressed",
+            out.to_str().unwrap(),
+            "--output-format",
+            "fasta",
+        ],
+    );
+    assert!(output.status.success());
+    let fasta = fs::read_to_string(&out).unwrap();
+    assert_eq!(4, fasta.lines().count());
+    assert!(fasta.contains(">first\nACGTTGCAACGTAGCTAGCT\n"));
+    assert!(fasta.contains(">second\nACGTTGCAACGTAGCTAGCA\n"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn compress_and_decompress() {
+    let dir = test_dir("roundtrip");
+    let input = write_input(&dir);
+    let compressed = dir.join("out.rlz");
+    let output = run_cli(
+        &dir,
+        &[
+            "compress",
+            input.to_str().unwrap(),
+            "-c",
+            "ACGT",
+            "-o",
+            compressed.to_str().unwrap(),
+        ],
+    );
+    assert!(output.status.success());
+
+    let restored = dir.join("restored.txt");
+    let output = run_cli(
+        &dir,
+        &[
+            "decompress",
+            compressed.to_str().unwrap(),
+            "-o",
+            restored.to_str().unwrap(),
+        ],
+    );
+    assert!(output.status.success());
+    assert_eq!(
+        fs::read_to_string(&input).unwrap(),
+        fs::read_to_string(&restored).unwrap()
+    );
+
+    // Anything else is not mistaken for compressed data
+    let output = run_cli(
+        &dir,
+        &[
+            "decompress",
+            input.to_str().unwrap(),
+            "-o",
+            restored.to_str().unwrap(),
+        ],
+    );
+    assert!(!output.status.success());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn decompress_restores_a_directory() {
+    let dir = test_dir("roundtrip_dir");
+    let input = write_input_dir(&dir);
+    let compressed = dir.join("out.rlz");
+    let output = run_cli(
+        &dir,
+        &[
+            "compress",
+            input.to_str().unwrap(),
+            "-d",
+            "-c",
+            "ACGT",
+            "-o",
+            compressed.to_str().unwrap(),
+        ],
+    );
+    assert!(output.status.success());
+
+    let restored = dir.join("restored");
+    let output = run_cli(
+        &dir,
+        &[
+            "decompress",
+            compressed.to_str().unwrap(),
+            "-o",
+            restored.to_str().unwrap(),
+        ],
+    );
+    assert!(output.status.success());
+    for name in &["first", "second"] {
+        assert_eq!(
+            fs::read_to_string(input.join(name)).unwrap(),
+            fs::read_to_string(restored.join(name)).unwrap()
+        );
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn reference_streams_a_directory() {
+    let dir = test_dir("reference");
+    let input = write_input_dir(&dir);
+    let reference = dir.join("reference.txt");
+    fs::write(&reference, "ACGTTGCAACGTAGCTAG\n").unwrap();
+    let compressed = dir.join("out.rlz");
+    let output = run_cli(
+        &dir,
+        &[
+            input.to_str().unwrap(),
+            "-d",
+            "-c",
+            "",
+            "-r",
+            reference.to_str().unwrap(),
+            "-o",
+            compressed.to_str().unwrap(),
+        ],
+    );
+    assert!(output.status.success());
+
+    let restored = dir.join("restored");
+    let output = run_cli(
+        &dir,
+        &[
+            "decompress",
+            compressed.to_str().unwrap(),
+            "-o",
+            restored.to_str().unwrap(),
+        ],
+    );
+    assert!(output.status.success());
+    for name in &["first", "second"] {
+        assert_eq!(
+            fs::read_to_string(input.join(name)).unwrap(),
+            fs::read_to_string(restored.join(name)).unwrap()
+        );
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
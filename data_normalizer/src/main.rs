@@ -3,13 +3,14 @@ use std::ffi::OsString;
 use std::fs;
 use std::fs::File;
 use std::io::prelude::*;
-use std::io::BufReader;
+use std::io::{self, BufReader};
 use std::path::Path;
 use std::path::PathBuf;
 
 // CLI tools
 use anyhow::{Context, Result};
 use console::style;
+use flate2::read::GzDecoder;
 use indicatif::{HumanBytes, ProgressBar};
 use structopt::StructOpt;
 
@@ -40,6 +41,10 @@ struct CliInput {
     /// Denotes whether to accept only if a single file with `ext` is there, or if multiple should be accepted and concatenated
     #[structopt(short, long)]
     multiple_files: bool,
+
+    /// Reads every input file as gzip compressed. Files ending in `.gz` (e.g. `.fna.gz`) always are
+    #[structopt(long)]
+    gzip: bool,
 }
 
 impl CliInput {
@@ -50,6 +55,36 @@ impl CliInput {
     }
 }
 
+// Whether the file has the extension, either as is or followed by `.gz`
+fn has_ext(path: &Path, ext: &str) -> bool {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("gz") if ext != "gz" => Path::new(path.file_stem().unwrap())
+            .extension()
+            .is_some_and(|e| e == ext),
+        Some(e) => e == ext,
+        None => false,
+    }
+}
+
+// Opens the file for reading lines, decompressing it on the fly if it is
+// gzip compressed
+fn open_input(path: &Path, gzip: bool) -> io::Result<Box<dyn BufRead>> {
+    let file = File::open(path)?;
+    if gzip || path.extension().is_some_and(|e| e == "gz") {
+        Ok(Box::new(BufReader::new(GzDecoder::new(file))))
+    } else {
+        Ok(Box::new(BufReader::new(file)))
+    }
+}
+
+// Every line of the file, failing on the first one that cannot be read
+// (like in a truncated gzip file) instead of stopping short there
+fn read_lines(path: &Path, gzip: bool) -> Result<Vec<String>> {
+    open_input(path, gzip)
+        .and_then(|reader| reader.lines().collect::<io::Result<_>>())
+        .with_context(|| format!("Could not read file `{}`", path.display()))
+}
+
 fn main() -> Result<()> {
     let args = CliInput::from_args();
 
@@ -99,13 +134,12 @@ fn single_file_impl(args: &CliInput) -> Result<()> {
                 let ext_path = assembly_entry?.path();
                 let ext = ext_path.extension().unwrap();
                 extensions.insert(ext.to_os_string());
-                let ext = ext.to_str().unwrap();
 
-                if found_fna && ext == &args.ext {
+                if found_fna && has_ext(&ext_path, &args.ext) {
                     denied = true;
                 }
 
-                if ext == &args.ext {
+                if has_ext(&ext_path, &args.ext) {
                     found_fna = true;
                 }
             }
@@ -130,34 +164,30 @@ fn single_file_impl(args: &CliInput) -> Result<()> {
         let pb = ProgressBar::new(acceptable_dir.len() as u64);
 
         // Gets the '&args.ext' file
-        fn get_file(p: &PathBuf, ext: &str) -> Option<File> {
-            for entry in fs::read_dir(&p).unwrap() {
+        fn get_file(p: &PathBuf, ext: &str) -> Option<PathBuf> {
+            for entry in fs::read_dir(p).unwrap() {
                 let p = entry.unwrap().path();
-                if p.extension()?.to_str()? == ext {
-                    return Some(File::open(&p).unwrap());
+                if has_ext(&p, ext) {
+                    return Some(p);
                 }
             }
             None
         }
-        acceptable_dir.retain(|dir_entry| {
+        let mut checked_dir = Vec::with_capacity(acceptable_dir.len());
+        for dir_entry in acceptable_dir {
             pb.inc(1);
 
-            let mut delete = false;
-            let file = get_file(&dir_entry, &args.ext).unwrap();
-            // let file = File::open(&ext_path)?;
-            let buf_reader = BufReader::new(file);
-            for line in buf_reader.lines() {
-                if let Ok(s) = line {
-                    if !s.starts_with(&args.pattern) {
-                        if s.to_uppercase().chars().any(|c| !ac.contains(&c)) {
-                            denied_dir.push(dir_entry.clone());
-                            delete = true;
-                        }
-                    }
-                }
+            let path = get_file(&dir_entry, &args.ext).unwrap();
+            let denied = read_lines(&path, args.gzip)?.iter().any(|s| {
+                !s.starts_with(&args.pattern) && s.to_uppercase().chars().any(|c| !ac.contains(&c))
+            });
+            if denied {
+                denied_dir.push(dir_entry);
+            } else {
+                checked_dir.push(dir_entry);
             }
-            !delete
-        });
+        }
+        acceptable_dir = checked_dir;
         pb.finish_and_clear();
     }
 
@@ -190,32 +220,25 @@ fn single_file_impl(args: &CliInput) -> Result<()> {
             .unwrap_or_else(|| panic!("Could not read foldername of `{}`", dir_entry.display()));
         for entry in fs::read_dir(&dir_entry)? {
             let path = entry?.path();
-            if let Some(ext) = path.extension() {
-                let ext = ext.to_str().unwrap();
-                if ext != &args.ext {
-                    continue;
-                }
+            if path.extension().is_some() && !has_ext(&path, &args.ext) {
+                continue;
             }
 
-            let file = File::open(&path)?;
-            let mut contents = match file.metadata() {
+            let mut contents = match fs::metadata(&path) {
                 Ok(d) => String::with_capacity(d.len() as usize),
                 Err(_) => String::new(),
             };
-            let buf_reader = BufReader::new(file);
-            for line in buf_reader.lines() {
-                if let Ok(s) = line {
-                    if !s.starts_with(&args.pattern) {
-                        let formatted_string = s.to_uppercase();
-                        contents.push_str(&formatted_string);
-
-                        formatted_string.chars().for_each(|c| {
-                            // Insert returns a boolean whether it existed
-                            // previously or not, but we just want all characters
-                            // added to the hash set for debugging.
-                            *characters.entry(c).or_insert(0) += 1;
-                        });
-                    }
+            for s in read_lines(&path, args.gzip)? {
+                if !s.starts_with(&args.pattern) {
+                    let formatted_string = s.to_uppercase();
+                    contents.push_str(&formatted_string);
+
+                    formatted_string.chars().for_each(|c| {
+                        // Insert returns a boolean whether it existed
+                        // previously or not, but we just want all characters
+                        // added to the hash set for debugging.
+                        *characters.entry(c).or_insert(0) += 1;
+                    });
                 }
             }
             contents.shrink_to_fit();
@@ -263,9 +286,8 @@ fn multiple_files_impl(args: &CliInput) -> Result<()> {
                 let ext_path = assembly_entry?.path();
                 let ext = ext_path.extension().unwrap();
                 extensions.insert(ext.to_os_string());
-                let ext = ext.to_str().unwrap();
 
-                if ext == &args.ext {
+                if has_ext(&ext_path, &args.ext) {
                     found_ext = true;
                 }
             }
@@ -287,37 +309,37 @@ fn multiple_files_impl(args: &CliInput) -> Result<()> {
 
         let pb = ProgressBar::new(acceptable_dir.len() as u64);
 
-        // Gets the '&args.ext' file
-        fn get_files(p: &PathBuf, ext: &str) -> Vec<File> {
+        // Gets the '&args.ext' files
+        fn get_files(p: &PathBuf, ext: &str) -> Vec<PathBuf> {
             let mut files = vec![];
-            for entry in fs::read_dir(&p).unwrap() {
+            for entry in fs::read_dir(p).unwrap() {
                 let p = entry.unwrap().path();
-                if p.extension().unwrap().to_str().unwrap() == ext {
-                    files.push(File::open(&p).unwrap());
+                if has_ext(&p, ext) {
+                    files.push(p);
                 }
             }
             files
         }
-        acceptable_dir.retain(|dir_entry| {
+        let mut checked_dir = Vec::with_capacity(acceptable_dir.len());
+        for dir_entry in acceptable_dir {
             pb.inc(1);
 
             let mut delete = false;
-            let files = get_files(&dir_entry, &args.ext);
-            for file in files {
-                let buf_reader = BufReader::new(file);
-                for line in buf_reader.lines() {
-                    if let Ok(s) = line {
-                        if !s.starts_with(&args.pattern) {
-                            if s.to_uppercase().chars().any(|c| !ac.contains(&c)) {
-                                // Todo: Can probably early return from this double inner for loop, but oh god the time presssure
-                                delete = true;
-                            }
-                        }
-                    }
+            for path in get_files(&dir_entry, &args.ext) {
+                let lines = read_lines(&path, args.gzip)?;
+                if lines.iter().any(|s| {
+                    !s.starts_with(&args.pattern)
+                        && s.to_uppercase().chars().any(|c| !ac.contains(&c))
+                }) {
+                    delete = true;
+                    break;
                 }
             }
-            !delete
-        });
+            if !delete {
+                checked_dir.push(dir_entry);
+            }
+        }
+        acceptable_dir = checked_dir;
         pb.finish_and_clear();
     }
 
@@ -342,32 +364,25 @@ fn multiple_files_impl(args: &CliInput) -> Result<()> {
         let mut file_content = String::new();
         for entry in fs::read_dir(&dir_entry)? {
             let path = entry?.path();
-            if let Some(ext) = path.extension() {
-                let ext = ext.to_str().unwrap();
-                if ext != &args.ext {
-                    continue;
-                }
+            if path.extension().is_some() && !has_ext(&path, &args.ext) {
+                continue;
             }
 
-            let file = File::open(&path)?;
-            let mut contents = match file.metadata() {
+            let mut contents = match fs::metadata(&path) {
                 Ok(d) => String::with_capacity(d.len() as usize),
                 Err(_) => String::new(),
             };
-            let buf_reader = BufReader::new(file);
-            for line in buf_reader.lines() {
-                if let Ok(s) = line {
-                    if !s.starts_with(&args.pattern) {
-                        let formatted_string = s.to_uppercase();
-                        contents.push_str(&formatted_string);
-
-                        formatted_string.chars().for_each(|c| {
-                            // Insert returns a boolean whether it existed
-                            // previously or not, but we just want all characters
-                            // added to the hash set for debugging.
-                            *characters.entry(c).or_insert(0) += 1;
-                        });
-                    }
+            for s in read_lines(&path, args.gzip)? {
+                if !s.starts_with(&args.pattern) {
+                    let formatted_string = s.to_uppercase();
+                    contents.push_str(&formatted_string);
+
+                    formatted_string.chars().for_each(|c| {
+                        // Insert returns a boolean whether it existed
+                        // previously or not, but we just want all characters
+                        // added to the hash set for debugging.
+                        *characters.entry(c).or_insert(0) += 1;
+                    });
                 }
             }
             contents.shrink_to_fit();
@@ -0,0 +1,36 @@
+// The collection the benches (and the memory tests) are run on. Lives in
+// a directory of its own so cargo does not take it for a bench.
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+const ALPHABET: &[u8] = b"ACGT";
+
+// `count` sequences of length `len`, where every sequence is a copy of the
+// first with `mutation_rate` of the bases substituted, and a tenth of
+// that deleted or followed by an inserted base. At 1% this is roughly what
+// a collection of genomes of one species looks like. The RNG is seeded,
+// so it is the same collection on every run.
+pub fn dna_collection(count: usize, len: usize, mutation_rate: f64) -> Vec<String> {
+    let mut rng = StdRng::seed_from_u64(42);
+    let random_base = |rng: &mut StdRng| ALPHABET[rng.gen_range(0, ALPHABET.len())];
+    let base: Vec<u8> = (0..len).map(|_| random_base(&mut rng)).collect();
+
+    let mut sequences = vec![String::from_utf8(base.clone()).unwrap()];
+    for _ in 1..count {
+        let mut mutated = Vec::with_capacity(len);
+        for &b in base.iter() {
+            let r: f64 = rng.gen();
+            if r < mutation_rate {
+                mutated.push(random_base(&mut rng));
+            } else if r < mutation_rate * 1.1 {
+                // Deleted
+            } else if r < mutation_rate * 1.2 {
+                mutated.extend_from_slice(&[b, random_base(&mut rng)]);
+            } else {
+                mutated.push(b);
+            }
+        }
+        sequences.push(String::from_utf8(mutated).unwrap());
+    }
+    sequences
+}
@@ -0,0 +1,79 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use relative_lempel_ziv::options::EncodeOptions;
+use relative_lempel_ziv::RelativeLempelZiv;
+use std::io::Write;
+
+mod common;
+
+fn gzip_size(strings: &[String]) -> usize {
+    let mut encoder = GzEncoder::new(vec![], Compression::default());
+    for s in strings {
+        encoder.write_all(s.as_bytes()).unwrap();
+    }
+    encoder.finish().unwrap().len()
+}
+
+fn rlz_against_gzip(c: &mut Criterion) {
+    let strings = common::dna_collection(20, 20_000, 0.01);
+    let options = EncodeOptions {
+        show_progress: false,
+        ..Default::default()
+    };
+    let encode = || {
+        RelativeLempelZiv::<u32>::encode_with_options(
+            &strings,
+            None,
+            None as Option<&str>,
+            &options,
+        )
+    };
+
+    // The sizes are the same on every run, so they are printed
+    // once instead of being measured
+    let raw_size: usize = strings.iter().map(|s| s.len()).sum();
+    let rlz = encode();
+    let memory = rlz.memory_footprint(Some(raw_size));
+    let gzip = gzip_size(&strings);
+    eprintln!(
+        "Raw: {} bytes, rlz: {} bytes (rate {:.4}), {} without random access (rate {:.4}), gzip: {} bytes (rate {:.4})",
+        raw_size,
+        memory.total_memory(),
+        memory.compression_rate().unwrap(),
+        memory.compressed_size(),
+        memory.compression_rate_without_ra().unwrap(),
+        gzip,
+        gzip as f64 / raw_size as f64
+    );
+
+    let mut group = c.benchmark_group("dna collection");
+    group.sample_size(10);
+    group.bench_function("rlz encode", |b| b.iter(encode));
+    group.bench_function("rlz decode", |b| b.iter(|| rlz.decode()));
+    group.bench_function("rlz par decode", |b| b.iter(|| rlz.par_decode()));
+    group.bench_function("gzip", |b| b.iter(|| gzip_size(&strings)));
+
+    // The same positions every run, spread over every string
+    let mut rng = StdRng::seed_from_u64(42);
+    let positions: Vec<(u32, u32)> = (0..1_000)
+        .map(|_| {
+            let i = rng.gen_range(0, strings.len());
+            (i as u32, rng.gen_range(0, strings[i].len()) as u32)
+        })
+        .collect();
+    group.bench_function("rlz random access", |b| {
+        b.iter(|| {
+            positions
+                .iter()
+                .map(|&(i, x)| rlz.random_access(i, x) as usize)
+                .sum::<usize>()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, rlz_against_gzip);
+criterion_main!(benches);
@@ -0,0 +1,91 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use relative_lempel_ziv::cache::SubstringCache;
+use relative_lempel_ziv::options::EncodeOptions;
+use relative_lempel_ziv::RelativeLempelZiv;
+
+mod common;
+
+// `distinct` mutated sequences, repeated `copies` times. The first
+// sequence is used as the reference.
+fn duplicated_collection(distinct: usize, copies: usize, len: usize) -> Vec<String> {
+    let sequences = common::dna_collection(distinct, len, 0.05);
+    let mut collection = Vec::with_capacity(distinct * copies);
+    for _ in 0..copies {
+        collection.extend(sequences.iter().cloned());
+    }
+    collection
+}
+
+fn substring_cache(c: &mut Criterion) {
+    let strings = duplicated_collection(10, 20, 4_000);
+
+    let cache = SubstringCache::new(1 << 14, 32);
+    RelativeLempelZiv::<u32>::encode_with_cache(&strings, None, None as Option<&str>, &cache);
+    eprintln!(
+        "Substring cache hit rate: {:.2} ({} hits, {} misses)",
+        cache.hit_rate(),
+        cache.hits(),
+        cache.misses()
+    );
+
+    let mut group = c.benchmark_group("duplicated collection");
+    group.sample_size(10);
+    group.bench_function("uncached", |b| {
+        b.iter(|| RelativeLempelZiv::<u32>::encode(&strings, None, None as Option<&str>))
+    });
+    group.bench_function("cached", |b| {
+        b.iter(|| {
+            let cache = SubstringCache::new(1 << 14, 32);
+            RelativeLempelZiv::<u32>::encode_with_cache(
+                &strings,
+                None,
+                None as Option<&str>,
+                &cache,
+            )
+        })
+    });
+    group.finish();
+}
+
+// Criterion runs every iteration in the same process, so the pool is
+// already warm here and this only shows the per-call cost of each. The
+// threshold mostly pays off in short-lived processes, where the serial
+// encode never has to start the pool at all.
+fn tiny_collection(c: &mut Criterion) {
+    let strings = duplicated_collection(8, 1, 200);
+    let serial = EncodeOptions {
+        show_progress: false,
+        ..Default::default()
+    };
+    let parallel = EncodeOptions {
+        parallel_threshold: 0,
+        show_progress: false,
+        ..Default::default()
+    };
+
+    let mut group = c.benchmark_group("tiny collection");
+    group.bench_function("serial threshold", |b| {
+        b.iter(|| {
+            RelativeLempelZiv::<u32>::encode_with_options(
+                &strings,
+                None,
+                None as Option<&str>,
+                &serial,
+            )
+        })
+    });
+    group.bench_function("always parallel", |b| {
+        b.iter(|| {
+            RelativeLempelZiv::<u32>::encode_with_options(
+                &strings,
+                None,
+                None as Option<&str>,
+                &parallel,
+            )
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, substring_cache, tiny_collection);
+criterion_main!(benches);
@@ -2,7 +2,9 @@
 // Compressed rate (compressized size and raw size)
 // Assembly name
 
+use std::borrow::Cow;
 use std::fmt;
+use std::io::{self, Write};
 
 pub struct AnalysisResult {
   pub list: Vec<Analysis>,
@@ -25,6 +27,29 @@ impl Analysis {
     }
   }
 
+  // Number of factors in the factorization
+  pub fn len(&self) -> usize {
+    self.len
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+
+  // Bytes used by the factorization
+  pub fn c_size(&self) -> usize {
+    self.c_size
+  }
+
+  // Bytes of the raw string
+  pub fn r_size(&self) -> usize {
+    self.r_size
+  }
+
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+
   pub fn compressed_rate(&self) -> f64 {
     self.c_size as f64 / self.r_size as f64
   }
@@ -54,18 +79,177 @@ impl AnalysisResult {
     AnalysisResult { list }
   }
 
+  // Sorted from the worst compressed string to the best
+  pub fn list(&self) -> &[Analysis] {
+    &self.list
+  }
+
   // Reference is valid as long as self is valid (the list is ummutable after initialization)
   // Assumes the vector is sorted from init (which it should be since it's sorted on init and immutable)
-  pub fn worst_reference_string<'s>(&'s self) -> &'s str {
-    &self.list[0].name
+  // Skips the strings that are already part of the reference, since adding them again
+  // only makes the reference bigger. None if every string is already in there.
+  pub fn worst_reference_string(&self, references: &[String]) -> Option<&str> {
+    self
+      .list
+      .iter()
+      .map(|a| a.name.as_str())
+      .find(|name| !references.iter().any(|r| r == name))
+  }
+
+  // The counterpart of `worst_reference_string`, the best compressed
+  // string that is not part of the reference
+  pub fn best_reference_string(&self, references: &[String]) -> Option<&str> {
+    self
+      .list
+      .iter()
+      .rev()
+      .map(|a| a.name.as_str())
+      .find(|name| !references.iter().any(|r| r == name))
+  }
+
+  pub fn total_compressed_size(&self) -> usize {
+    self.list.iter().map(|a| a.c_size).sum()
+  }
+
+  pub fn total_raw_size(&self) -> usize {
+    self.list.iter().map(|a| a.r_size).sum()
+  }
+
+  // The compression rate of the collection as a whole, which weighs the
+  // strings by their size unlike the mean of the per-string rates. None
+  // if there is nothing to compress.
+  pub fn overall_compression_rate(&self) -> Option<f64> {
+    match self.total_raw_size() {
+      0 => None,
+      raw => Some(self.total_compressed_size() as f64 / raw as f64),
+    }
+  }
+
+  // The mean number of factors per string, None for an empty result
+  pub fn mean_factorization_length(&self) -> Option<f64> {
+    match self.list.len() {
+      0 => None,
+      n => Some(self.list.iter().map(|a| a.len).sum::<usize>() as f64 / n as f64),
+    }
+  }
+}
+
+impl AnalysisResult {
+  // Writes the analyses as CSV with a header row, in the same order
+  // and with the same columns as `Display`
+  pub fn to_csv<W: Write>(&self, w: &mut W) -> io::Result<()> {
+    self.write_table(w, ',')
+  }
+
+  // Same as `to_csv`, but tab separated
+  pub fn to_tsv<W: Write>(&self, w: &mut W) -> io::Result<()> {
+    self.write_table(w, '\t')
+  }
+
+  fn write_table<W: Write>(&self, w: &mut W, sep: char) -> io::Result<()> {
+    writeln!(
+      w,
+      "compression_rate{0}compressed_size{0}raw_size{0}factorizations{0}name",
+      sep
+    )?;
+    for a in self.list.iter() {
+      writeln!(
+        w,
+        "{:.4}{5}{}{5}{}{5}{}{5}{}",
+        a.compressed_rate(),
+        a.c_size,
+        a.r_size,
+        a.len,
+        quote(&a.name, sep),
+        sep
+      )?;
+    }
+    Ok(())
+  }
+}
+
+// Quotes the field if it contains the separator, a quote or a line
+// break, doubling the quotes inside it (RFC 4180)
+fn quote(field: &str, sep: char) -> Cow<'_, str> {
+  if field.contains([sep, '"', '\n', '\r']) {
+    Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+  } else {
+    Cow::Borrowed(field)
   }
 }
 
 impl fmt::Display for AnalysisResult {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     for s in self.list.iter() {
-      write!(f, "{}\n", s)?;
+      writeln!(f, "{}", s)?;
     }
     Ok(())
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn worst_reference_string_skips_references() {
+    let result = AnalysisResult::new(vec![
+      Analysis::new(1, 16, 8, "b"),
+      Analysis::new(1, 16, 1, "a"),
+      Analysis::new(1, 16, 16, "c"),
+    ]);
+    assert_eq!(Some("a"), result.worst_reference_string(&[]));
+    assert_eq!(
+      Some("b"),
+      result.worst_reference_string(&[String::from("a")])
+    );
+
+    let all = vec![String::from("a"), String::from("b"), String::from("c")];
+    assert_eq!(None, result.worst_reference_string(&all));
+  }
+
+  #[test]
+  fn totals() {
+    let result = AnalysisResult::new(vec![
+      Analysis::new(1, 16, 8, "b"),
+      Analysis::new(4, 16, 1, "a"),
+      Analysis::new(1, 16, 16, "c"),
+    ]);
+    assert_eq!(48, result.total_compressed_size());
+    assert_eq!(25, result.total_raw_size());
+    assert_eq!(Some(48.0 / 25.0), result.overall_compression_rate());
+    assert_eq!(Some(2.0), result.mean_factorization_length());
+    assert_eq!(Some("c"), result.best_reference_string(&[]));
+    assert_eq!(
+      Some("b"),
+      result.best_reference_string(&[String::from("c")])
+    );
+
+    let empty = AnalysisResult::new(vec![]);
+    assert_eq!(None, empty.overall_compression_rate());
+    assert_eq!(None, empty.mean_factorization_length());
+    assert_eq!(None, empty.best_reference_string(&[]));
+  }
+
+  #[test]
+  fn to_csv_quotes_names() {
+    let result = AnalysisResult::new(vec![
+      Analysis::new(2, 32, 16, "plain"),
+      Analysis::new(1, 16, 16, "chr1, \"alt\""),
+    ]);
+    let mut csv = Vec::new();
+    result.to_csv(&mut csv).unwrap();
+    assert_eq!(
+      "compression_rate,compressed_size,raw_size,factorizations,name\n\
+       2.0000,32,16,2,plain\n\
+       1.0000,16,16,1,\"chr1, \"\"alt\"\"\"\n",
+      String::from_utf8(csv).unwrap()
+    );
+
+    let mut tsv = Vec::new();
+    result.to_tsv(&mut tsv).unwrap();
+    assert!(String::from_utf8(tsv)
+      .unwrap()
+      .ends_with("1.0000\t16\t16\t1\t\"chr1, \"\"alt\"\"\"\n"));
+  }
+}
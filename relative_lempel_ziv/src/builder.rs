@@ -0,0 +1,215 @@
+// Builder for the encode configuration, for when the positional
+// arguments of `encode` and friends get unwieldy. Every setting starts
+// out as what `encode` does without it, so
+//
+//   RlzBuilder::new().encode(&strings)
+//
+// is the same as `RelativeLempelZiv::encode(&strings, None, None)`.
+use std::convert::{TryFrom, TryInto};
+use std::fmt;
+
+use super::error::EncodeError;
+use super::options::EncodeOptions;
+use super::progress::{Progress, ProgressCallback};
+use super::{internal_try_encode, str_bytes, RelativeLempelZiv};
+
+#[derive(Debug, Clone, Default)]
+pub struct RlzBuilder {
+  reference_indices: Option<Vec<usize>>,
+  required_chars: Option<String>,
+  options: EncodeOptions,
+}
+
+impl RlzBuilder {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  // The strings that are concatenated into the reference, the first
+  // one if not given
+  pub fn reference_indices(mut self, indices: Vec<usize>) -> Self {
+    self.reference_indices = Some(indices);
+    self
+  }
+
+  // Chars that are appended to the reference, so they are there for
+  // strings pushed later on
+  pub fn required_chars<T: Into<String>>(mut self, chars: T) -> Self {
+    self.required_chars = Some(chars.into());
+    self
+  }
+
+  // No progress on stderr
+  pub fn quiet(mut self) -> Self {
+    self.options.show_progress = false;
+    self
+  }
+
+  // Calls f with the progress of the encoding instead of showing it on
+  // stderr, see `progress::Progress`
+  pub fn on_progress<F: FnMut(Progress) + Send + 'static>(mut self, f: F) -> Self {
+    self.options.progress = Some(ProgressCallback::new(f));
+    self
+  }
+
+  // Encodes with at most this many threads, see `EncodeOptions::threads`
+  pub fn threads(mut self, threads: usize) -> Self {
+    self.options.threads = Some(threads);
+    self
+  }
+
+  // Stores strings raw when that is smaller, see `EncodeOptions::store_raw`
+  pub fn raw_fallback(mut self) -> Self {
+    self.options.store_raw = true;
+    self
+  }
+
+  // Stores the runs of N as run factors, see `EncodeOptions::runs`
+  pub fn collapse_runs(self) -> Self {
+    self.collapse_runs_of(b'N')
+  }
+
+  // Same as `collapse_runs`, for runs of any byte
+  pub fn collapse_runs_of(mut self, byte: u8) -> Self {
+    self.options.runs = Some(byte);
+    self
+  }
+
+  // Stores short factors as literal bytes, see
+  // `EncodeOptions::min_factor_length`
+  pub fn min_factor_length(mut self, len: usize) -> Self {
+    self.options.min_factor_length = len;
+    self
+  }
+
+  // Encodes identical strings only once, see `EncodeOptions::dedup`
+  pub fn dedup(mut self) -> Self {
+    self.options.dedup = true;
+    self
+  }
+
+  // Also matches against the reverse complement of the reference, see
+  // `EncodeOptions::reverse_complement`
+  pub fn reverse_complement(mut self) -> Self {
+    self.options.reverse_complement = true;
+    self
+  }
+
+  // Everything else of `EncodeOptions` that has no method of its own.
+  // Replaces what the other methods set.
+  pub fn options(mut self, options: EncodeOptions) -> Self {
+    self.options = options;
+    self
+  }
+
+  // Same as `try_encode`, but panics on the error
+  pub fn encode<U, T>(&self, strings: &[T]) -> RelativeLempelZiv<U>
+  where
+    U: Copy + TryFrom<usize> + TryInto<usize> + Send,
+    <U as TryFrom<usize>>::Error: fmt::Debug,
+    <U as TryInto<usize>>::Error: fmt::Debug,
+    T: AsRef<str> + Sync,
+  {
+    self.try_encode(strings).unwrap_or_else(|e| panic!("{}", e))
+  }
+
+  // Encodes the strings with the index width U, which is usually
+  // inferred from where the result goes
+  pub fn try_encode<U, T>(&self, strings: &[T]) -> Result<RelativeLempelZiv<U>, EncodeError>
+  where
+    U: Copy + TryFrom<usize> + TryInto<usize> + Send,
+    <U as TryFrom<usize>>::Error: fmt::Debug,
+    <U as TryInto<usize>>::Error: fmt::Debug,
+    T: AsRef<str> + Sync,
+  {
+    internal_try_encode(
+      &str_bytes(strings),
+      self.reference_indices.clone(),
+      self.required_chars.as_ref(),
+      None,
+      &self.options,
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::FactorKind;
+
+  #[test]
+  fn defaults_match_encode() {
+    let test_data = vec!["ACGTTGCA", "TTGCAACG", "GCAX"];
+    let built: RelativeLempelZiv<u8> = RlzBuilder::new().quiet().encode(&test_data);
+    let encoded = RelativeLempelZiv::<u8>::encode(&test_data, None, None as Option<&str>);
+    assert_eq!(encoded.base_data, built.base_data);
+    assert_eq!(encoded.decode(), built.decode());
+  }
+
+  #[test]
+  fn every_setting() {
+    let test_data = vec!["ACGTTGCA", "TTGCAACG", "AZCYGXTW"];
+    let rlz: RelativeLempelZiv<u8> = RlzBuilder::new()
+      .reference_indices(vec![1, 0])
+      .required_chars("N")
+      .quiet()
+      .raw_fallback()
+      .encode(&test_data);
+
+    assert_eq!(b"TTGCAACGACGTTGCAN".to_vec(), rlz.base_data[..17].to_vec());
+    assert_eq!(FactorKind::StoredRaw, rlz.data[2][0].kind);
+    assert_eq!(test_data, rlz.decode());
+  }
+
+  #[test]
+  fn on_progress() {
+    use crate::progress::Phase;
+    use std::sync::{Arc, Mutex};
+
+    let test_data = vec!["ACGTTGCA", "TTGCAACG", "GCAX", "ACGT"];
+    let reports = Arc::new(Mutex::new(vec![]));
+    let sink = reports.clone();
+    let rlz: RelativeLempelZiv<u8> = RlzBuilder::new()
+      .on_progress(move |p| sink.lock().unwrap().push(p))
+      .encode(&test_data);
+    assert_eq!(test_data, rlz.decode());
+
+    let reports = reports.lock().unwrap();
+    let phases: Vec<Phase> = reports.iter().map(|p| p.phase).collect();
+    assert_eq!(
+      vec![Phase::FindingBase, Phase::BuildingTree],
+      phases[..2].to_vec()
+    );
+    assert!(phases[2..].iter().all(|&p| p == Phase::Encoding));
+    let fractions: Vec<f64> = reports[2..].iter().map(|p| p.fraction).collect();
+    assert_eq!(vec![0.0, 0.25, 0.5, 0.75, 1.0], fractions);
+  }
+
+  #[test]
+  fn try_encode_too_large() {
+    let test_data = vec!["A".repeat(300)];
+    let result = RlzBuilder::new().quiet().try_encode::<u8, _>(&test_data);
+    assert!(matches!(
+      result,
+      Err(EncodeError::ReferenceTooLarge { len: 300, .. })
+    ));
+  }
+
+  #[test]
+  fn try_encode_too_large_after_appending() {
+    // The reference fits in u8, but every "AC" is its own factor, so the
+    // string ends up appended to the base and pushes it past 255
+    let reference = format!("{}{}", "A".repeat(100), "C".repeat(100));
+    let test_data = vec![reference, "AC".repeat(40)];
+    for builder in [
+      RlzBuilder::new().quiet().raw_fallback(),
+      RlzBuilder::new().quiet().min_factor_length(8),
+    ] {
+      assert_eq!(
+        Err(EncodeError::ReferenceTooLarge { len: 280, max: 255 }),
+        builder.try_encode::<u8, _>(&test_data)
+      );
+      assert!(builder.try_encode::<u16, _>(&test_data).is_ok());
+    }
+  }
+}
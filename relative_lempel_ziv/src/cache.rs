@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use suffix_tree::SuffixTree;
+
+// Cache of `longest_substring` results for collections with a lot of
+// identical content, where the same tree walks happen over and over.
+//
+// The key is the first `key_len` bytes of the query. The walk down the
+// tree only ever looks at the bytes it matched plus the one that did
+// not match, so a result is only stored when that is within the key
+// (or the query is shorter than the key, so no other query shares it),
+// which makes it valid for any other query starting with the same bytes.
+// A query of exactly `key_len` bytes that matched in full is not stored,
+// as longer queries with the same key could match further.
+pub struct SubstringCache {
+  inner: Mutex<LruCache>,
+  key_len: usize,
+  hits: AtomicUsize,
+  misses: AtomicUsize,
+}
+
+impl SubstringCache {
+  pub fn new(capacity: usize, key_len: usize) -> Self {
+    SubstringCache {
+      inner: Mutex::new(LruCache::new(capacity)),
+      key_len,
+      hits: AtomicUsize::new(0),
+      misses: AtomicUsize::new(0),
+    }
+  }
+
+  pub fn hits(&self) -> usize {
+    self.hits.load(Ordering::Relaxed)
+  }
+
+  pub fn misses(&self) -> usize {
+    self.misses.load(Ordering::Relaxed)
+  }
+
+  pub fn hit_rate(&self) -> f64 {
+    let lookups = self.hits() + self.misses();
+    if lookups == 0 {
+      return 0.0;
+    }
+    self.hits() as f64 / lookups as f64
+  }
+
+  pub(crate) fn longest_substring(&self, st: &SuffixTree, bytes: &[u8]) -> Option<(usize, usize)> {
+    let key = &bytes[..self.key_len.min(bytes.len())];
+    if let Some(result) = self.inner.lock().unwrap().get(key) {
+      self.hits.fetch_add(1, Ordering::Relaxed);
+      return result;
+    }
+    self.misses.fetch_add(1, Ordering::Relaxed);
+
+    let result = st.longest_substring(bytes);
+    let matched = result.map_or(0, |(start, end)| end - start);
+    if matched < key.len() || bytes.len() < self.key_len {
+      self.inner.lock().unwrap().insert(key.to_vec(), result);
+    }
+    result
+  }
+}
+
+type Entry = Option<(usize, usize)>;
+
+// A plain least recently used cache, where the entries are kept in a
+// doubly linked list (via indices into `slots`) ordered by last use.
+struct LruCache {
+  capacity: usize,
+  map: HashMap<Vec<u8>, usize>,
+  slots: Vec<Slot>,
+  // Most and least recently used slots
+  head: Option<usize>,
+  tail: Option<usize>,
+}
+
+struct Slot {
+  key: Vec<u8>,
+  value: Entry,
+  prev: Option<usize>,
+  next: Option<usize>,
+}
+
+impl LruCache {
+  fn new(capacity: usize) -> Self {
+    LruCache {
+      capacity,
+      map: HashMap::with_capacity(capacity),
+      slots: Vec::with_capacity(capacity),
+      head: None,
+      tail: None,
+    }
+  }
+
+  fn get(&mut self, key: &[u8]) -> Option<Entry> {
+    let slot = *self.map.get(key)?;
+    self.unlink(slot);
+    self.push_front(slot);
+    Some(self.slots[slot].value)
+  }
+
+  fn insert(&mut self, key: Vec<u8>, value: Entry) {
+    if self.capacity == 0 {
+      return;
+    }
+    if let Some(&slot) = self.map.get(&key) {
+      self.slots[slot].value = value;
+      self.unlink(slot);
+      self.push_front(slot);
+      return;
+    }
+
+    let slot = if self.slots.len() < self.capacity {
+      self.slots.push(Slot {
+        key: key.clone(),
+        value,
+        prev: None,
+        next: None,
+      });
+      self.slots.len() - 1
+    } else {
+      // Reuses the least recently used slot for the new entry
+      let slot = self.tail.unwrap();
+      self.unlink(slot);
+      let old_key = std::mem::replace(&mut self.slots[slot].key, key.clone());
+      self.map.remove(&old_key);
+      self.slots[slot].value = value;
+      slot
+    };
+    self.map.insert(key, slot);
+    self.push_front(slot);
+  }
+
+  fn unlink(&mut self, slot: usize) {
+    let (prev, next) = (self.slots[slot].prev, self.slots[slot].next);
+    match prev {
+      Some(p) => self.slots[p].next = next,
+      None => self.head = next,
+    }
+    match next {
+      Some(n) => self.slots[n].prev = prev,
+      None => self.tail = prev,
+    }
+  }
+
+  fn push_front(&mut self, slot: usize) {
+    self.slots[slot].prev = None;
+    self.slots[slot].next = self.head;
+    if let Some(h) = self.head {
+      self.slots[h].prev = Some(slot);
+    }
+    self.head = Some(slot);
+    if self.tail.is_none() {
+      self.tail = Some(slot);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn lru_evicts_least_recently_used() {
+    let mut lru = LruCache::new(2);
+    lru.insert(b"a".to_vec(), Some((0, 1)));
+    lru.insert(b"b".to_vec(), Some((1, 2)));
+    assert_eq!(Some(Some((0, 1))), lru.get(b"a"));
+
+    // "b" is now the least recently used, so it is evicted
+    lru.insert(b"c".to_vec(), None);
+    assert_eq!(None, lru.get(b"b"));
+    assert_eq!(Some(Some((0, 1))), lru.get(b"a"));
+    assert_eq!(Some(None), lru.get(b"c"));
+  }
+
+  #[test]
+  fn only_caches_results_decided_by_the_key() {
+    let st = SuffixTree::new("banana");
+    let cache = SubstringCache::new(8, 2);
+
+    // The match "ban" is longer than the key "ba", so caching it
+    // would give a wrong answer for "bax"
+    assert_eq!(Some((0, 3)), cache.longest_substring(&st, b"banx"));
+    assert_eq!(Some((0, 2)), cache.longest_substring(&st, b"bax"));
+    assert_eq!(0, cache.hits());
+
+    // "nx" only matched "n", which the key decides
+    assert_eq!(Some((2, 3)), cache.longest_substring(&st, b"nxy"));
+    assert_eq!(Some((2, 3)), cache.longest_substring(&st, b"nxz"));
+    assert_eq!(1, cache.hits());
+  }
+}
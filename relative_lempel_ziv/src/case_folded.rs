@@ -0,0 +1,187 @@
+// Encoding of text where the case matters, but where the collection
+// would otherwise factorize much better in a single case. The strings
+// are upper-cased and encoded as usual, so the base data only has one
+// case, and which bytes were lowercase is kept in a bitmap per string.
+// Decoding lowercases those bytes again, so nothing is lost.
+//
+// Only ASCII letters are folded, which keeps UTF-8 strings valid, and
+// a string without lowercase letters has an empty bitmap.
+use serde::{Deserialize, Serialize};
+use std::convert::{TryFrom, TryInto};
+use std::fmt;
+use std::mem;
+
+use super::error::DecodeError;
+use super::memory_usage::MemoryUsage;
+use super::options::EncodeOptions;
+use super::{internal_decode_single_bytes, internal_encode, internal_memory_footprint};
+use super::{internal_random_access, RelativeLempelZiv};
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct CaseFolded<U> {
+  rlz: RelativeLempelZiv<U>,
+  // Bit x of the i'th bitmap is set when byte x of the i'th string was
+  // lowercase, only up to the word of the last one
+  masks: Vec<Vec<u64>>,
+}
+
+impl<U> CaseFolded<U>
+where
+  U: Copy + TryFrom<usize> + TryInto<usize> + Send,
+  <U as TryFrom<usize>>::Error: fmt::Debug,
+  <U as TryInto<usize>>::Error: fmt::Debug,
+{
+  // Same arguments as `RelativeLempelZiv::encode`, where the chars are
+  // upper-cased as well
+  pub fn encode<T: AsRef<str> + Sync>(
+    strings: &[T],
+    n: Option<Vec<usize>>,
+    chars: Option<impl AsRef<str>>,
+  ) -> Self {
+    let folded: Vec<Vec<u8>> = strings
+      .iter()
+      .map(|s| s.as_ref().as_bytes().to_ascii_uppercase())
+      .collect();
+    let masks = strings
+      .iter()
+      .map(|s| case_mask(s.as_ref().as_bytes()))
+      .collect();
+    let chars = chars.map(|c| c.as_ref().to_ascii_uppercase());
+    let rlz = internal_encode(&folded, n, chars, None, &EncodeOptions::default());
+    CaseFolded { rlz, masks }
+  }
+}
+
+impl<U> CaseFolded<U>
+where
+  U: Copy + Ord + TryInto<usize>,
+  <U as TryInto<usize>>::Error: fmt::Debug,
+{
+  pub fn decode(&self) -> Vec<String> {
+    self.try_decode().unwrap_or_else(|e| panic!("{}", e))
+  }
+
+  pub fn try_decode(&self) -> Result<Vec<String>, DecodeError> {
+    (0..self.len())
+      .map(|i| {
+        let bytes = self.decode_single_bytes(i)?;
+        String::from_utf8(bytes).map_err(|e| DecodeError::InvalidUtf8 {
+          string: i,
+          offset: e.utf8_error().valid_up_to(),
+        })
+      })
+      .collect()
+  }
+
+  fn decode_single_bytes(&self, i: usize) -> Result<Vec<u8>, DecodeError> {
+    let mut bytes = internal_decode_single_bytes(&self.rlz, i)?;
+    for (x, b) in bytes.iter_mut().enumerate() {
+      if is_set(&self.masks[i], x) {
+        b.make_ascii_lowercase();
+      }
+    }
+    Ok(bytes)
+  }
+
+  // Gets the x'th byte of the i'th string in its original case
+  pub fn random_access(&self, i: U, x: U) -> u8 {
+    let b = internal_random_access(&self.rlz, i, x);
+    let (i, x): (usize, usize) = (i.try_into().unwrap(), x.try_into().unwrap());
+    if is_set(&self.masks[i], x) {
+      b.to_ascii_lowercase()
+    } else {
+      b
+    }
+  }
+
+  pub fn len(&self) -> usize {
+    self.masks.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.masks.is_empty()
+  }
+
+  // The encoding of the upper-cased strings
+  pub fn rlz(&self) -> &RelativeLempelZiv<U> {
+    &self.rlz
+  }
+
+  // The bitmaps count towards the factorizations
+  pub fn memory_footprint(&self, total_size: Option<usize>) -> MemoryUsage {
+    let rlz = internal_memory_footprint(&self.rlz, total_size);
+    let masks: usize = self
+      .masks
+      .iter()
+      .map(|m| mem::size_of::<Vec<u64>>() + m.capacity() * mem::size_of::<u64>())
+      .sum();
+    MemoryUsage::new(
+      rlz.reference_size(),
+      rlz.factorizations_size() + masks,
+      rlz.random_access_size(),
+      total_size,
+    )
+  }
+}
+
+fn case_mask(bytes: &[u8]) -> Vec<u64> {
+  let mut mask = vec![];
+  for (x, b) in bytes.iter().enumerate() {
+    if b.is_ascii_lowercase() {
+      mask.resize(x / 64 + 1, 0);
+      mask[x / 64] |= 1 << (x % 64);
+    }
+  }
+  mask
+}
+
+fn is_set(mask: &[u64], x: usize) -> bool {
+  mask.get(x / 64).is_some_and(|w| w & (1 << (x % 64)) != 0)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use quickcheck::TestResult;
+
+  #[test]
+  fn mixed_case_round_trip() {
+    let test_data = vec![
+      "The Quick Brown Fox",
+      "the quick brown fox",
+      "THE QUICK",
+      "ünïcode Ok",
+    ];
+    let folded =
+      RelativeLempelZiv::<u8>::encode_case_folded(&test_data, None, None as Option<&str>);
+    assert_eq!(test_data, folded.decode());
+    assert_eq!(b'q', folded.random_access(1, 4));
+    assert_eq!(b'Q', folded.random_access(2, 4));
+
+    // The reference is the first string in upper case only, which the
+    // second string is a single factor of
+    assert_eq!(
+      b"THE QUICK BROWN FOX".to_vec(),
+      folded.rlz().base_data[..19].to_vec()
+    );
+    assert_eq!(1, folded.rlz().data[1].len());
+    assert!(folded.masks[2].is_empty());
+  }
+
+  #[test]
+  fn masks_past_a_word() {
+    let s = format!("{}{}", "A".repeat(70), "b");
+    let mask = case_mask(s.as_bytes());
+    assert_eq!(vec![0, 1 << 6], mask);
+    assert!(is_set(&mask, 70) && !is_set(&mask, 69) && !is_set(&mask, 200));
+  }
+
+  #[quickcheck]
+  fn quickcheck_case_folded_round_trip(xs: Vec<String>) -> TestResult {
+    if xs.is_empty() {
+      return TestResult::discard();
+    }
+    let folded = CaseFolded::<u32>::encode(&xs, None, None as Option<&str>);
+    TestResult::from_bool(folded.decode() == xs)
+  }
+}
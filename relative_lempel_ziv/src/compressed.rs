@@ -0,0 +1,142 @@
+// A collection where the base data is kept deflated, for when the
+// reference takes up most of the memory. The base data is inflated the
+// first time a string is decoded or accessed, and is then kept around,
+// so only the first access pays for it.
+//
+// The factors are moved over to the inflated collection rather than
+// copied, so they are never in memory twice.
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use std::convert::TryInto;
+use std::fmt;
+use std::io::{Read, Write};
+use std::sync::{Mutex, OnceLock};
+
+use super::error::DecodeError;
+use super::memory_usage::MemoryUsage;
+use super::{
+  internal_decode, internal_memory_footprint, internal_random_access, RelativeLempelZiv,
+};
+
+pub struct CompressedReference<U> {
+  deflated: Vec<u8>,
+  // The collection with an empty base data, until it is inflated
+  deflated_rlz: Mutex<Option<RelativeLempelZiv<U>>>,
+  inflated_rlz: OnceLock<RelativeLempelZiv<U>>,
+}
+
+impl<U> CompressedReference<U> {
+  pub fn new(mut rlz: RelativeLempelZiv<U>) -> Self {
+    // Writing to a Vec does not fail
+    let mut encoder = DeflateEncoder::new(vec![], Compression::best());
+    encoder.write_all(&rlz.base_data).unwrap();
+    let deflated = encoder.finish().unwrap();
+
+    // The suffix tree is only there for pushing, which needs the base
+    rlz.base_data = vec![];
+    rlz.index = None;
+    CompressedReference {
+      deflated,
+      deflated_rlz: Mutex::new(Some(rlz)),
+      inflated_rlz: OnceLock::new(),
+    }
+  }
+
+  // The collection with the base data, inflating it if it is not yet
+  pub fn rlz(&self) -> &RelativeLempelZiv<U> {
+    self.inflated_rlz.get_or_init(|| {
+      let mut rlz = self.deflated_rlz.lock().unwrap().take().unwrap();
+      DeflateDecoder::new(&self.deflated[..])
+        .read_to_end(&mut rlz.base_data)
+        .expect("The base data was deflated by `new`");
+      rlz
+    })
+  }
+
+  pub fn is_inflated(&self) -> bool {
+    self.inflated_rlz.get().is_some()
+  }
+
+  pub fn compressed_reference_size(&self) -> usize {
+    self.deflated.len()
+  }
+
+  pub fn into_inner(self) -> RelativeLempelZiv<U> {
+    self.rlz();
+    self.inflated_rlz.into_inner().unwrap()
+  }
+}
+
+impl<U> CompressedReference<U>
+where
+  U: Copy + Ord + TryInto<usize>,
+  <U as TryInto<usize>>::Error: fmt::Debug,
+{
+  pub fn decode(&self) -> Vec<String> {
+    self.try_decode().unwrap_or_else(|e| panic!("{}", e))
+  }
+
+  pub fn try_decode(&self) -> Result<Vec<String>, DecodeError> {
+    internal_decode(self.rlz())
+  }
+
+  pub fn random_access(&self, i: U, x: U) -> u8 {
+    internal_random_access(self.rlz(), i, x)
+  }
+
+  // The deflated base data is the reference, and once it is inflated
+  // the inflated one is counted as well
+  pub fn memory_footprint(&self, raw_size: Option<usize>) -> MemoryUsage {
+    let usage = match self.inflated_rlz.get() {
+      Some(rlz) => internal_memory_footprint(rlz, raw_size),
+      None => internal_memory_footprint(
+        self.deflated_rlz.lock().unwrap().as_ref().unwrap(),
+        raw_size,
+      ),
+    };
+    MemoryUsage::new(
+      usage.reference_size() + self.deflated.capacity(),
+      usage.factorizations_size(),
+      usage.random_access_size(),
+      raw_size,
+    )
+  }
+}
+
+impl<U> fmt::Debug for CompressedReference<U> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.debug_struct("CompressedReference")
+      .field("compressed_reference_size", &self.deflated.len())
+      .field("inflated", &self.is_inflated())
+      .finish()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn inflates_on_first_access() {
+    let reference = "ACGTTGCAAGGCTAGC".repeat(200);
+    let test_data = vec![
+      reference.clone(),
+      reference[5..300].to_string(),
+      "TTGCAA".into(),
+    ];
+    let rlz = RelativeLempelZiv::<u32>::encode(&test_data, None, None as Option<&str>);
+    let plain = rlz.memory_footprint(None).reference_size();
+
+    let compressed = RelativeLempelZiv::compress_reference(rlz);
+    assert!(!compressed.is_inflated());
+    let deflated = compressed.memory_footprint(None).reference_size();
+    assert!(deflated < plain / 10);
+
+    assert_eq!(b'T', compressed.random_access(2, 0));
+    assert!(compressed.is_inflated());
+    assert_eq!(test_data, compressed.decode());
+    assert!(compressed.memory_footprint(None).reference_size() > plain);
+    assert_eq!(test_data, compressed.into_inner().decode());
+  }
+}
@@ -0,0 +1,36 @@
+// Helpers for DNA collections, where a sequence is just as likely to
+// show up on the other strand, i.e. as the reverse complement.
+
+// The complement of a nucleotide, keeping its case. Anything that is
+// not one of ACGT (like N) is its own complement.
+pub fn complement(b: u8) -> u8 {
+  match b {
+    b'A' => b'T',
+    b'T' => b'A',
+    b'C' => b'G',
+    b'G' => b'C',
+    b'a' => b't',
+    b't' => b'a',
+    b'c' => b'g',
+    b'g' => b'c',
+    _ => b,
+  }
+}
+
+pub fn reverse_complement(bytes: &[u8]) -> Vec<u8> {
+  bytes.iter().rev().map(|&b| complement(b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn reverse_complement_keeps_case_and_other_bytes() {
+    assert_eq!(b"NACGTacgt".to_vec(), reverse_complement(b"acgtACGTN"));
+    assert_eq!(
+      b"TTGCA".to_vec(),
+      reverse_complement(&reverse_complement(b"TTGCA"))
+    );
+  }
+}
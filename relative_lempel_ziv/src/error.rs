@@ -0,0 +1,189 @@
+use std::error::Error;
+use std::fmt;
+
+// Errors that can happen when turning the factorizations
+// back into strings. Both carry the index of the string
+// that failed, so a caller can figure out which record
+// of the collection is the culprit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+  // A factor references bytes outside of the base data,
+  // which only happens for corrupted structures.
+  FactorOutOfBounds {
+    string: usize,
+    factor: usize,
+    range: (usize, usize),
+    base_len: usize,
+  },
+  // The reconstructed bytes are not valid UTF-8. The offset
+  // is the byte position in the decoded string where the
+  // invalid sequence starts.
+  InvalidUtf8 {
+    string: usize,
+    offset: usize,
+  },
+}
+
+impl fmt::Display for DecodeError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::FactorOutOfBounds {
+        string,
+        factor,
+        range,
+        base_len,
+      } => write!(
+        f,
+        "Factor {} of string {} has range ({}, {}) outside of the base data of length {}",
+        factor, string, range.0, range.1, base_len
+      ),
+      Self::InvalidUtf8 { string, offset } => write!(
+        f,
+        "String {} is not valid UTF-8 from byte offset {}",
+        string, offset
+      ),
+    }
+  }
+}
+
+impl Error for DecodeError {}
+
+// Errors that can happen when encoding a collection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EncodeError {
+  // The base data is longer than the index type of the factors
+  // can address, where max is the largest offset it holds.
+  ReferenceTooLarge {
+    len: usize,
+    max: usize,
+  },
+  // Same as above, but for one of the strings, since the factors
+  // also store the offset into the string they came from.
+  StringTooLarge {
+    string: usize,
+    len: usize,
+    max: usize,
+  },
+}
+
+impl fmt::Display for EncodeError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::ReferenceTooLarge { len, max } => write!(
+        f,
+        "Reference of {} bytes is too large for the index type, which holds offsets up to {}",
+        len, max
+      ),
+      Self::StringTooLarge { string, len, max } => write!(
+        f,
+        "String {} of {} bytes is too large for the index type, which holds offsets up to {}",
+        string, len, max
+      ),
+    }
+  }
+}
+
+impl Error for EncodeError {}
+
+// Errors that can happen when merging two encodings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MergeError {
+  // The encodings are against different base data, so the factors of
+  // one mean nothing against the base of the other
+  DifferentBaseData { len: usize, other_len: usize },
+}
+
+impl fmt::Display for MergeError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::DifferentBaseData { len, other_len } => write!(
+        f,
+        "Cannot merge encodings with different base data, of {} and {} bytes",
+        len, other_len
+      ),
+    }
+  }
+}
+
+impl Error for MergeError {}
+
+// Inconsistencies found by `RelativeLempelZiv::validate`, for the first
+// factor (or alias) where something is off. Any of these would otherwise
+// panic or give wrong bytes somewhere in decoding or random access.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+  // The range of the factor starts after it ends
+  ReversedRange {
+    string: usize,
+    factor: usize,
+    range: (usize, usize),
+  },
+  // The factor copies bytes past the end of the base data
+  FactorOutOfBounds {
+    string: usize,
+    factor: usize,
+    range: (usize, usize),
+    base_len: usize,
+  },
+  // The len of the factor is not the length of the string before it,
+  // which random access depends on to binary search for a factor
+  WrongOffset {
+    string: usize,
+    factor: usize,
+    len: usize,
+    expected: usize,
+  },
+  // The string is an alias of an entry that is not there
+  AliasOutOfBounds {
+    string: usize,
+    alias: usize,
+    entries: usize,
+  },
+}
+
+impl fmt::Display for ValidationError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::ReversedRange {
+        string,
+        factor,
+        range,
+      } => write!(
+        f,
+        "Factor {} of string {} has range ({}, {}) that starts after its end",
+        factor, string, range.0, range.1
+      ),
+      Self::FactorOutOfBounds {
+        string,
+        factor,
+        range,
+        base_len,
+      } => write!(
+        f,
+        "Factor {} of string {} has range ({}, {}) outside of the base data of length {}",
+        factor, string, range.0, range.1, base_len
+      ),
+      Self::WrongOffset {
+        string,
+        factor,
+        len,
+        expected,
+      } => write!(
+        f,
+        "Factor {} of string {} is at offset {}, but the factors before it are {} bytes",
+        factor, string, len, expected
+      ),
+      Self::AliasOutOfBounds {
+        string,
+        alias,
+        entries,
+      } => write!(
+        f,
+        "String {} is an alias of entry {}, but there are only {} entries",
+        string, alias, entries
+      ),
+    }
+  }
+}
+
+impl Error for ValidationError {}
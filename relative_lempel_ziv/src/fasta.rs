@@ -0,0 +1,54 @@
+use std::io::{self, BufRead};
+
+// Reads FASTA records as (sequence, name) pairs, where the name is the
+// header line without the '>' and the sequence is every line up to the
+// next header joined together and uppercased. Blank lines are skipped
+// and CRLF line endings are handled, so files from Windows read the same.
+pub fn read_records<R: BufRead>(reader: R) -> io::Result<Vec<(String, String)>> {
+  let mut records: Vec<(String, String)> = vec![];
+  for (i, line) in reader.lines().enumerate() {
+    let line = line?;
+    let line = line.trim_end_matches('\r');
+    if line.is_empty() {
+      continue;
+    }
+    match line.strip_prefix('>') {
+      Some(header) => records.push((String::new(), String::from(header.trim()))),
+      None => match records.last_mut() {
+        Some((sequence, _)) => sequence.push_str(&line.to_ascii_uppercase()),
+        None => {
+          return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Line {} is a sequence before the first header", i + 1),
+          ))
+        }
+      },
+    }
+  }
+  Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn reads_records() {
+    let fasta = ">chr1 first\r\nacgt\r\nNNAC\r\n\r\n>chr2\nGGTT\n>empty\n";
+    let records = read_records(fasta.as_bytes()).unwrap();
+    assert_eq!(
+      vec![
+        (String::from("ACGTNNAC"), String::from("chr1 first")),
+        (String::from("GGTT"), String::from("chr2")),
+        (String::new(), String::from("empty")),
+      ],
+      records
+    );
+  }
+
+  #[test]
+  fn sequence_before_a_header() {
+    let err = read_records("ACGT\n>chr1\nACGT\n".as_bytes()).unwrap_err();
+    assert_eq!(io::ErrorKind::InvalidData, err.kind());
+  }
+}
@@ -1,25 +1,84 @@
 // Relative Lempel Ziv Implementation
+#[cfg(feature = "cli")]
+use rand::rngs::StdRng;
+#[cfg(feature = "cli")]
 use rand::seq::SliceRandom;
+#[cfg(feature = "cli")]
+use rand::{Rng, SeedableRng};
+#[cfg(feature = "parallel")]
 use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::cmp::Ord;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap};
 use std::convert::{TryFrom, TryInto};
 use std::fmt;
+use std::io;
 use std::mem;
-use std::sync::Mutex;
+use std::ops::Range;
+use std::time::Instant;
 use suffix_tree::SuffixTree;
 
-// For showing output progress to the cli
-use indicatif::{ProgressBar, ProgressStyle};
-
 // For debug
-mod analysis;
+pub mod analysis;
 use analysis::*;
 
 pub mod memory_usage;
 use memory_usage::*;
 
-#[derive(Debug, Clone, Copy)]
+pub mod error;
+use error::*;
+
+pub mod cache;
+use cache::SubstringCache;
+
+pub mod packed;
+use packed::PackedRelativeLempelZiv;
+
+pub mod options;
+use options::EncodeOptions;
+
+pub mod builder;
+
+pub mod stats;
+
+pub mod case_folded;
+use case_folded::CaseFolded;
+
+pub mod compressed;
+use compressed::CompressedReference;
+use stats::EncodeStats;
+
+pub mod dna;
+use dna::{complement, reverse_complement};
+
+pub mod fasta;
+
+pub mod progress;
+use progress::{Phase, Reporter};
+
+// How the bytes of a factor are taken from its range of the base data
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FactorKind {
+    // The bytes as they are
+    Forward,
+    // The reverse complement of the bytes, see `EncodeOptions`
+    ReverseComplement,
+    // The whole string as it is, for a string that would take more
+    // space factorized than raw. Its bytes are appended to the base data
+    // after the reference, where nothing else matches against them.
+    StoredRaw,
+    // A run of the byte, which is not in the base data at all. The range
+    // is (0, length of the run), see `EncodeOptions::runs`.
+    Run(u8),
+    // Bytes of the string as they are, for bytes that are not in the
+    // base data at all (like when pushing to a reference without them),
+    // and in place of factors shorter than
+    // `EncodeOptions::min_factor_length`. Appended to the base data like
+    // stored raw strings.
+    Literal,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct EncodePart<U> {
     len: U,
     // (start, end)
@@ -28,48 +87,112 @@ pub struct EncodePart<U> {
     // and offset but to work nicely with Rust's
     // slices, the end is used instead.
     range: (U, U),
+    kind: FactorKind,
 }
 
 pub type EncodedString<U> = Vec<EncodePart<U>>;
 
-#[derive(Debug)]
+// U is the integer type of the offsets and ranges stored in the factors,
+// so both the base data and every string have to fit within it:
+//   u16: up to 65,535 bytes (64 KiB)
+//   u32: up to 4,294,967,295 bytes (4 GiB)
+//   u64 and usize: anything that fits in memory
+// A smaller U makes every factor smaller, u16 halves them next to u32.
+// Encoding panics if a string or the base data is too large for U,
+// `try_encode` returns an `EncodeError` instead.
+#[derive(Serialize, Deserialize)]
 pub struct RelativeLempelZiv<U> {
     pub base_data: Vec<u8>,
     pub data: Vec<EncodedString<U>>,
+    // Suffix tree of the base data, built by `build_index` or the first
+    // `push` and then reused for the following pushes. It has to be
+    // dropped with `drop_index` if the base data is changed by hand.
+    #[serde(skip)]
+    index: Option<SuffixTree>,
+    // Names of the strings, in the same order as `data`. Kept by every
+    // encode function that takes (string, name) pairs, otherwise empty.
+    names: Vec<String>,
+    name_index: HashMap<String, U>,
+    // The entry of `data` of every string, when identical strings were
+    // only encoded once, see `EncodeOptions::dedup`. Empty otherwise,
+    // where the i'th string is data[i].
+    aliases: Vec<usize>,
+    // The range of every reference in the base data and the reference
+    // every string was matched against, when encoded with
+    // `encode_multi_reference`. Both are empty otherwise, and strings
+    // pushed afterwards were matched against all of the base data.
+    references: Vec<(usize, usize)>,
+    reference_ids: Vec<Option<usize>>,
 }
 
-// Todo: Debugging
-// impl<U> fmt::Debug for RelativeLempelZiv<U>
-// where
-//     U: fmt::Display,
-// {
-//     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-//         // fn fmt_inner(f: &mut fmt::Formatter, s: usize, e: &Vec<EncodedString<U>>) -> fmt::Result {
-//         //     writeln!(f, "{}", s)
-//         // }
-//         // Only output the first 5 data strings...
-//         fn fmt_inner<U>(e: &Vec<EncodePart<U>>) -> String
-//         where
-//             U: fmt::Display,
-//         {
-//             e.iter()
-//                 .map(|v| format!("({}, {})", v.range.0, v.range.1))
-//                 .collect::<Vec<_>>()
-//                 .join("")
-//         }
+// Two encodings are equal when they have the same base data, factors,
+// names, aliases and references (with the reference of every string).
+// Whether the suffix tree index is built does not matter.
+impl<U: PartialEq> PartialEq for RelativeLempelZiv<U> {
+    fn eq(&self, other: &Self) -> bool {
+        self.base_data == other.base_data
+            && self.data == other.data
+            && self.names == other.names
+            && self.aliases == other.aliases
+            && self.references == other.references
+            && self.reference_ids == other.reference_ids
+    }
+}
 
-//         writeln!(f, "Data length: {}", self.data.len());
+impl<U: Eq> Eq for RelativeLempelZiv<U> {}
 
-//         for i in self.data.iter().take(5) {
-//             writeln!(f, "{}, {}", i.len(), fmt_inner(&i))?;
-//         }
+// An empty collection with an empty base, so only empty strings can
+// be appended to it. Use `with_reference` to start out with a base.
+impl<U> Default for RelativeLempelZiv<U> {
+    fn default() -> Self {
+        RelativeLempelZiv {
+            base_data: vec![],
+            data: vec![],
+            index: None,
+            names: vec![],
+            name_index: HashMap::new(),
+            aliases: vec![],
+            references: vec![],
+            reference_ids: vec![],
+        }
+    }
+}
 
-//         // for _ in 0..cmp::min(5, self.data.len()) {
-//         // }
+// The number of strings whose factors are printed by `Debug`
+const DEBUG_STRINGS: usize = 5;
 
-//         Ok(())
-//     }
-// }
+// A summary instead of the whole base data and every factor, which is
+// far too much to print for anything but a tiny collection. Only the
+// first few strings get their factors printed, as (start, end).
+impl<U: fmt::Debug> fmt::Debug for RelativeLempelZiv<U> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let counts: Vec<usize> = (0..internal_len(self))
+            .map(|i| string_factors(self, i).len())
+            .collect();
+        let mean = if counts.is_empty() {
+            None
+        } else {
+            Some(counts.iter().sum::<usize>() as f64 / counts.len() as f64)
+        };
+        let first: Vec<Vec<(&U, &U)>> = (0..counts.len().min(DEBUG_STRINGS))
+            .map(|i| {
+                string_factors(self, i)
+                    .iter()
+                    .map(|part| (&part.range.0, &part.range.1))
+                    .collect()
+            })
+            .collect();
+
+        f.debug_struct("RelativeLempelZiv")
+            .field("base_data_len", &self.base_data.len())
+            .field("strings", &counts.len())
+            .field("min_factors", &counts.iter().min())
+            .field("mean_factors", &mean)
+            .field("max_factors", &counts.iter().max())
+            .field("first_strings", &first)
+            .finish()
+    }
+}
 
 impl<U> RelativeLempelZiv<U>
 where
@@ -87,60 +210,623 @@ where
 
         let base_string = base_string(&strings, n, chars);
         let st = create_suffix_tree(base_string);
-        let rlz = encode_parts(&strings, &st);
-
-        let mut a_vec = Vec::with_capacity(strings.len());
-        for (i, (encoded, name)) in rlz.data.iter().zip(names.iter()).enumerate() {
-            let len = encoded.len();
-            let c_size = internal_memory_single_list(&encoded);
-            let r_size = strings[i].len();
-            let analysis = Analysis::new(len, c_size, r_size, name);
-            a_vec.push(analysis);
-        }
+        let mut rlz = encode_parts(&strings, &st, None, &EncodeOptions::default());
 
-        let analysis_result = AnalysisResult::new(a_vec);
+        let analysis_result = internal_analysis(&rlz, &names);
+        set_names(&mut rlz, names);
         (rlz, analysis_result)
     }
 
+    // Per-string analysis of an encoding, so it can be had from any of
+    // the encode functions, e.g. `RelativeLempelZiv::encode(&strings, None,
+    // None as Option<&str>).analysis()`. When the strings were encoded
+    // without names, they are named by their index.
+    pub fn analysis(&self) -> AnalysisResult {
+        internal_analysis(self, &self.names)
+    }
+
+    // Panics if the reference or one of the strings is too large for U,
+    // see `try_encode` for a version that returns the error instead.
     pub fn encode<T: AsRef<str> + Sync>(
         strings: &[T],
         n: Option<Vec<usize>>,
         chars: Option<impl AsRef<str>>,
     ) -> Self {
-        let spinner_style = ProgressStyle::default_spinner()
-            .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈ ")
-            .template("{spinner} {wide_msg}");
+        internal_encode(
+            &str_bytes(strings),
+            n,
+            chars,
+            None,
+            &EncodeOptions::default(),
+        )
+    }
 
-        let pb = ProgressBar::new(1);
-        pb.set_style(spinner_style);
-        pb.set_message("Finding base string...");
-        let base_string = base_string(&strings, n, chars);
+    // Same as `encode`, but also returns the bytes that were missing from
+    // the reference and the chars, and had to be appended to the base,
+    // as (index of the first string with the byte, byte). Stray bytes
+    // there usually mean something is off with the data.
+    pub fn encode_with_report<T: AsRef<str> + Sync>(
+        strings: &[T],
+        n: Option<Vec<usize>>,
+        chars: Option<impl AsRef<str>>,
+    ) -> (Self, Vec<(usize, u8)>) {
+        internal_encode_with_report(&str_bytes(strings), n, chars)
+    }
 
-        pb.set_message("Creating suffix tree from base string...");
-        let st = create_suffix_tree(base_string);
+    // Keeps the base data deflated until a string is decoded, see
+    // `compressed`
+    pub fn compress_reference(self) -> CompressedReference<U> {
+        CompressedReference::new(self)
+    }
+
+    // Encodes the strings in upper case and keeps which bytes were
+    // lowercase on the side, see `case_folded`
+    pub fn encode_case_folded<T: AsRef<str> + Sync>(
+        strings: &[T],
+        n: Option<Vec<usize>>,
+        chars: Option<impl AsRef<str>>,
+    ) -> CaseFolded<U> {
+        CaseFolded::encode(strings, n, chars)
+    }
+
+    // Same as `encode`, but also times the phases of the encoding, which
+    // tells more about where the time goes than timing the whole call
+    pub fn encode_timed<T: AsRef<str> + Sync>(
+        strings: &[T],
+        n: Option<Vec<usize>>,
+        chars: Option<impl AsRef<str>>,
+    ) -> (Self, EncodeStats) {
+        internal_encode_timed(&str_bytes(strings), n, chars)
+    }
+
+    // Same as `encode`, but checks up front that the reference and the
+    // strings fit in U before any of the work is done
+    pub fn try_encode<T: AsRef<str> + Sync>(
+        strings: &[T],
+        n: Option<Vec<usize>>,
+        chars: Option<impl AsRef<str>>,
+    ) -> Result<Self, EncodeError> {
+        internal_try_encode(
+            &str_bytes(strings),
+            n,
+            chars,
+            None,
+            &EncodeOptions::default(),
+        )
+    }
+
+    // Same as `encode`, but for any bytes and not only valid UTF-8, like
+    // protein or quality strings. There are no chars to give, since every
+    // byte of the input is made sure to be in the base anyway.
+    pub fn encode_bytes<T: AsRef<[u8]> + Sync>(data: &[T], n: Option<Vec<usize>>) -> Self {
+        internal_encode(
+            data,
+            n,
+            None as Option<&str>,
+            None,
+            &EncodeOptions::default(),
+        )
+    }
+
+    // Same as `encode`, but for (string, name) pairs where the names
+    // are kept, so strings can be looked up by name afterwards.
+    pub fn from_collection_with_names<T: AsRef<str> + Sync>(
+        strings: &[(T, T)],
+        n: Option<Vec<usize>>,
+        chars: Option<impl AsRef<str>>,
+    ) -> Self {
+        internal_from_collection_with_names(strings, n, chars)
+    }
+
+    // Reads the records of a FASTA file and encodes them with their
+    // headers as names, see `fasta::read_records` for how they are read.
+    // The names are also returned in the order of the records.
+    pub fn encode_fasta<R: io::BufRead>(
+        reader: R,
+        n: Option<Vec<usize>>,
+    ) -> io::Result<(Self, Vec<String>)> {
+        let records = fasta::read_records(reader)?;
+        let rlz = internal_from_collection_with_names(&records, n, None as Option<&str>);
+        let names = records.into_iter().map(|(_, name)| name).collect();
+        Ok((rlz, names))
+    }
+
+    // Picks the reference string by trying `sample_size` strings spread
+    // evenly over the collection, and keeping the one that factorizes the
+    // sampled strings into the fewest factors in total. Cheaper than
+    // `encode_reference_merge`, since every candidate is only tried once
+    // against the sample. Returns the indices of the chosen reference
+    // strings along with the encoding, which are what `encode` takes.
+    pub fn encode_auto<T: AsRef<str> + Sync>(
+        strings: &[T],
+        sample_size: usize,
+    ) -> (Self, Vec<usize>) {
+        internal_encode_auto(strings, sample_size)
+    }
+
+    pub fn encode_with_options<T: AsRef<str> + Sync>(
+        strings: &[T],
+        n: Option<Vec<usize>>,
+        chars: Option<impl AsRef<str>>,
+        options: &EncodeOptions,
+    ) -> Self {
+        internal_encode(&str_bytes(strings), n, chars, None, options)
+    }
+
+    // Encodes every string against the given reference instead of
+    // building the base from the collection itself. Chars of the strings
+    // that are missing from the reference are appended to it, so the
+    // base data can be slightly longer than the reference.
+    pub fn encode_with_reference<T: AsRef<str> + Sync>(strings: &[T], reference: &str) -> Self {
+        internal_encode_with_reference(strings, reference)
+    }
+
+    // Encodes every string against whichever of the references gives it
+    // the fewest factors, where the first one wins ties. The references
+    // are concatenated into the base data, but every factor stays within
+    // the reference of its string, see `references` and `reference_of`.
+    pub fn encode_multi_reference<T: AsRef<str> + Sync>(
+        references: &[&str],
+        strings: &[T],
+    ) -> Self {
+        internal_encode_multi_reference(references, strings)
+    }
+
+    // Encodes the strings against the reference as they are pulled from
+    // the iterator, so only the reference and the factors have to fit in
+    // memory and not the whole collection. Since the strings are not
+    // known up front, a byte that is missing from the base is appended to
+    // it when a string first needs it, which extends the suffix tree
+    // online and leaves the factors of the earlier strings as they are.
+    pub fn encode_streaming<I: IntoIterator<Item = String>>(reference: &str, strings: I) -> Self {
+        internal_encode_streaming(reference, strings)
+    }
+
+    // Same as `encode_streaming`, but for (string, name) pairs where the
+    // names are kept like in `from_collection_with_names`.
+    pub fn encode_streaming_with_names<I: IntoIterator<Item = (String, String)>>(
+        reference: &str,
+        strings: I,
+    ) -> Self {
+        let mut names = vec![];
+        let mut rlz = internal_encode_streaming(
+            reference,
+            strings.into_iter().map(|(s, name)| {
+                names.push(name);
+                s
+            }),
+        );
+        set_names(&mut rlz, names);
+        rlz
+    }
+
+    // Estimates the compression rate of a collection like the sample
+    // against the reference, without keeping the encoding. The sample is
+    // taken to be the whole collection, so this is the rate that
+    // `memory_footprint(..).compression_rate()` would give for it.
+    pub fn estimate_compression<T: AsRef<str> + Sync>(reference: &str, sample: &[T]) -> f64 {
+        let raw_size = internal_memory_string_list(sample) as usize;
+        internal_estimate_compression::<U, T>(reference, sample, raw_size)
+    }
+
+    // Same as `estimate_compression`, but for a collection of total_size
+    // bytes in all, of which the sample is a part. The factors per byte of
+    // the sample are extrapolated to the rest of the collection, while the
+    // reference is only counted once.
+    pub fn estimate_compression_of_size<T: AsRef<str> + Sync>(
+        reference: &str,
+        sample: &[T],
+        total_size: usize,
+    ) -> f64 {
+        internal_estimate_compression::<U, T>(reference, sample, total_size)
+    }
+
+    // Same as `encode`, but looks up the factors through the cache first,
+    // which pays off for collections with a lot of duplicated content.
+    // The cache keeps its hit rate, so it can be inspected afterwards.
+    pub fn encode_with_cache<T: AsRef<str> + Sync>(
+        strings: &[T],
+        n: Option<Vec<usize>>,
+        chars: Option<impl AsRef<str>>,
+        cache: &SubstringCache,
+    ) -> Self {
+        internal_encode(
+            &str_bytes(strings),
+            n,
+            chars,
+            Some(cache),
+            &EncodeOptions::default(),
+        )
+    }
+
+    // Creates a collection with no strings in it, where the given
+    // reference is the base that strings are encoded against
+    // when they are appended.
+    pub fn with_reference<T: AsRef<str>>(reference: T) -> Self {
+        RelativeLempelZiv {
+            base_data: reference.as_ref().as_bytes().to_vec(),
+            data: vec![],
+            ..Default::default()
+        }
+    }
+
+    // Adds the string to the end of the collection, encoded against
+    // the current base data. Bytes that are not in the base data are
+    // stored as literals at the end of it, but the base data before them
+    // is never changed, so the strings already there stay valid.
+    pub fn push<T: AsRef<str>>(&mut self, s: T) {
+        internal_push(self, s.as_ref())
+    }
+
+    // Same as `append_many`
+    pub fn extend<T: AsRef<str> + Sync>(&mut self, strings: &[T]) {
+        self.append_many(strings)
+    }
+
+    // Adds all of the strings at the end of the collection like `push`,
+    // but factorizes them in parallel against the (cached) suffix tree.
+    pub fn append_many<T: AsRef<str> + Sync>(&mut self, strings: &[T]) {
+        internal_append_many(self, strings)
+    }
 
-        pb.set_message("Encoding...");
-        let res = encode_parts(strings, &st);
-        pb.finish_and_clear();
-        res
+    // Same as `push`
+    pub fn append<T: AsRef<str>>(&mut self, s: T) {
+        self.push(s)
     }
 
+    #[cfg(feature = "cli")]
     pub fn encode_reference_merge<T>(strings: &[(T, T)], chars: Option<impl AsRef<str>>) -> Self
     where
         T: AsRef<str> + Sync + Eq,
     {
-        encode_by_reference_merge(strings, chars)
+        encode_by_reference_merge(strings, chars, rand::random(), &EncodeOptions::default())
+    }
+
+    // Same as `encode_reference_merge`, but the initial reference string
+    // is picked by an RNG with the given seed, so the result is the same
+    // every time for the same collection.
+    #[cfg(feature = "cli")]
+    pub fn encode_reference_merge_seeded<T>(
+        strings: &[(T, T)],
+        chars: Option<impl AsRef<str>>,
+        seed: u64,
+    ) -> Self
+    where
+        T: AsRef<str> + Sync + Eq,
+    {
+        encode_by_reference_merge(strings, chars, seed, &EncodeOptions::default())
+    }
+
+    // Same as `encode_reference_merge_seeded`, with the options used for
+    // every encoding pass. `show_progress` also covers the messages
+    // about each pass on stderr, so they can be turned off along with
+    // the bar.
+    #[cfg(feature = "cli")]
+    pub fn encode_reference_merge_with_options<T>(
+        strings: &[(T, T)],
+        chars: Option<impl AsRef<str>>,
+        seed: u64,
+        options: &EncodeOptions,
+    ) -> Self
+    where
+        T: AsRef<str> + Sync + Eq,
+    {
+        encode_by_reference_merge(strings, chars, seed, options)
+    }
+
+    // Same strategy as `encode_reference_merge`, but for collections too
+    // large to keep in memory. `open` is called once per encoding pass
+    // and has to give the (string, name) pairs in the same order each
+    // time, e.g. by reopening a file. Only the reference strings and the
+    // encoding itself are kept in memory, and the strings are encoded one
+    // at a time rather than in parallel.
+    #[cfg(feature = "cli")]
+    pub fn encode_reference_merge_streaming<F, I>(
+        open: F,
+        chars: Option<impl AsRef<str>>,
+    ) -> io::Result<Self>
+    where
+        F: FnMut() -> io::Result<I>,
+        I: IntoIterator<Item = io::Result<(String, String)>>,
+    {
+        encode_by_reference_merge_streaming(open, chars, rand::random())
+    }
+
+    // Same as `encode_reference_merge_streaming`, with the initial
+    // reference string picked by an RNG with the given seed, like
+    // `encode_reference_merge_seeded`.
+    #[cfg(feature = "cli")]
+    pub fn encode_reference_merge_streaming_seeded<F, I>(
+        open: F,
+        chars: Option<impl AsRef<str>>,
+        seed: u64,
+    ) -> io::Result<Self>
+    where
+        F: FnMut() -> io::Result<I>,
+        I: IntoIterator<Item = io::Result<(String, String)>>,
+    {
+        encode_by_reference_merge_streaming(open, chars, seed)
     }
 
+    // Convenience wrapper around `try_decode` for when the
+    // input is known to be valid UTF-8, panics otherwise.
     pub fn decode(&self) -> Vec<String> {
+        self.try_decode().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_decode(&self) -> Result<Vec<String>, DecodeError> {
         internal_decode(self)
     }
 
+    // Same as `decode`, but decodes the strings in parallel, which pays
+    // off for large collections since they all decode independently
+    #[cfg(feature = "parallel")]
+    pub fn par_decode(&self) -> Vec<String>
+    where
+        U: Sync,
+    {
+        (0..self.len())
+            .into_par_iter()
+            .map(|i| internal_decode_single(self, i))
+            .collect::<Result<_, _>>()
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    // The decoded strings as they are, without checking that they are
+    // UTF-8, which is what `encode_bytes` input has to be decoded with.
+    // Only panics if a factor is outside of the base data.
+    pub fn decode_bytes(&self) -> Vec<Vec<u8>> {
+        (0..self.len())
+            .map(|i| internal_decode_single_bytes(self, i).unwrap_or_else(|e| panic!("{}", e)))
+            .collect()
+    }
+
+    // Writes every decoded string followed by `sep` to the writer, without
+    // going through a String, so the bytes do not have to be valid UTF-8.
+    // Fails with `InvalidData` if a factor is outside of the base data.
+    pub fn decode_to_writer<W: io::Write>(&self, sep: &[u8], w: &mut W) -> io::Result<()> {
+        internal_decode_to_writer(self, sep, w)
+    }
+
+    // Decodes the strings one at a time as the iterator is advanced,
+    // so only a single decoded string has to be in memory at once.
+    // Panics like `decode` on a string that is not valid UTF-8.
+    pub fn iter_decoded(&self) -> impl Iterator<Item = String> + '_ {
+        (0..self.len())
+            .map(move |i| internal_decode_single(self, i).unwrap_or_else(|e| panic!("{}", e)))
+    }
+
+    // Decodes only the i'th string
+    pub fn extract(&self, i: U) -> String {
+        internal_decode_single(self, i.try_into().unwrap()).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    // Writes only the i'th decoded string to the writer, like
+    // `decode_to_writer` does for all of them
+    pub fn extract_to_writer<W: io::Write>(&self, i: U, w: &mut W) -> io::Result<()> {
+        internal_write_string(self, i.try_into().unwrap(), w)
+    }
+
+    // The names given with the strings, e.g. to `from_collection_with_names`,
+    // in the same order as the strings. Empty if the collection is not named.
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    // The references given to `encode_multi_reference`, in order. Empty
+    // for a collection with a single reference.
+    pub fn references(&self) -> Vec<&[u8]> {
+        self.references
+            .iter()
+            .map(|&(start, end)| &self.base_data[start..end])
+            .collect()
+    }
+
+    // The index in `references` of the reference the i'th string was
+    // encoded against, if it was encoded by `encode_multi_reference`
+    pub fn reference_of(&self, i: U) -> Option<usize> {
+        let i: usize = i.try_into().unwrap();
+        self.reference_ids.get(i).copied().flatten()
+    }
+
+    // Decodes the string with the given name, if there is one
+    pub fn extract_by_name(&self, name: &str) -> Option<String> {
+        self.index_of(name).map(|i| self.extract(i))
+    }
+
+    // The index of the string with the given name. If several strings
+    // share a name, it is the first of them.
+    pub fn index_of(&self, name: &str) -> Option<U> {
+        self.name_index.get(name).copied()
+    }
+
+    // The factors of the i'th string as (start, end, len, kind), where len
+    // is the offset in the string where it goes, i.e. the length of the
+    // string before it. What start..end means depends on the kind: it is
+    // the range of the base data for forward factors, copied backwards
+    // for reverse complement ones, but (0, length of the run) for runs,
+    // which copy nothing from the base.
+    pub fn factors(&self, i: U) -> Vec<(U, U, U, FactorKind)> {
+        string_factors(self, i.try_into().unwrap())
+            .iter()
+            .map(|part| (part.range.0, part.range.1, part.len, part.kind))
+            .collect()
+    }
+
+    // The ranges of the base data the factors of the i'th string copy,
+    // in the order of the string. Run factors copy nothing, so they are
+    // left out.
+    pub fn factor_ranges(&self, i: U) -> impl Iterator<Item = Range<usize>> + '_ {
+        string_factors(self, i.try_into().unwrap())
+            .iter()
+            .filter(|part| !matches!(part.kind, FactorKind::Run(_)))
+            .map(|part| part.range.0.try_into().unwrap()..part.range.1.try_into().unwrap())
+    }
+
+    // How many factors there are of every length, over all of the
+    // strings. Longer factors mean the reference covers the strings better.
+    pub fn factor_length_histogram(&self) -> BTreeMap<usize, usize> {
+        let mut histogram = BTreeMap::new();
+        for i in 0..self.len() {
+            for len in factor_lengths(self, i) {
+                *histogram.entry(len).or_insert(0) += 1;
+            }
+        }
+        histogram
+    }
+
+    // The length of the shortest factor of the i'th string, None if the
+    // string is empty
+    pub fn min_factor_length(&self, i: U) -> Option<usize> {
+        factor_lengths(self, i.try_into().unwrap()).min()
+    }
+
+    // The length of the longest factor of the i'th string
+    pub fn max_factor_length(&self, i: U) -> Option<usize> {
+        factor_lengths(self, i.try_into().unwrap()).max()
+    }
+
+    // The mean length of the factors of the i'th string
+    pub fn mean_factor_length(&self, i: U) -> Option<f64> {
+        let i = i.try_into().unwrap();
+        let factors = string_factors(self, i).len();
+        if factors == 0 {
+            return None;
+        }
+        Some(factor_lengths(self, i).sum::<usize>() as f64 / factors as f64)
+    }
+
+    // Decodes the i'th string, where every byte is paired with the
+    // position in the base data it was copied from, or usize::MAX for
+    // the bytes of runs.
+    pub fn decode_annotated(&self, i: U) -> Vec<(u8, usize)> {
+        internal_decode_annotated(self, i)
+    }
+
+    // The number of strings in the collection
+    pub fn len(&self) -> usize {
+        internal_len(self)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // The number of strings that are stored as an alias of an identical
+    // string before them, which is 0 unless encoded with
+    // `EncodeOptions::dedup`
+    pub fn dedup_stats(&self) -> usize {
+        self.len() - self.data.len()
+    }
+
+    // The number of bytes of the strings that are stored in run factors,
+    // so they did not have to be matched, see `EncodeOptions::runs`
+    pub fn bytes_in_runs(&self) -> usize {
+        (0..self.len())
+            .flat_map(|i| string_factors(self, i).iter())
+            .filter(|part| matches!(part.kind, FactorKind::Run(_)))
+            .map(|part| part.range.1.try_into().unwrap())
+            .sum()
+    }
+
+    // For every byte of the base data, whether a factor of any string
+    // copies it. The bytes that are not, are only taking up space and
+    // can be trimmed from the reference before encoding again.
+    pub fn reference_coverage(&self) -> Vec<bool> {
+        internal_reference_coverage(self)
+    }
+
+    // Removes the bytes of the base data that no factor copies, see
+    // `reference_coverage`, and moves the factors to where their bytes
+    // end up. The strings decode the same as before.
+    pub fn trim_reference(&mut self) {
+        internal_trim_reference(self);
+    }
+
+    // Checks that every factor is within the base data and sits at the
+    // right offset of its string, e.g. for an encoding that was loaded
+    // from a file or merged, so it fails here rather than in decoding.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        internal_validate(self)
+    }
+
+    // Whether a suffix tree of the base data is currently held on to.
+    // The encode functions only need the tree while encoding, so it is
+    // dropped before they return, and only `push` and `build_index`
+    // keep one around.
+    pub fn is_indexed(&self) -> bool {
+        self.index.is_some()
+    }
+
+    // Builds the suffix tree of the base data up front, so the first
+    // `push` does not have to. Does nothing if it is already built.
+    pub fn build_index(&mut self) {
+        internal_index(self);
+    }
+
+    // Frees the suffix tree again, which is a lot bigger than the
+    // encoding itself. The next `push` will have to rebuild it.
+    pub fn drop_index(&mut self) {
+        self.index = None;
+    }
+
+    // The length in bytes of the i'th string, without decoding it.
+    // Useful for bounds checking before calling `random_access`.
+    pub fn string_len(&self, i: U) -> usize {
+        internal_string_len(self, i)
+    }
+
     // Gets the x'th byte from the i'th string
     pub fn random_access(&self, i: U, x: U) -> u8 {
         internal_random_access(self, i, x)
     }
 
+    // Gets the char at the char index of the i'th string. Chars of UTF-8
+    // can be 1 to 4 bytes, so where a char starts is not known without
+    // going through the string up to it. That makes this linear in the
+    // char index, where `random_access` only does a binary search over
+    // the factors.
+    pub fn random_access_char(&self, i: U, char_index: U) -> char {
+        internal_random_access_char(self, i, char_index)
+    }
+
+    // Gets the bytes from `start` to `end` (not inclusive) of the i'th string.
+    // Only searches for the factor containing `start` once, and then copies
+    // from the following factors until `end` is reached.
+    pub fn random_access_range(&self, i: U, start: U, end: U) -> Vec<u8> {
+        internal_random_access_range(self, i, start, end)
+    }
+
+    // The ranges of the base data that both the i'th and j'th string
+    // copy from, regardless of which of their factors do it or in which
+    // order. These are the overlaps of the factors of the two strings,
+    // not only factors that are exactly the same, merged into sorted,
+    // non-overlapping ranges. Reverse complement factors count as well,
+    // since they still copy from the same part of the base.
+    pub fn common_factors(&self, i: U, j: U) -> Vec<(U, U)> {
+        internal_common_factors(self, i, j)
+    }
+
+    // Appends the strings of the other encoding to this one, which only
+    // works if both were encoded against the same base data, e.g. shards
+    // of a collection encoded separately with `encode_with_reference`.
+    // Bytes appended to the base for stored raw strings and literals are
+    // carried over, so only the part before those has to be the same.
+    // The strings without names get empty ones if only one has names.
+    pub fn merge(self, other: Self) -> Result<Self, MergeError> {
+        internal_merge(self, other)
+    }
+
+    // The compact form of the factorizations, see `packed`
+    pub fn pack(&self) -> PackedRelativeLempelZiv {
+        PackedRelativeLempelZiv::new(self)
+    }
+
+    // Same as `pack`, in the delta mode for very similar strings
+    pub fn pack_delta(&self) -> PackedRelativeLempelZiv {
+        PackedRelativeLempelZiv::new_delta(self)
+    }
+
     pub fn memory_footprint(&self, total_size: Option<usize>) -> MemoryUsage {
         internal_memory_footprint(self, total_size)
     }
@@ -158,95 +844,576 @@ where
     // }
 }
 
-fn base_string_by_name<T: AsRef<str> + Eq>(
-    strings: &[(T, T)],
-    names: &Vec<String>,
-    chars: &Option<impl AsRef<str>>,
-) -> String {
-    let mut ref_str = strings
-        .iter()
-        .filter(|(_, n)| names.contains(&String::from(n.as_ref())))
-        .map(|(s, _)| s.as_ref())
-        .collect::<Vec<_>>()
-        .join("");
-
-    if let Some(s) = chars {
-        ref_str.push_str(s.as_ref());
-    }
-    ref_str
+// The bytes of every string, which is what the encoding works on
+pub(crate) fn str_bytes<T: AsRef<str>>(strings: &[T]) -> Vec<&[u8]> {
+    strings.iter().map(|s| s.as_ref().as_bytes()).collect()
 }
 
-fn encode_by_reference_merge<U, T>(
-    strings: &[(T, T)],
+fn internal_encode<U, T>(
+    strings: &[T],
+    n: Option<Vec<usize>>,
     chars: Option<impl AsRef<str>>,
+    cache: Option<&SubstringCache>,
+    options: &EncodeOptions,
 ) -> RelativeLempelZiv<U>
 where
-    U: Copy + Ord + TryFrom<usize> + TryInto<usize> + Send,
+    U: Copy + TryFrom<usize> + TryInto<usize> + Send,
     <U as TryFrom<usize>>::Error: fmt::Debug,
     <U as TryInto<usize>>::Error: fmt::Debug,
-    T: AsRef<str> + Sync + Eq,
+    T: AsRef<[u8]> + Sync,
 {
-    let raw_strings: Vec<&str> = strings.iter().map(|t| t.0.as_ref()).collect();
-    let names: Vec<&str> = strings.iter().map(|t| t.1.as_ref()).collect();
+    internal_try_encode(strings, n, chars, cache, options).unwrap_or_else(|e| panic!("{}", e))
+}
 
-    let total_size = internal_memory_string_list(&raw_strings);
+fn internal_try_encode<U, T>(
+    strings: &[T],
+    n: Option<Vec<usize>>,
+    chars: Option<impl AsRef<str>>,
+    cache: Option<&SubstringCache>,
+    options: &EncodeOptions,
+) -> Result<RelativeLempelZiv<U>, EncodeError>
+where
+    U: Copy + TryFrom<usize> + TryInto<usize> + Send,
+    <U as TryFrom<usize>>::Error: fmt::Debug,
+    <U as TryInto<usize>>::Error: fmt::Debug,
+    T: AsRef<[u8]> + Sync,
+{
+    let progress = Reporter::new(options);
+    progress.phase(Phase::FindingBase);
+    let base_string = base_string(strings, n, chars);
+    check_index_width::<U, T>(base_string.len(), strings)?;
 
-    // Initially pick a random reference string
-    let mut reference_names: Vec<String> = Vec::new();
+    progress.phase(Phase::BuildingTree);
+    let st = create_suffix_tree(base_string);
+    drop(progress);
 
-    let initial_element = strings.choose(&mut rand::thread_rng()).unwrap();
-    reference_names.push(String::from(initial_element.1.as_ref()));
+    try_encode_parts(strings, &st, cache, options)
+}
 
-    // Loop until best compression rate is found
-    let mut best_compression_rate = 1.0f64;
-    let mut i = 0;
-    let mut best_rlz = None;
-    loop {
-        i += 1;
+// The factors store offsets up to the length of the base data and of the
+// string they came from, so those are the ones that have to fit in U
+fn internal_encode_with_report<U, T>(
+    strings: &[T],
+    n: Option<Vec<usize>>,
+    chars: Option<impl AsRef<str>>,
+) -> (RelativeLempelZiv<U>, Vec<(usize, u8)>)
+where
+    U: Copy + TryFrom<usize> + TryInto<usize> + Send,
+    <U as TryFrom<usize>>::Error: fmt::Debug,
+    <U as TryInto<usize>>::Error: fmt::Debug,
+    T: AsRef<[u8]> + Sync,
+{
+    let (base_string, report) = base_string_with_report(strings, n, chars);
+    check_index_width::<U, T>(base_string.len(), strings).unwrap_or_else(|e| panic!("{}", e));
+    let st = create_suffix_tree(base_string);
+    let rlz = encode_parts(strings, &st, None, &EncodeOptions::default());
+    (rlz, report)
+}
 
-        // This scope is to uninitialize the base_string and st
-        // asap because we still do computation after, but they
-        // aren't needed for that. Thanks to Rust's borrowing
-        // system, they will be removed from memory after the
+fn internal_encode_timed<U, T>(
+    strings: &[T],
+    n: Option<Vec<usize>>,
+    chars: Option<impl AsRef<str>>,
+) -> (RelativeLempelZiv<U>, EncodeStats)
+where
+    U: Copy + TryFrom<usize> + TryInto<usize> + Send,
+    <U as TryFrom<usize>>::Error: fmt::Debug,
+    <U as TryInto<usize>>::Error: fmt::Debug,
+    T: AsRef<[u8]> + Sync,
+{
+    let stopwatch = Instant::now();
+    let base_string = base_string(strings, n, chars);
+    check_index_width::<U, T>(base_string.len(), strings).unwrap_or_else(|e| panic!("{}", e));
+    let base_selection = stopwatch.elapsed();
+
+    let stopwatch = Instant::now();
+    let st = create_suffix_tree(base_string);
+    let tree_build = stopwatch.elapsed();
+
+    let stopwatch = Instant::now();
+    let rlz: RelativeLempelZiv<U> = encode_parts(strings, &st, None, &EncodeOptions::default());
+    let factorization = stopwatch.elapsed();
+
+    let stats = EncodeStats {
+        base_selection,
+        tree_build,
+        factorization,
+        node_count: st.node_count(),
+        factor_count: rlz.data.iter().map(Vec::len).sum(),
+    };
+    (rlz, stats)
+}
+
+fn check_index_width<U, T>(base_len: usize, strings: &[T]) -> Result<(), EncodeError>
+where
+    U: TryFrom<usize>,
+    T: AsRef<[u8]>,
+{
+    check_index_width_of_lengths::<U>(
+        base_len,
+        strings.iter().map(|s| s.as_ref().len()).enumerate(),
+    )
+}
+
+// Same as `check_index_width`, for the (index, length) of the strings,
+// when they are not all at hand like in `encode_streaming`
+fn check_index_width_of_lengths<U>(
+    base_len: usize,
+    lengths: impl IntoIterator<Item = (usize, usize)>,
+) -> Result<(), EncodeError>
+where
+    U: TryFrom<usize>,
+{
+    let max = max_index::<U>();
+    if base_len > max {
+        return Err(EncodeError::ReferenceTooLarge { len: base_len, max });
+    }
+    match lengths.into_iter().find(|&(_, len)| len > max) {
+        Some((string, len)) => Err(EncodeError::StringTooLarge { string, len, max }),
+        None => Ok(()),
+    }
+}
+
+// The largest usize that converts into U, found bit by bit from the top,
+// which works for any unsigned type since they hold every value below it
+fn max_index<U: TryFrom<usize>>() -> usize {
+    (0..usize::BITS).rev().fold(0, |max, bit| {
+        let candidate = max | 1 << bit;
+        if U::try_from(candidate).is_ok() {
+            candidate
+        } else {
+            max
+        }
+    })
+}
+
+fn internal_encode_auto<U, T>(
+    strings: &[T],
+    sample_size: usize,
+) -> (RelativeLempelZiv<U>, Vec<usize>)
+where
+    U: Copy + TryFrom<usize> + TryInto<usize> + Send,
+    <U as TryFrom<usize>>::Error: fmt::Debug,
+    <U as TryInto<usize>>::Error: fmt::Debug,
+    T: AsRef<str> + Sync,
+{
+    if strings.is_empty() {
+        return (RelativeLempelZiv::default(), vec![]);
+    }
+
+    let k = sample_size.clamp(1, strings.len());
+    let sample: Vec<usize> = (0..k).map(|x| x * strings.len() / k).collect();
+    let sampled = || sample.iter().map(|&i| strings[i].as_ref());
+
+    // Every candidate is scored on the whole sample, which is the
+    // candidate itself plus the others. Ties go to the earliest.
+    let factor_count = |candidate: usize| {
+        let mut base_string = strings[candidate].as_ref().as_bytes().to_vec();
+        append_missing_bytes(&mut base_string, sampled().map(str::as_bytes));
+        let st = create_suffix_tree(base_string);
+        sampled()
+            .map(|s| encode_string::<usize>(s.as_bytes(), &st, None, None).len())
+            .sum::<usize>()
+    };
+    #[cfg(feature = "parallel")]
+    let candidates = sample.par_iter();
+    #[cfg(not(feature = "parallel"))]
+    let candidates = sample.iter();
+    let best = candidates
+        .map(|&candidate| (factor_count(candidate), candidate))
+        .min()
+        .unwrap()
+        .1;
+
+    let chosen = vec![best];
+    let rlz = internal_encode(
+        &str_bytes(strings),
+        Some(chosen.clone()),
+        None as Option<&str>,
+        None,
+        &EncodeOptions::default(),
+    );
+    (rlz, chosen)
+}
+
+fn internal_from_collection_with_names<U, T>(
+    strings: &[(T, T)],
+    n: Option<Vec<usize>>,
+    chars: Option<impl AsRef<str>>,
+) -> RelativeLempelZiv<U>
+where
+    U: Copy + TryFrom<usize> + TryInto<usize> + Send,
+    <U as TryFrom<usize>>::Error: fmt::Debug,
+    <U as TryInto<usize>>::Error: fmt::Debug,
+    T: AsRef<str> + Sync,
+{
+    let raw_strings: Vec<&str> = strings.iter().map(|t| t.0.as_ref()).collect();
+    let mut rlz = internal_encode(&raw_strings, n, chars, None, &EncodeOptions::default());
+    set_names(&mut rlz, strings.iter().map(|t| t.1.as_ref()));
+    rlz
+}
+
+// Names the strings of the collection in order, where the first
+// string wins if a name is used more than once
+fn set_names<U, T, I>(rlz: &mut RelativeLempelZiv<U>, names: I)
+where
+    U: TryFrom<usize>,
+    <U as TryFrom<usize>>::Error: fmt::Debug,
+    T: Into<String>,
+    I: IntoIterator<Item = T>,
+{
+    rlz.names = names.into_iter().map(Into::into).collect();
+    rlz.name_index.clear();
+    for (i, name) in rlz.names.iter().enumerate() {
+        rlz.name_index
+            .entry(name.clone())
+            .or_insert_with(|| U::try_from(i).unwrap());
+    }
+}
+
+fn internal_merge<U>(
+    mut rlz: RelativeLempelZiv<U>,
+    mut other: RelativeLempelZiv<U>,
+) -> Result<RelativeLempelZiv<U>, MergeError>
+where
+    U: Copy + TryFrom<usize> + TryInto<usize>,
+    <U as TryFrom<usize>>::Error: fmt::Debug,
+    <U as TryInto<usize>>::Error: fmt::Debug,
+{
+    let different = MergeError::DifferentBaseData {
+        len: rlz.base_data.len(),
+        other_len: other.base_data.len(),
+    };
+
+    // Stored raw strings and literals append their bytes to the base,
+    // so both bases only have to agree up to the first of those in the
+    // other encoding. Its appended bytes move to the end of this base.
+    let shared = rlz
+        .base_data
+        .iter()
+        .zip(&other.base_data)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let shared = other
+        .data
+        .iter()
+        .flatten()
+        .filter(|part| matches!(part.kind, FactorKind::StoredRaw | FactorKind::Literal))
+        .map(|part| part.range.0.try_into().unwrap())
+        .fold(shared, usize::min);
+    let offset = rlz.base_data.len();
+    for part in other.data.iter_mut().flatten() {
+        let (start, end): (usize, usize) = (
+            part.range.0.try_into().unwrap(),
+            part.range.1.try_into().unwrap(),
+        );
+        match part.kind {
+            FactorKind::Run(_) => {}
+            FactorKind::StoredRaw | FactorKind::Literal => {
+                part.range = (
+                    to_index(start - shared + offset),
+                    to_index(end - shared + offset),
+                );
+            }
+            FactorKind::Forward | FactorKind::ReverseComplement if end > shared => {
+                return Err(different);
+            }
+            FactorKind::Forward | FactorKind::ReverseComplement => {}
+        }
+    }
+    if shared < other.base_data.len() {
+        rlz.base_data.extend_from_slice(&other.base_data[shared..]);
+        rlz.index = None;
+    }
+
+    let (len, other_len) = (internal_len(&rlz), internal_len(&other));
+    if !rlz.aliases.is_empty() || !other.aliases.is_empty() {
+        let offset = rlz.data.len();
+        let mut aliases = alias_table(&rlz);
+        aliases.extend(alias_table(&other).into_iter().map(|a| a + offset));
+        rlz.aliases = aliases;
+    }
+    if !rlz.reference_ids.is_empty() || !other.reference_ids.is_empty() {
+        let mut ids = mem::take(&mut rlz.reference_ids);
+        ids.resize(len, None);
+        ids.extend(other.reference_ids);
+        ids.resize(len + other_len, None);
+        rlz.reference_ids = ids;
+        if rlz.references.is_empty() {
+            rlz.references = other.references;
+        }
+    }
+    if !rlz.names.is_empty() || !other.names.is_empty() {
+        let mut names = mem::take(&mut rlz.names);
+        names.resize(len, String::new());
+        names.extend(other.names);
+        names.resize(len + other_len, String::new());
+        set_names(&mut rlz, names);
+    }
+    rlz.data.extend(other.data);
+    Ok(rlz)
+}
+
+fn internal_encode_with_reference<U, T>(strings: &[T], reference: &str) -> RelativeLempelZiv<U>
+where
+    U: Copy + TryFrom<usize> + TryInto<usize> + Send,
+    <U as TryFrom<usize>>::Error: fmt::Debug,
+    <U as TryInto<usize>>::Error: fmt::Debug,
+    T: AsRef<str> + Sync,
+{
+    let mut base_string = reference.as_bytes().to_vec();
+    append_missing_bytes(
+        &mut base_string,
+        strings.iter().map(|s| s.as_ref().as_bytes()),
+    );
+    let bytes = str_bytes(strings);
+    check_index_width::<U, _>(base_string.len(), &bytes).unwrap_or_else(|e| panic!("{}", e));
+    let st = create_suffix_tree(base_string);
+    encode_parts(&bytes, &st, None, &EncodeOptions::default())
+}
+
+fn internal_encode_multi_reference<U, T>(references: &[&str], strings: &[T]) -> RelativeLempelZiv<U>
+where
+    U: Copy + TryFrom<usize> + TryInto<usize> + Send,
+    <U as TryFrom<usize>>::Error: fmt::Debug,
+    <U as TryInto<usize>>::Error: fmt::Debug,
+    T: AsRef<str> + Sync,
+{
+    assert!(!references.is_empty(), "At least one reference is needed");
+    let bytes = str_bytes(strings);
+    let references_len = references.iter().map(|r| r.len()).sum();
+    check_index_width::<U, _>(references_len, &bytes).unwrap_or_else(|e| panic!("{}", e));
+    let options = EncodeOptions::default();
+
+    // Every string is factorized against every reference, keeping the
+    // shortest factorization so far and the reference it came from
+    let mut base_data = vec![];
+    let mut ranges = vec![];
+    let mut best: Vec<(usize, EncodedString<U>)> = vec![];
+    for (r, reference) in references.iter().enumerate() {
+        let start = base_data.len();
+        base_data.extend_from_slice(reference.as_bytes());
+        ranges.push((start, base_data.len()));
+
+        let st = create_suffix_tree(reference);
+        let encoded = encode_strings(&bytes, &st, None, &options);
+        if r == 0 {
+            best = encoded.into_iter().map(|e| (0, e)).collect();
+        } else {
+            for (b, e) in best.iter_mut().zip(encoded) {
+                if e.len() < b.1.len() {
+                    *b = (r, e);
+                }
+            }
+        }
+    }
+
+    // The factors are relative to their own reference until they are
+    // moved to where it is in the base data. Literals are still relative
+    // to the string and are moved after the references.
+    let mut rlz = RelativeLempelZiv {
+        base_data,
+        ..Default::default()
+    };
+    for ((r, mut encoded), s) in best.into_iter().zip(&bytes) {
+        let offset = ranges[r].0;
+        for part in encoded.iter_mut().filter(|p| p.kind == FactorKind::Forward) {
+            let start: usize = part.range.0.try_into().unwrap();
+            let end: usize = part.range.1.try_into().unwrap();
+            part.range = (to_index(start + offset), to_index(end + offset));
+        }
+        resolve_literals(&mut rlz.base_data, &mut encoded, s).unwrap_or_else(|e| panic!("{}", e));
+        rlz.data.push(encoded);
+        rlz.reference_ids.push(Some(r));
+    }
+    rlz.references = ranges;
+    rlz
+}
+
+fn internal_encode_streaming<U, I>(reference: &str, strings: I) -> RelativeLempelZiv<U>
+where
+    U: TryFrom<usize>,
+    <U as TryFrom<usize>>::Error: fmt::Debug,
+    I: IntoIterator<Item = String>,
+{
+    let mut found = [false; 256];
+    for &b in reference.as_bytes() {
+        found[b as usize] = true;
+    }
+
+    check_index_width_of_lengths::<U>(reference.len(), std::iter::empty())
+        .unwrap_or_else(|e| panic!("{}", e));
+    let mut st = create_suffix_tree(reference);
+    let mut data = vec![];
+    for (i, s) in strings.into_iter().enumerate() {
+        let mut missing = vec![];
+        for &b in s.as_bytes() {
+            if !found[b as usize] {
+                found[b as usize] = true;
+                missing.push(b);
+            }
+        }
+        if !missing.is_empty() {
+            st.extend(&missing);
+        }
+        check_index_width_of_lengths::<U>(st.bytes().len(), [(i, s.len())])
+            .unwrap_or_else(|e| panic!("{}", e));
+        data.push(encode_string(s.as_bytes(), &st, None, None));
+    }
+
+    data.shrink_to_fit();
+    RelativeLempelZiv {
+        base_data: st.bytes().to_vec(),
+        data,
+        ..Default::default()
+    }
+}
+
+fn internal_estimate_compression<U, T>(reference: &str, sample: &[T], total_size: usize) -> f64
+where
+    U: Copy + TryFrom<usize> + TryInto<usize> + Send,
+    <U as TryFrom<usize>>::Error: fmt::Debug,
+    <U as TryInto<usize>>::Error: fmt::Debug,
+    T: AsRef<str> + Sync,
+{
+    let mut base_string = reference.as_bytes().to_vec();
+    append_missing_bytes(
+        &mut base_string,
+        sample.iter().map(|s| s.as_ref().as_bytes()),
+    );
+    let st = create_suffix_tree(&base_string);
+    let options = EncodeOptions {
+        show_progress: false,
+        ..Default::default()
+    };
+    let factors: usize = encode_strings::<U, _>(&str_bytes(sample), &st, None, &options)
+        .iter()
+        .map(|e| e.len())
+        .sum();
+
+    let sample_size = internal_memory_string_list(sample) as usize;
+    let factors_per_byte = factors as f64 / sample_size.max(1) as f64;
+    let factors = (factors_per_byte * total_size as f64) as usize;
+    let (factor_size, random_access_size) = factor_sizes::<U>();
+    MemoryUsage::new(
+        base_string.len(),
+        factor_size * factors,
+        random_access_size * factors,
+        Some(total_size),
+    )
+    .compression_rate()
+    .unwrap()
+}
+
+#[cfg(feature = "cli")]
+fn base_string_by_name<T: AsRef<str> + Eq>(
+    strings: &[(T, T)],
+    names: &[String],
+    chars: &Option<impl AsRef<str>>,
+) -> Vec<u8> {
+    let mut ref_str = strings
+        .iter()
+        .filter(|(_, n)| names.contains(&String::from(n.as_ref())))
+        .map(|(s, _)| s.as_ref())
+        .collect::<Vec<_>>()
+        .join("")
+        .into_bytes();
+
+    if let Some(s) = chars {
+        ref_str.extend_from_slice(s.as_ref().as_bytes());
+    }
+    append_missing_bytes(
+        &mut ref_str,
+        strings.iter().map(|(s, _)| s.as_ref().as_bytes()),
+    );
+    ref_str
+}
+
+#[cfg(feature = "cli")]
+fn encode_by_reference_merge<U, T>(
+    strings: &[(T, T)],
+    chars: Option<impl AsRef<str>>,
+    seed: u64,
+    options: &EncodeOptions,
+) -> RelativeLempelZiv<U>
+where
+    U: Copy + Ord + TryFrom<usize> + TryInto<usize> + Send,
+    <U as TryFrom<usize>>::Error: fmt::Debug,
+    <U as TryInto<usize>>::Error: fmt::Debug,
+    T: AsRef<str> + Sync + Eq,
+{
+    let raw_strings: Vec<&str> = strings.iter().map(|t| t.0.as_ref()).collect();
+    let names: Vec<&str> = strings.iter().map(|t| t.1.as_ref()).collect();
+
+    let total_size = internal_memory_string_list(&raw_strings);
+
+    // Initially pick a random reference string
+    let mut reference_names: Vec<String> = Vec::new();
+
+    let initial_element = strings.choose(&mut StdRng::seed_from_u64(seed)).unwrap();
+    reference_names.push(String::from(initial_element.1.as_ref()));
+
+    // Loop until best compression rate is found
+    // Starts at infinity so the first encoding is always kept, even if
+    // it does not compress at all, and there is something to return.
+    let mut best_compression_rate = f64::INFINITY;
+    let mut i = 0;
+    let mut best_rlz = None;
+    loop {
+        i += 1;
+
+        // This scope is to uninitialize the base_string and st
+        // asap because we still do computation after, but they
+        // aren't needed for that. Thanks to Rust's borrowing
+        // system, they will be removed from memory after the
         // scope ends.
-        let rlz: RelativeLempelZiv<U> = {
+        let mut rlz: RelativeLempelZiv<U> = {
             let base_string = base_string_by_name(strings, &reference_names, &chars);
             let st = create_suffix_tree(base_string);
-            encode_parts(&raw_strings, &st)
+            encode_parts(&raw_strings, &st, None, options)
         };
 
-        let mut a_vec = Vec::with_capacity(strings.len());
-        for (i, (encoded, name)) in rlz.data.iter().zip(names.iter()).enumerate() {
-            let len = encoded.len();
-            let c_size = internal_memory_single_list(&encoded);
-            let r_size = raw_strings[i].len();
-            let analysis = Analysis::new(len, c_size, r_size, name);
-            a_vec.push(analysis);
-        }
-
-        let analysis_result = AnalysisResult::new(a_vec);
+        let analysis_result = internal_analysis(&rlz, &names);
+        set_names(&mut rlz, names.iter().copied());
         let memory_usage = rlz.memory_footprint(Some(total_size as usize));
         let compressed_rate = memory_usage.compression_rate().unwrap();
 
         if compressed_rate < best_compression_rate {
-            eprintln!(
-                "{} < {} in the {} iteration.",
-                compressed_rate, best_compression_rate, i
+            progress::message(
+                options,
+                format_args!(
+                    "{} < {} in the {} iteration.",
+                    compressed_rate, best_compression_rate, i
+                ),
             );
             best_compression_rate = compressed_rate;
 
-            let worst_ref = String::from(analysis_result.worst_reference_string());
-            reference_names.push(worst_ref);
-
             best_rlz = Some(rlz);
+
+            match analysis_result.worst_reference_string(&reference_names) {
+                Some(worst_ref) => reference_names.push(String::from(worst_ref)),
+                None => {
+                    progress::message(
+                        options,
+                        format_args!("Every string is already part of the reference."),
+                    );
+                    return best_rlz.unwrap();
+                }
+            }
         } else {
-            eprintln!(
-                "{} > {} in the {} iteration.",
-                compressed_rate, best_compression_rate, i
+            progress::message(
+                options,
+                format_args!(
+                    "{} > {} in the {} iteration.",
+                    compressed_rate, best_compression_rate, i
+                ),
             );
-            eprintln!(
-                "Returning best rate {} with the following strings: {:#?}",
-                best_compression_rate, reference_names
+            progress::message(
+                options,
+                format_args!(
+                    "Returning best rate {} with the following strings: {:#?}",
+                    best_compression_rate, reference_names
+                ),
             );
             return best_rlz.expect("Tried to return without actually finding an RLZ");
         }
@@ -261,283 +1428,2726 @@ where
     // 6. If not, go with this.
 }
 
+#[cfg(feature = "cli")]
+fn encode_by_reference_merge_streaming<U, F, I>(
+    mut open: F,
+    chars: Option<impl AsRef<str>>,
+    seed: u64,
+) -> io::Result<RelativeLempelZiv<U>>
+where
+    U: Copy + TryFrom<usize> + TryInto<usize>,
+    <U as TryFrom<usize>>::Error: fmt::Debug,
+    <U as TryInto<usize>>::Error: fmt::Debug,
+    F: FnMut() -> io::Result<I>,
+    I: IntoIterator<Item = io::Result<(String, String)>>,
+{
+    // The first pass finds the total size and every char in the
+    // collection, and picks the initial reference string at random
+    // via reservoir sampling, since the amount of strings is unknown.
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut total_size = 0;
+    let mut count = 0;
+    let mut collection_bytes = [false; 256];
+    let mut initial = None;
+    for item in open()? {
+        let (s, name) = item?;
+        count += 1;
+        total_size += s.len();
+        for b in s.bytes() {
+            collection_bytes[b as usize] = true;
+        }
+        if rng.gen_range(0, count) == 0 {
+            initial = Some((s, name));
+        }
+    }
+    let mut references: Vec<(String, String)> = match initial {
+        Some(reference) => vec![reference],
+        None => return Ok(RelativeLempelZiv::default()),
+    };
+    let collection_bytes: Vec<u8> = (0..=255)
+        .filter(|&b| collection_bytes[b as usize])
+        .collect();
+
+    let mut best_compression_rate = f64::INFINITY;
+    let mut best_rlz = None;
+    loop {
+        let mut base_string: Vec<u8> = references.iter().flat_map(|(s, _)| s.bytes()).collect();
+        if let Some(s) = &chars {
+            base_string.extend_from_slice(s.as_ref().as_bytes());
+        }
+        append_missing_bytes(&mut base_string, std::iter::once(&collection_bytes[..]));
+        let st = create_suffix_tree(base_string);
+
+        // Only the worst compressed string so far is kept around, as it
+        // becomes part of the reference if this pass is an improvement.
+        // Strings that already are part of it are skipped.
+        let mut data = vec![];
+        let mut names = vec![];
+        let mut worst: Option<(f64, String, String)> = None;
+        for item in open()? {
+            let (s, name) = item?;
+            let encoded = encode_string(s.as_bytes(), &st, None, None);
+            let rate = internal_memory_single_list(&encoded) as f64 / s.len() as f64;
+            let is_reference = references.iter().any(|(_, n)| *n == name);
+            names.push(name.clone());
+            if !s.is_empty() && !is_reference && worst.as_ref().is_none_or(|w| rate > w.0) {
+                worst = Some((rate, s, name));
+            }
+            data.push(encoded);
+        }
+
+        let mut rlz = RelativeLempelZiv {
+            base_data: st.bytes().to_vec(),
+            data,
+            ..Default::default()
+        };
+        set_names(&mut rlz, names);
+        drop(st);
+
+        let compressed_rate = internal_memory_footprint(&rlz, Some(total_size))
+            .compression_rate()
+            .unwrap();
+        if compressed_rate >= best_compression_rate {
+            return Ok(best_rlz.unwrap());
+        }
+        best_compression_rate = compressed_rate;
+        best_rlz = Some(rlz);
+        match worst {
+            Some((_, s, name)) => references.push((s, name)),
+            None => return Ok(best_rlz.unwrap()),
+        }
+    }
+}
+
 // Todo: Find ways to improve the base string finding
-// Todo: Change this to bytes, since that simplifies
-// the amount of chars needed.
-fn base_string<T: AsRef<str>>(
+fn base_string<T: AsRef<[u8]>>(
+    strings: &[T],
+    n: Option<Vec<usize>>,
+    chars: Option<impl AsRef<str>>,
+) -> Vec<u8> {
+    base_string_with_report(strings, n, chars).0
+}
+
+// Same as `base_string`, along with the bytes that had to be appended
+// for the strings, see `append_missing_bytes`
+fn base_string_with_report<T: AsRef<[u8]>>(
     strings: &[T],
     n: Option<Vec<usize>>,
     chars: Option<impl AsRef<str>>,
-) -> String {
-    // Select suitable base string
+) -> (Vec<u8>, Vec<(usize, u8)>) {
+    // Select suitable base string, where an empty collection simply
+    // gets an empty base
+    let default_n = if strings.is_empty() { vec![] } else { vec![0] };
     let base_string = n
-        .unwrap_or(vec![0])
+        .unwrap_or(default_n)
         .iter()
         .map(|&x| strings[x].as_ref())
         .collect::<Vec<_>>()
-        .join("");
+        .concat();
     // let base_string = strings[n.unwrap_or(0)].as_ref();
-    // For now assume that reference string contains all chars
-    // If this breaks, just ensure ACGTN are there...
-    let mut s = String::from(base_string);
+    let mut s = base_string;
 
-    // Either appends the characters given from the chars input
-    // or reads through the entire string to ensure that every char is present.
+    // Appends the characters given from the chars input, and then reads
+    // through all the strings anyway to ensure that every byte is present,
+    // since the encoding panics on a byte that is not in the base.
     if let Some(append) = chars {
-        if !append.as_ref().is_empty() {
-            s.push_str(&append.as_ref());
-            return s;
+        s.extend_from_slice(append.as_ref().as_bytes());
+    }
+    let report = append_missing_bytes(&mut s, strings.iter().map(|s| s.as_ref()));
+
+    (s, report)
+}
+
+// Appends every byte of the strings that is not already in the base
+// string, in the order they are first seen. It works on bytes and not
+// chars since the encoding does, so the base may end up with a lone
+// UTF-8 continuation byte, which is fine as it is never decoded alone.
+// Returns the appended bytes along with the index of the string each was
+// first seen in.
+fn append_missing_bytes<'a>(
+    base_string: &mut Vec<u8>,
+    strings: impl Iterator<Item = &'a [u8]>,
+) -> Vec<(usize, u8)> {
+    let mut found = [false; 256];
+    for &b in base_string.iter() {
+        found[b as usize] = true;
+    }
+
+    // Iterate through all strings to ensure all bytes are covered
+    let mut appended = vec![];
+    for (i, string) in strings.enumerate() {
+        for &b in string {
+            if !found[b as usize] {
+                found[b as usize] = true;
+                base_string.push(b);
+                appended.push((i, b));
+            }
         }
     }
+    appended
+}
+
+// Built via the suffix array, which is a lot faster than Ukkonen's
+// algorithm for the large base strings of genome collections.
+fn create_suffix_tree<T: AsRef<[u8]>>(s: T) -> SuffixTree {
+    SuffixTree::from_suffix_array(s.as_ref())
+}
+
+// fn encode_parts<U, T>(strings: &[T], suffix_tree: &SuffixTree) -> RelativeLempelZiv<U>
+// where
+//     U: TryFrom<usize>,
+//     <U as TryFrom<usize>>::Error: fmt::Debug,
+//     T: AsRef<str>,
+// {
+//     // For io::stderr output of progress
+//     let pb = ProgressBar::new(strings.len() as u64);
+
+//     let mut data = vec![];
+//     for s in strings {
+//         pb.inc(1);
+
+//         let mut encoded_string_list: Vec<EncodePart<U>> = vec![];
+//         let mut len = 0;
+
+//         let base_bytes = s.as_ref().as_bytes();
+//         let mut index = 0;
+//         while index < base_bytes.len() {
+//             let len_converted = U::try_from(len).unwrap();
+//             let (start, end) = suffix_tree
+//                 .longest_substring(&base_bytes[index..])
+//                 .expect("Reference string did not contain substring");
+//             index += end - start;
+//             let start_converted = U::try_from(start).unwrap();
+//             let end_converted = U::try_from(end).unwrap();
+//             let next = EncodePart {
+//                 len: len_converted,
+//                 range: (start_converted, end_converted),
+//             };
+//             len += end - start;
+//             encoded_string_list.push(next);
+//         }
+//         encoded_string_list.shrink_to_fit();
+//         data.push(encoded_string_list);
+//     }
+
+//     pb.finish_and_clear();
+
+//     data.shrink_to_fit();
+//     RelativeLempelZiv {
+//         base_data: suffix_tree.bytes().to_vec(),
+//         data,
+//     }
+// }
+
+fn encode_parts<U, T>(
+    strings: &[T],
+    suffix_tree: &SuffixTree,
+    cache: Option<&SubstringCache>,
+    options: &EncodeOptions,
+) -> RelativeLempelZiv<U>
+where
+    U: Copy + TryFrom<usize> + TryInto<usize> + Send,
+    <U as TryFrom<usize>>::Error: fmt::Debug,
+    <U as TryInto<usize>>::Error: fmt::Debug,
+    T: AsRef<[u8]> + Sync,
+{
+    try_encode_parts(strings, suffix_tree, cache, options).unwrap_or_else(|e| panic!("{}", e))
+}
+
+// Literals and stored raw strings append to the base data after the
+// reference, so it can still outgrow U here even if the reference fits
+fn try_encode_parts<U, T>(
+    strings: &[T],
+    suffix_tree: &SuffixTree,
+    cache: Option<&SubstringCache>,
+    options: &EncodeOptions,
+) -> Result<RelativeLempelZiv<U>, EncodeError>
+where
+    U: Copy + TryFrom<usize> + TryInto<usize> + Send,
+    <U as TryFrom<usize>>::Error: fmt::Debug,
+    <U as TryInto<usize>>::Error: fmt::Debug,
+    T: AsRef<[u8]> + Sync,
+{
+    if options.dedup {
+        let (distinct, aliases) = distinct_strings(strings);
+        if distinct.len() < strings.len() {
+            let options = EncodeOptions {
+                dedup: false,
+                ..options.clone()
+            };
+            let mut rlz = try_encode_parts(&distinct, suffix_tree, cache, &options)?;
+            rlz.aliases = aliases;
+            return Ok(rlz);
+        }
+    }
+
+    let mut rlz = RelativeLempelZiv {
+        base_data: suffix_tree.bytes().to_vec(),
+        data: encode_strings(strings, suffix_tree, cache, options),
+        ..Default::default()
+    };
+    for (encoded, s) in rlz.data.iter_mut().zip(strings) {
+        resolve_literals(&mut rlz.base_data, encoded, s.as_ref())?;
+    }
+    if options.min_factor_length > 0 {
+        literal_short_factors(&mut rlz, strings, options.min_factor_length)?;
+    }
+    if options.store_raw {
+        store_raw_strings(&mut rlz, strings)?;
+    }
+    Ok(rlz)
+}
+
+// The distinct strings in the order they first show up, and for every
+// string the index of its distinct one
+fn distinct_strings<T: AsRef<[u8]>>(strings: &[T]) -> (Vec<&[u8]>, Vec<usize>) {
+    let mut first = HashMap::new();
+    let mut distinct = vec![];
+    let aliases = strings
+        .iter()
+        .map(|s| {
+            *first.entry(s.as_ref()).or_insert_with(|| {
+                distinct.push(s.as_ref());
+                distinct.len() - 1
+            })
+        })
+        .collect();
+    (distinct, aliases)
+}
+
+// The number of strings, which is more than the factorizations if some
+// of them are aliases
+fn internal_len<U>(rlz: &RelativeLempelZiv<U>) -> usize {
+    if rlz.aliases.is_empty() {
+        rlz.data.len()
+    } else {
+        rlz.aliases.len()
+    }
+}
+
+// The factors of the i'th string, going through the aliases if there are any
+fn string_factors<U>(rlz: &RelativeLempelZiv<U>, i: usize) -> &EncodedString<U> {
+    if rlz.aliases.is_empty() {
+        &rlz.data[i]
+    } else {
+        &rlz.data[rlz.aliases[i]]
+    }
+}
+
+// The number of bytes every factor of the i'th string copies
+fn factor_lengths<U>(rlz: &RelativeLempelZiv<U>, i: usize) -> impl Iterator<Item = usize> + '_
+where
+    U: Copy + TryInto<usize>,
+    <U as TryInto<usize>>::Error: fmt::Debug,
+{
+    string_factors(rlz, i).iter().map(|part| {
+        let start: usize = part.range.0.try_into().unwrap();
+        let end: usize = part.range.1.try_into().unwrap();
+        end - start
+    })
+}
+
+// The entry of `data` of every string, also when there are no aliases
+fn alias_table<U>(rlz: &RelativeLempelZiv<U>) -> Vec<usize> {
+    if rlz.aliases.is_empty() {
+        (0..rlz.data.len()).collect()
+    } else {
+        rlz.aliases.clone()
+    }
+}
+
+// Replaces the factors of every string that takes more space than its
+// raw bytes plus the single factor pointing at them, so no string ends
+// up larger than that
+fn store_raw_strings<U, T>(rlz: &mut RelativeLempelZiv<U>, strings: &[T]) -> Result<(), EncodeError>
+where
+    U: TryFrom<usize>,
+    <U as TryFrom<usize>>::Error: fmt::Debug,
+    T: AsRef<[u8]>,
+{
+    let factor_size = mem::size_of::<EncodePart<U>>();
+    for (encoded, s) in rlz.data.iter_mut().zip(strings) {
+        let bytes = s.as_ref();
+        if encoded.len() * factor_size > bytes.len() + factor_size {
+            let start = rlz.base_data.len();
+            rlz.base_data.extend_from_slice(bytes);
+            check_base_len::<U>(rlz.base_data.len())?;
+            *encoded = vec![EncodePart {
+                len: to_index(0),
+                range: (to_index(start), to_index(start + bytes.len())),
+                kind: FactorKind::StoredRaw,
+            }];
+        }
+    }
+    Ok(())
+}
+
+// The literals of a string that was just encoded have the range of their
+// bytes in the string itself, since the base data cannot be added to
+// while the strings are encoded in parallel. This moves the bytes to
+// the end of the base data instead. The factors before them are still
+// valid, since the base data before the new bytes stays the same.
+fn resolve_literals<U>(
+    base_data: &mut Vec<u8>,
+    encoded: &mut [EncodePart<U>],
+    bytes: &[u8],
+) -> Result<(), EncodeError>
+where
+    U: Copy + TryFrom<usize> + TryInto<usize>,
+    <U as TryFrom<usize>>::Error: fmt::Debug,
+    <U as TryInto<usize>>::Error: fmt::Debug,
+{
+    for part in encoded.iter_mut().filter(|p| p.kind == FactorKind::Literal) {
+        let from: usize = part.range.0.try_into().unwrap();
+        let to: usize = part.range.1.try_into().unwrap();
+        let start = base_data.len();
+        base_data.extend_from_slice(&bytes[from..to]);
+        check_base_len::<U>(base_data.len())?;
+        part.range = (to_index(start), to_index(start + to - from));
+    }
+    Ok(())
+}
+
+// The base data has to stay addressable by U as bytes are appended to it
+fn check_base_len<U: TryFrom<usize>>(base_len: usize) -> Result<(), EncodeError> {
+    check_index_width_of_lengths::<U>(base_len, std::iter::empty())
+}
+
+// Replaces every stretch of factors shorter than min_len with a single
+// literal factor of their bytes, when that takes less space than the
+// factors. A lone short factor never does.
+fn literal_short_factors<U, T>(
+    rlz: &mut RelativeLempelZiv<U>,
+    strings: &[T],
+    min_len: usize,
+) -> Result<(), EncodeError>
+where
+    U: Copy + TryFrom<usize> + TryInto<usize>,
+    <U as TryFrom<usize>>::Error: fmt::Debug,
+    <U as TryInto<usize>>::Error: fmt::Debug,
+    T: AsRef<[u8]>,
+{
+    let factor_size = mem::size_of::<EncodePart<U>>();
+    let factor_len = |part: &EncodePart<U>| -> usize {
+        let start: usize = part.range.0.try_into().unwrap();
+        let end: usize = part.range.1.try_into().unwrap();
+        end - start
+    };
+    let is_short = |part: &EncodePart<U>| {
+        !matches!(part.kind, FactorKind::Run(_) | FactorKind::Literal) && factor_len(part) < min_len
+    };
+
+    for (encoded, s) in rlz.data.iter_mut().zip(strings) {
+        let bytes = s.as_ref();
+        let mut parts = Vec::with_capacity(encoded.len());
+        let mut k = 0;
+        while k < encoded.len() {
+            let short = encoded[k..]
+                .iter()
+                .take_while(|part| is_short(part))
+                .count();
+            if short == 0 {
+                parts.push(encoded[k]);
+                k += 1;
+                continue;
+            }
+
+            let last = &encoded[k + short - 1];
+            let from: usize = encoded[k].len.try_into().unwrap();
+            let to = last.len.try_into().unwrap() + factor_len(last);
+            if short * factor_size > to - from + factor_size {
+                let start = rlz.base_data.len();
+                rlz.base_data.extend_from_slice(&bytes[from..to]);
+                check_base_len::<U>(rlz.base_data.len())?;
+                parts.push(EncodePart {
+                    len: encoded[k].len,
+                    range: (to_index(start), to_index(start + to - from)),
+                    kind: FactorKind::Literal,
+                });
+            } else {
+                parts.extend_from_slice(&encoded[k..k + short]);
+            }
+            k += short;
+        }
+        parts.shrink_to_fit();
+        *encoded = parts;
+    }
+    Ok(())
+}
+
+// Factorizes every string against the suffix tree, in parallel unless
+// there are only a few of them
+fn encode_strings<U, T>(
+    strings: &[T],
+    suffix_tree: &SuffixTree,
+    cache: Option<&SubstringCache>,
+    options: &EncodeOptions,
+) -> Vec<EncodedString<U>>
+where
+    U: TryFrom<usize> + Send,
+    <U as TryFrom<usize>>::Error: fmt::Debug,
+    T: AsRef<[u8]> + Sync,
+{
+    let progress = Reporter::strings(options, strings.len());
+    progress.phase(Phase::Encoding);
+
+    // The reverse complement strand only needs its own tree when it
+    // is actually matched against
+    let reverse_tree = if options.reverse_complement {
+        Some(SuffixTree::from_suffix_array(&reverse_complement(
+            suffix_tree.bytes(),
+        )))
+    } else {
+        None
+    };
+
+    let encode = |s: &T| {
+        let encoded = match options.runs {
+            Some(run_byte) => encode_with_runs(s.as_ref(), run_byte, |segment| {
+                encode_string(segment, suffix_tree, reverse_tree.as_ref(), cache)
+            }),
+            None => encode_string(s.as_ref(), suffix_tree, reverse_tree.as_ref(), cache),
+        };
+        progress.inc();
+        encoded
+    };
+
+    // Small collections are not worth the thread pool. Rayon's collect
+    // keeps the order of the input, so the results end up at the right
+    // index without any locking.
+    #[cfg(not(feature = "parallel"))]
+    let list = strings.iter().map(encode).collect();
+    #[cfg(feature = "parallel")]
+    let list = if strings.len() < options.parallel_threshold {
+        strings.iter().map(encode).collect()
+    } else if let Some(threads) = options.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .expect("Could not build the thread pool for encoding")
+            .install(|| strings.par_iter().map(encode).collect())
+    } else {
+        strings.par_iter().map(encode).collect()
+    };
+    list
+}
+
+// Factorizes a single string against the suffix tree of the base string.
+// If the tree of the reverse complement of the base is given, each factor
+// is the longest match of the two, preferring the forward one on ties.
+fn encode_string<U>(
+    bytes: &[u8],
+    suffix_tree: &SuffixTree,
+    reverse_tree: Option<&SuffixTree>,
+    cache: Option<&SubstringCache>,
+) -> EncodedString<U>
+where
+    U: TryFrom<usize>,
+    <U as TryFrom<usize>>::Error: fmt::Debug,
+{
+    let mut encoded_string_list: Vec<EncodePart<U>> = vec![];
+    let mut len = 0;
+    let base_len = suffix_tree.bytes().len();
+
+    // Stops once the whole string is consumed, which is also where
+    // longest_substring would return None for the empty rest.
+    let mut index = 0;
+    while index < bytes.len() {
+        let len_converted = to_index(len);
+        let longest_substring = match cache {
+            Some(c) => c.longest_substring(suffix_tree, &bytes[index..]),
+            None => suffix_tree.longest_substring(&bytes[index..]),
+        };
+        let (mut start, mut end) = longest_substring.unwrap_or((0, 0));
+        let mut kind = FactorKind::Forward;
+
+        // Position i of the reverse complement is position base_len - 1 - i
+        // of the base, so a match there maps back to the mirrored range.
+        if let Some(reverse_tree) = reverse_tree {
+            if let Some((r_start, r_end)) = reverse_tree.longest_substring(&bytes[index..]) {
+                if r_end - r_start > end - start {
+                    start = base_len - r_end;
+                    end = base_len - r_start;
+                    kind = FactorKind::ReverseComplement;
+                }
+            }
+        }
+
+        // The byte is not in the base data at all, so it is kept as a
+        // literal along with the unmatched bytes right before it, see
+        // `resolve_literals`
+        if start == end {
+            match encoded_string_list.last_mut() {
+                Some(last) if last.kind == FactorKind::Literal => {
+                    last.range.1 = to_index(index + 1)
+                }
+                _ => encoded_string_list.push(EncodePart {
+                    len: len_converted,
+                    range: (to_index(index), to_index(index + 1)),
+                    kind: FactorKind::Literal,
+                }),
+            }
+            index += 1;
+            len += 1;
+            continue;
+        }
+
+        // The tree never matches its separator, so every factor is
+        // inside of the base data and can be sliced out when decoding
+        debug_assert!(end <= base_len);
+        index += end - start;
+        let start_converted = to_index(start);
+        let end_converted = to_index(end);
+        let next = EncodePart {
+            len: len_converted,
+            range: (start_converted, end_converted),
+            kind,
+        };
+        len += end - start;
+        encoded_string_list.push(next);
+    }
+    encoded_string_list.shrink_to_fit();
+    encoded_string_list
+}
+
+// Runs of the run byte at least this long are stored as run factors.
+// Shorter ones are left to the tree, which can match them along with
+// the bytes around them.
+const MIN_RUN_LEN: usize = 8;
+
+// Stores every long enough run of the byte as a run factor, and encodes
+// the bytes between the runs as usual
+fn encode_with_runs<U>(
+    bytes: &[u8],
+    run_byte: u8,
+    encode: impl Fn(&[u8]) -> EncodedString<usize>,
+) -> EncodedString<U>
+where
+    U: TryFrom<usize>,
+    <U as TryFrom<usize>>::Error: fmt::Debug,
+{
+    let mut parts = vec![];
+    // The factors of a segment start at 0, so they are moved to where
+    // the segment is in the string
+    let push_segment = |parts: &mut Vec<EncodePart<U>>, from: usize, to: usize| {
+        parts.extend(encode(&bytes[from..to]).into_iter().map(|part| {
+            // Unresolved literals point into the segment
+            let shift = if part.kind == FactorKind::Literal {
+                from
+            } else {
+                0
+            };
+            EncodePart {
+                len: to_index(from + part.len),
+                range: (
+                    to_index(shift + part.range.0),
+                    to_index(shift + part.range.1),
+                ),
+                kind: part.kind,
+            }
+        }));
+    };
+
+    // Where the bytes that are not in a run start
+    let mut segment = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != run_byte {
+            i += 1;
+            continue;
+        }
+        let run_end = i + bytes[i..].iter().take_while(|&&b| b == run_byte).count();
+        if run_end - i >= MIN_RUN_LEN {
+            push_segment(&mut parts, segment, i);
+            parts.push(EncodePart {
+                len: to_index(i),
+                range: (to_index(0), to_index(run_end - i)),
+                kind: FactorKind::Run(run_byte),
+            });
+            segment = run_end;
+        }
+        i = run_end;
+    }
+    push_segment(&mut parts, segment, bytes.len());
+    parts.shrink_to_fit();
+    parts
+}
+
+// Converts an offset into the index type of the factors, with a panic
+// that says what went wrong instead of the conversion error
+fn to_index<U>(x: usize) -> U
+where
+    U: TryFrom<usize>,
+{
+    U::try_from(x).unwrap_or_else(|_| {
+        panic!(
+            "Offset {} does not fit in the index type {}, use a wider one for strings or references this large",
+            x,
+            std::any::type_name::<U>()
+        )
+    })
+}
+
+// The bytes at offsets from..to within a factor covering start..end
+// of the base data
+fn factor_bytes(
+    base_data: &[u8],
+    kind: FactorKind,
+    (start, end): (usize, usize),
+    from: usize,
+    to: usize,
+) -> Vec<u8> {
+    match kind {
+        FactorKind::Forward | FactorKind::StoredRaw | FactorKind::Literal => {
+            base_data[start + from..start + to].to_vec()
+        }
+        FactorKind::ReverseComplement => reverse_complement(&base_data[end - to..end - from]),
+        FactorKind::Run(b) => vec![b; to - from],
+    }
+}
+
+// The position in the base data of the byte at the offset within a factor
+// covering start..end, along with the byte itself. Runs are not copied
+// from the base, so their position is usize::MAX.
+fn factor_byte(
+    base_data: &[u8],
+    kind: FactorKind,
+    (start, end): (usize, usize),
+    offset: usize,
+) -> (u8, usize) {
+    match kind {
+        FactorKind::Forward | FactorKind::StoredRaw | FactorKind::Literal => {
+            (base_data[start + offset], start + offset)
+        }
+        FactorKind::ReverseComplement => {
+            (complement(base_data[end - 1 - offset]), end - 1 - offset)
+        }
+        FactorKind::Run(b) => (b, usize::MAX),
+    }
+}
+
+// Encodes the string against the existing base data and adds it
+// at the end of the collection. The suffix tree of the base data
+// is only built on the first push.
+fn internal_push<U>(rlz: &mut RelativeLempelZiv<U>, s: &str)
+where
+    U: Copy + TryFrom<usize> + TryInto<usize>,
+    <U as TryFrom<usize>>::Error: fmt::Debug,
+    <U as TryInto<usize>>::Error: fmt::Debug,
+{
+    let mut encoded_string = encode_string(s.as_bytes(), internal_index(rlz), None, None);
+    resolve_literals(&mut rlz.base_data, &mut encoded_string, s.as_bytes())
+        .unwrap_or_else(|e| panic!("{}", e));
+    if !rlz.aliases.is_empty() {
+        rlz.aliases.push(rlz.data.len());
+    }
+    rlz.data.push(encoded_string);
+    pad_added_strings(rlz);
+}
+
+fn internal_append_many<U, T>(rlz: &mut RelativeLempelZiv<U>, strings: &[T])
+where
+    U: Copy + TryFrom<usize> + TryInto<usize> + Send,
+    <U as TryFrom<usize>>::Error: fmt::Debug,
+    <U as TryInto<usize>>::Error: fmt::Debug,
+    T: AsRef<str> + Sync,
+{
+    let mut encoded = encode_strings(
+        &str_bytes(strings),
+        internal_index(rlz),
+        None,
+        &EncodeOptions::default(),
+    );
+    for (encoded, s) in encoded.iter_mut().zip(strings) {
+        resolve_literals(&mut rlz.base_data, encoded, s.as_ref().as_bytes())
+            .unwrap_or_else(|e| panic!("{}", e));
+    }
+    if !rlz.aliases.is_empty() {
+        rlz.aliases
+            .extend(rlz.data.len()..rlz.data.len() + encoded.len());
+    }
+    rlz.data.extend(encoded);
+    pad_added_strings(rlz);
+}
+
+// Strings added after encoding get an empty name and no reference, like
+// in `internal_merge`, so the names and references stay aligned with the
+// strings when the collection has them
+fn pad_added_strings<U>(rlz: &mut RelativeLempelZiv<U>)
+where
+    U: TryFrom<usize>,
+    <U as TryFrom<usize>>::Error: fmt::Debug,
+{
+    let len = internal_len(rlz);
+    if !rlz.reference_ids.is_empty() {
+        rlz.reference_ids.resize(len, None);
+    }
+    if !rlz.names.is_empty() {
+        for i in rlz.names.len()..len {
+            rlz.names.push(String::new());
+            rlz.name_index
+                .entry(String::new())
+                .or_insert_with(|| U::try_from(i).unwrap());
+        }
+    }
+}
+
+// The suffix tree of the base data, building it if it is not there
+fn internal_index<U>(rlz: &mut RelativeLempelZiv<U>) -> &SuffixTree {
+    let base_data = &rlz.base_data;
+    rlz.index
+        .get_or_insert_with(|| SuffixTree::from_suffix_array(base_data))
+}
+
+fn internal_decode<U>(encoded_data: &RelativeLempelZiv<U>) -> Result<Vec<String>, DecodeError>
+where
+    U: Copy + TryInto<usize>,
+    <U as TryInto<usize>>::Error: fmt::Debug,
+{
+    let mut data = Vec::with_capacity(internal_len(encoded_data));
+
+    for i in 0..internal_len(encoded_data) {
+        data.push(internal_decode_single(encoded_data, i)?);
+    }
+
+    Ok(data)
+}
+
+// The range of the j'th factor of the i'th string, as long as it is
+// within the base data
+fn checked_range<U>(
+    encoded_data: &RelativeLempelZiv<U>,
+    i: usize,
+    j: usize,
+    part: &EncodePart<U>,
+) -> Result<(usize, usize), DecodeError>
+where
+    U: Copy + TryInto<usize>,
+    <U as TryInto<usize>>::Error: fmt::Debug,
+{
+    let base_len = encoded_data.base_data.len();
+    let (start, end) = part.range;
+    let start_as_u = start.try_into().unwrap();
+    let end_as_u = end.try_into().unwrap();
+
+    // A collection of only empty strings legitimately has an empty
+    // base and no factors, which never reaches this point. A factor
+    // reaching outside the base (empty or not) means the structure
+    // is corrupt, so we say exactly where instead of letting the
+    // slice panic on its own.
+    let in_base = matches!(part.kind, FactorKind::Run(_)) || end_as_u <= base_len;
+    if start_as_u > end_as_u || !in_base {
+        return Err(DecodeError::FactorOutOfBounds {
+            string: i,
+            factor: j,
+            range: (start_as_u, end_as_u),
+            base_len,
+        });
+    }
+    Ok((start_as_u, end_as_u))
+}
+
+// Writes the raw bytes of every string, followed by the separator.
+// Forward factors are written straight from the base data.
+fn internal_decode_to_writer<U, W>(
+    encoded_data: &RelativeLempelZiv<U>,
+    sep: &[u8],
+    w: &mut W,
+) -> io::Result<()>
+where
+    U: Copy + TryInto<usize>,
+    <U as TryInto<usize>>::Error: fmt::Debug,
+    W: io::Write,
+{
+    for i in 0..internal_len(encoded_data) {
+        internal_write_string(encoded_data, i, w)?;
+        w.write_all(sep)?;
+    }
+    Ok(())
+}
+
+fn internal_write_string<U, W>(
+    encoded_data: &RelativeLempelZiv<U>,
+    i: usize,
+    w: &mut W,
+) -> io::Result<()>
+where
+    U: Copy + TryInto<usize>,
+    <U as TryInto<usize>>::Error: fmt::Debug,
+    W: io::Write,
+{
+    for (j, part) in string_factors(encoded_data, i).iter().enumerate() {
+        let (start, end) = checked_range(encoded_data, i, j, part)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        match part.kind {
+            FactorKind::Forward | FactorKind::StoredRaw | FactorKind::Literal => {
+                w.write_all(&encoded_data.base_data[start..end])?
+            }
+            FactorKind::ReverseComplement => {
+                w.write_all(&reverse_complement(&encoded_data.base_data[start..end]))?
+            }
+            FactorKind::Run(b) => w.write_all(&vec![b; end - start])?,
+        }
+    }
+    Ok(())
+}
+
+fn internal_decode_single<U>(
+    encoded_data: &RelativeLempelZiv<U>,
+    i: usize,
+) -> Result<String, DecodeError>
+where
+    U: Copy + TryInto<usize>,
+    <U as TryInto<usize>>::Error: fmt::Debug,
+{
+    let bytes = internal_decode_single_bytes(encoded_data, i)?;
+    String::from_utf8(bytes).map_err(|e| DecodeError::InvalidUtf8 {
+        string: i,
+        offset: e.utf8_error().valid_up_to(),
+    })
+}
+
+fn internal_decode_single_bytes<U>(
+    encoded_data: &RelativeLempelZiv<U>,
+    i: usize,
+) -> Result<Vec<u8>, DecodeError>
+where
+    U: Copy + TryInto<usize>,
+    <U as TryInto<usize>>::Error: fmt::Debug,
+{
+    let mut string_parts = vec![];
+
+    for (j, part) in string_factors(encoded_data, i).iter().enumerate() {
+        let (start, end) = checked_range(encoded_data, i, j, part)?;
+        let mut c = factor_bytes(
+            &encoded_data.base_data,
+            part.kind,
+            (start, end),
+            0,
+            end - start,
+        );
+        string_parts.append(&mut c);
+    }
+    Ok(string_parts)
+}
+
+fn internal_decode_annotated<U>(encoded_data: &RelativeLempelZiv<U>, i: U) -> Vec<(u8, usize)>
+where
+    U: Copy + TryInto<usize>,
+    <U as TryInto<usize>>::Error: fmt::Debug,
+{
+    let i_usize = i.try_into().unwrap();
+    let mut annotated = vec![];
+
+    for part in string_factors(encoded_data, i_usize) {
+        let (start, end) = part.range;
+        let start_as_u = start.try_into().unwrap();
+        let end_as_u = end.try_into().unwrap();
+        for offset in 0..end_as_u - start_as_u {
+            annotated.push(factor_byte(
+                &encoded_data.base_data,
+                part.kind,
+                (start_as_u, end_as_u),
+                offset,
+            ));
+        }
+    }
+
+    annotated
+}
+
+// This function could use the `internal_memory_single_list` function, but doesn't
+// because there's no easy way to split up the part of len and range...
+// So instead this is done a bit manually.
+fn internal_memory_footprint<U: Copy>(
+    encoded: &RelativeLempelZiv<U>,
+    raw_size: Option<usize>,
+) -> MemoryUsage {
+    let factorizations: usize = encoded.data.iter().map(|v| v.capacity()).sum();
+    let aliases = internal_memory_single_list(&encoded.aliases)
+        + internal_memory_single_list(&encoded.references)
+        + internal_memory_single_list(&encoded.reference_ids);
+
+    let (factor_size, random_access_size) = factor_sizes::<U>();
+    let size_of_reference = internal_memory_single_list(&encoded.base_data);
+    MemoryUsage::new(
+        size_of_reference,
+        factor_size * factorizations + aliases,
+        random_access_size * factorizations,
+        raw_size,
+    )
+}
+
+// The bytes of an EncodePart that go to the factor itself (the range and
+// kind, plus padding) and to random access (the len, which is only there
+// to binary search for the factor covering an offset)
+fn factor_sizes<U>() -> (usize, usize) {
+    let random_access_size = mem::size_of::<U>();
+    (
+        mem::size_of::<EncodePart<U>>() - random_access_size,
+        random_access_size,
+    )
+}
+
+// --- Memory consumption functions ---
+// Computes the memory consumption of a slice of strings
+fn internal_memory_string_list<T: AsRef<str>>(v: &[T]) -> u64 {
+    v.iter().fold(0, |acc, s| acc + s.as_ref().len() as u64)
+}
+
+// Computes the memory consumption of a Vector of stack-allocated elements
+fn internal_memory_single_list<T: Copy>(v: &Vec<T>) -> usize {
+    v.capacity() * mem::size_of::<T>()
+}
+
+// Computes the memory consumption of a Vector of Vectors of stack-allocated elements
+// fn internal_memory_double_list<T: Copy>(vv: &Vec<Vec<T>>) -> usize {
+//     vv.iter().map(|v| internal_memory_single_list(v)).sum()
+// }
+
+fn internal_analysis<U, T>(rlz: &RelativeLempelZiv<U>, names: &[T]) -> AnalysisResult
+where
+    U: Copy + TryFrom<usize> + TryInto<usize>,
+    <U as TryFrom<usize>>::Error: fmt::Debug,
+    <U as TryInto<usize>>::Error: fmt::Debug,
+    T: AsRef<str>,
+{
+    let a_vec = (0..internal_len(rlz))
+        .map(|i| {
+            let encoded = string_factors(rlz, i);
+            let len = encoded.len();
+            let c_size = internal_memory_single_list(encoded);
+            let r_size = internal_string_len(rlz, U::try_from(i).unwrap());
+            match names.get(i) {
+                Some(name) => Analysis::new(len, c_size, r_size, name),
+                None => Analysis::new(len, c_size, r_size, i.to_string()),
+            }
+        })
+        .collect();
+    AnalysisResult::new(a_vec)
+}
+
+fn internal_string_len<U>(rlz: &RelativeLempelZiv<U>, i: U) -> usize
+where
+    U: Copy + TryInto<usize>,
+    <U as TryInto<usize>>::Error: fmt::Debug,
+{
+    let i_usize = i.try_into().unwrap();
+
+    // The len of every part is the length of the string before it,
+    // so the last part plus its own length is the length of the string
+    match string_factors(rlz, i_usize).last() {
+        None => 0,
+        Some(encode_part) => {
+            let (start, end) = encode_part.range;
+            let len: usize = encode_part.len.try_into().unwrap();
+            let start: usize = start.try_into().unwrap();
+            let end: usize = end.try_into().unwrap();
+            len + (end - start)
+        }
+    }
+}
+
+// Finds the index of the encode part that encompasses the x'th byte
+// of the encoded string.
+fn find_encode_part<U: Ord>(encoded_string: &[EncodePart<U>], x: &U) -> usize {
+    // Binary search on the string to find the corresponding
+    // encode part that encompasses the x'th byte
+    let matching_element = encoded_string.binary_search_by(|probe| probe.len.cmp(x));
+
+    // If the binary search does not find the exact element,
+    // it returns the next position, where it could be inserted.
+    // So because we want the previous one, we can just cover
+    // this use-case via a match.
+    match matching_element {
+        Ok(i) => i,
+        Err(i) => i - 1,
+    }
+}
+
+fn internal_random_access<U>(rlt: &RelativeLempelZiv<U>, i: U, x: U) -> u8
+where
+    U: Copy + Ord + TryInto<usize>,
+    <U as TryInto<usize>>::Error: fmt::Debug,
+{
+    // Converts i and x into usize for later
+    let i_usize = i.try_into().unwrap();
+    let x_usize = x.try_into().unwrap();
+
+    let encoded_string: &EncodedString<U> = string_factors(rlt, i_usize);
+    let index = find_encode_part(encoded_string, &x);
+
+    let encode_part = encoded_string[index];
+    let (start, end) = encode_part.range;
+
+    let len_usize = encode_part.len.try_into().unwrap();
+    let start_usize = start.try_into().unwrap();
+    let end_usize = end.try_into().unwrap();
+    let (byte, _) = factor_byte(
+        &rlt.base_data,
+        encode_part.kind,
+        (start_usize, end_usize),
+        x_usize - len_usize,
+    );
+    byte
+}
+
+fn internal_random_access_range<U>(rlt: &RelativeLempelZiv<U>, i: U, start: U, end: U) -> Vec<u8>
+where
+    U: Copy + Ord + TryInto<usize>,
+    <U as TryInto<usize>>::Error: fmt::Debug,
+{
+    let i_usize = i.try_into().unwrap();
+    let start_usize = start.try_into().unwrap();
+    let end_usize = end.try_into().unwrap();
+    assert!(
+        start_usize <= end_usize,
+        "Range start {} is after its end {}",
+        start_usize,
+        end_usize
+    );
+    let len = internal_string_len(rlt, i);
+    assert!(
+        end_usize <= len,
+        "Range end {} is past the end of string {} of length {}",
+        end_usize,
+        i_usize,
+        len
+    );
+
+    let mut bytes = Vec::with_capacity(end_usize - start_usize);
+    if start_usize == end_usize {
+        return bytes;
+    }
+
+    let encoded_string: &EncodedString<U> = string_factors(rlt, i_usize);
+    let first = find_encode_part(encoded_string, &start);
+
+    // The position in the decoded string we have copied up to
+    let mut pos = start_usize;
+    for encode_part in &encoded_string[first..] {
+        let len_usize: usize = encode_part.len.try_into().unwrap();
+        let (part_start, part_end) = encode_part.range;
+        let part_start: usize = part_start.try_into().unwrap();
+        let part_end: usize = part_end.try_into().unwrap();
+
+        // Only the first part can be entered in the middle, the
+        // rest are copied from their start until `end` is hit.
+        let from = pos - len_usize;
+        let to = (part_end - part_start).min(from + (end_usize - pos));
+        bytes.append(&mut factor_bytes(
+            &rlt.base_data,
+            encode_part.kind,
+            (part_start, part_end),
+            from,
+            to,
+        ));
+        pos += to - from;
+
+        if pos == end_usize {
+            return bytes;
+        }
+    }
+
+    panic!(
+        "Range end {} is past the end of string {} of length {}",
+        end_usize, i_usize, pos
+    );
+}
+
+fn internal_random_access_char<U>(rlz: &RelativeLempelZiv<U>, i: U, char_index: U) -> char
+where
+    U: Copy + TryInto<usize>,
+    <U as TryInto<usize>>::Error: fmt::Debug,
+{
+    let i_usize = i.try_into().unwrap();
+    let char_index = char_index.try_into().unwrap();
+
+    // Every byte that is not a continuation byte starts a char, so the
+    // bytes of the wanted char are the ones from its start up to the next
+    let mut chars = 0;
+    let mut bytes = vec![];
+    'factors: for part in string_factors(rlz, i_usize) {
+        let start = part.range.0.try_into().unwrap();
+        let end = part.range.1.try_into().unwrap();
+        for b in factor_bytes(&rlz.base_data, part.kind, (start, end), 0, end - start) {
+            if b & 0xC0 != 0x80 {
+                if chars > char_index {
+                    break 'factors;
+                }
+                chars += 1;
+            }
+            if chars == char_index + 1 {
+                bytes.push(b);
+            }
+        }
+    }
+
+    match std::str::from_utf8(&bytes) {
+        Ok(s) => s.chars().next().unwrap_or_else(|| {
+            panic!(
+                "Char index {} is outside of string {} of {} chars",
+                char_index, i_usize, chars
+            )
+        }),
+        Err(_) => panic!(
+            "Char {} of string {} is not valid UTF-8",
+            char_index, i_usize
+        ),
+    }
+}
+
+fn internal_validate<U>(rlz: &RelativeLempelZiv<U>) -> Result<(), ValidationError>
+where
+    U: Copy + TryInto<usize>,
+    <U as TryInto<usize>>::Error: fmt::Debug,
+{
+    if let Some((string, &alias)) = rlz
+        .aliases
+        .iter()
+        .enumerate()
+        .find(|(_, &a)| a >= rlz.data.len())
+    {
+        return Err(ValidationError::AliasOutOfBounds {
+            string,
+            alias,
+            entries: rlz.data.len(),
+        });
+    }
+
+    let base_len = rlz.base_data.len();
+    for string in 0..internal_len(rlz) {
+        let mut expected = 0;
+        for (factor, part) in string_factors(rlz, string).iter().enumerate() {
+            let len: usize = part.len.try_into().unwrap();
+            let range: (usize, usize) = (
+                part.range.0.try_into().unwrap(),
+                part.range.1.try_into().unwrap(),
+            );
+            if range.0 > range.1 {
+                return Err(ValidationError::ReversedRange {
+                    string,
+                    factor,
+                    range,
+                });
+            }
+            // The range of a run is its length and not in the base data
+            if !matches!(part.kind, FactorKind::Run(_)) && range.1 > base_len {
+                return Err(ValidationError::FactorOutOfBounds {
+                    string,
+                    factor,
+                    range,
+                    base_len,
+                });
+            }
+            if len != expected {
+                return Err(ValidationError::WrongOffset {
+                    string,
+                    factor,
+                    len,
+                    expected,
+                });
+            }
+            expected += range.1 - range.0;
+        }
+    }
+    Ok(())
+}
+
+fn internal_reference_coverage<U>(rlz: &RelativeLempelZiv<U>) -> Vec<bool>
+where
+    U: Copy + TryInto<usize>,
+    <U as TryInto<usize>>::Error: fmt::Debug,
+{
+    // +1 where a factor starts and -1 where it ends, so the running sum
+    // is the number of factors covering a byte. Run factors copy nothing.
+    let mut delta = vec![0isize; rlz.base_data.len() + 1];
+    for part in rlz.data.iter().flatten() {
+        if let FactorKind::Run(_) = part.kind {
+            continue;
+        }
+        let start: usize = part.range.0.try_into().unwrap();
+        let end: usize = part.range.1.try_into().unwrap();
+        delta[start] += 1;
+        delta[end] -= 1;
+    }
+
+    let mut covering = 0;
+    delta[..rlz.base_data.len()]
+        .iter()
+        .map(|d| {
+            covering += d;
+            covering > 0
+        })
+        .collect()
+}
+
+fn internal_trim_reference<U>(rlz: &mut RelativeLempelZiv<U>)
+where
+    U: Copy + TryFrom<usize> + TryInto<usize>,
+    <U as TryFrom<usize>>::Error: fmt::Debug,
+    <U as TryInto<usize>>::Error: fmt::Debug,
+{
+    let coverage = internal_reference_coverage(rlz);
+
+    // offsets[i] is where byte i of the base data ends up, which is the
+    // number of covered bytes before it. All of the bytes of a factor
+    // are covered, so they stay next to each other.
+    let mut offsets = Vec::with_capacity(coverage.len() + 1);
+    let mut kept = 0;
+    for &covered in &coverage {
+        offsets.push(kept);
+        kept += covered as usize;
+    }
+    offsets.push(kept);
+
+    for part in rlz.data.iter_mut().flatten() {
+        if let FactorKind::Run(_) = part.kind {
+            continue;
+        }
+        let start: usize = part.range.0.try_into().unwrap();
+        let end: usize = part.range.1.try_into().unwrap();
+        part.range = (to_index(offsets[start]), to_index(offsets[end]));
+    }
+    for range in rlz.references.iter_mut() {
+        *range = (offsets[range.0], offsets[range.1]);
+    }
+
+    let mut i = 0;
+    rlz.base_data.retain(|_| {
+        i += 1;
+        coverage[i - 1]
+    });
+    rlz.base_data.shrink_to_fit();
+    // The tree is of the old base data
+    rlz.index = None;
+}
+
+// The ranges of the base data covered by the factors of the i'th string,
+// sorted and merged where they overlap or touch. Run factors copy nothing
+// from the base, so they are left out.
+fn covered_ranges<U>(rlz: &RelativeLempelZiv<U>, i: U) -> Vec<(usize, usize)>
+where
+    U: Copy + TryInto<usize>,
+    <U as TryInto<usize>>::Error: fmt::Debug,
+{
+    let mut ranges: Vec<(usize, usize)> = string_factors(rlz, i.try_into().unwrap())
+        .iter()
+        .filter(|part| !matches!(part.kind, FactorKind::Run(_)))
+        .map(|part| {
+            (
+                part.range.0.try_into().unwrap(),
+                part.range.1.try_into().unwrap(),
+            )
+        })
+        .collect();
+    ranges.sort_unstable();
+
+    let mut merged: Vec<(usize, usize)> = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+fn internal_common_factors<U>(rlz: &RelativeLempelZiv<U>, i: U, j: U) -> Vec<(U, U)>
+where
+    U: Copy + TryFrom<usize> + TryInto<usize>,
+    <U as TryFrom<usize>>::Error: fmt::Debug,
+    <U as TryInto<usize>>::Error: fmt::Debug,
+{
+    let a = covered_ranges(rlz, i);
+    let b = covered_ranges(rlz, j);
+
+    // Both lists are sorted and disjoint, so the overlaps can be found
+    // by walking them side by side, always moving past the one ending first
+    let mut common = vec![];
+    let (mut x, mut y) = (0, 0);
+    while x < a.len() && y < b.len() {
+        let start = a[x].0.max(b[y].0);
+        let end = a[x].1.min(b[y].1);
+        if start < end {
+            common.push((U::try_from(start).unwrap(), U::try_from(end).unwrap()));
+        }
+        if a[x].1 < b[y].1 {
+            x += 1;
+        } else {
+            y += 1;
+        }
+    }
+    common
+}
+
+#[cfg(test)]
+#[macro_use(quickcheck)]
+extern crate quickcheck_macros;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quickcheck::{quickcheck, TestResult};
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn basic() {
+        let test_data = vec!["banana", "anaban", "aaa", "nananananabananana"];
+        println!("Original: {:?}", test_data);
+        let encoded = RelativeLempelZiv::<u8>::encode(&test_data, None, None as Option<&str>);
+        println!("Encoded: {:?}", encoded);
+
+        let decoded = encoded.decode();
+        println!("Decoded:  {:?}", decoded);
+    }
+
+    #[test]
+    fn debug_summary() {
+        let test_data = vec!["banana", "anaban", "aaa"];
+        let encoded = RelativeLempelZiv::<u8>::encode_with_reference(&test_data, "banana");
+        assert_eq!(
+            "RelativeLempelZiv { base_data_len: 6, strings: 3, min_factors: Some(1), \
+             mean_factors: Some(2.0), max_factors: Some(3), \
+             first_strings: [[(0, 6)], [(1, 4), (0, 3)], [(1, 2), (1, 2), (1, 2)]] }",
+            format!("{:?}", encoded)
+        );
+
+        let empty = RelativeLempelZiv::<u8>::default();
+        assert!(format!("{:?}", empty).contains("min_factors: None"));
+    }
+
+    #[test]
+    fn encode_empty_collection() {
+        let test_data: Vec<&str> = vec![];
+        let encoded = RelativeLempelZiv::<u8>::encode(&test_data, None, None as Option<&str>);
+        assert!(encoded.base_data.is_empty());
+        assert!(encoded.is_empty());
+        assert!(encoded.decode().is_empty());
+    }
+
+    #[test]
+    fn decode_all_empty_strings() {
+        let test_data = vec!["", "", ""];
+        let encoded = RelativeLempelZiv::<u8>::encode(&test_data, None, None as Option<&str>);
+        assert!(encoded.base_data.is_empty());
+        assert!(encoded.data.iter().all(|e| e.is_empty()));
+        assert_eq!(test_data, encoded.decode());
+    }
+
+    #[test]
+    #[should_panic(expected = "outside of the base data of length 0")]
+    fn decode_empty_base_with_factors() {
+        let encoded = RelativeLempelZiv::<u8> {
+            base_data: vec![],
+            data: vec![
+                vec![],
+                vec![EncodePart {
+                    len: 0,
+                    range: (0, 3),
+                    kind: FactorKind::Forward,
+                }],
+            ],
+            ..Default::default()
+        };
+        encoded.decode();
+    }
+
+    #[test]
+    fn try_decode_empty_base_with_factors() {
+        let encoded = RelativeLempelZiv::<u8> {
+            base_data: vec![],
+            data: vec![
+                vec![],
+                vec![EncodePart {
+                    len: 0,
+                    range: (0, 3),
+                    kind: FactorKind::Forward,
+                }],
+            ],
+            ..Default::default()
+        };
+        let expected = DecodeError::FactorOutOfBounds {
+            string: 1,
+            factor: 0,
+            range: (0, 3),
+            base_len: 0,
+        };
+        assert_eq!(Err(expected), encoded.try_decode());
+    }
+
+    #[test]
+    fn try_decode_invalid_utf8() {
+        let encoded = RelativeLempelZiv::<u8> {
+            base_data: vec![b'a', b'b', 0xFF],
+            data: vec![
+                vec![EncodePart {
+                    len: 0,
+                    range: (0, 2),
+                    kind: FactorKind::Forward,
+                }],
+                vec![
+                    EncodePart {
+                        len: 0,
+                        range: (0, 2),
+                        kind: FactorKind::Forward,
+                    },
+                    EncodePart {
+                        len: 2,
+                        range: (1, 3),
+                        kind: FactorKind::Forward,
+                    },
+                ],
+            ],
+            ..Default::default()
+        };
+        let expected = DecodeError::InvalidUtf8 {
+            string: 1,
+            offset: 3,
+        };
+        assert_eq!(Err(expected), encoded.try_decode());
+    }
+
+    #[test]
+    fn default_is_empty() {
+        let mut rlz = RelativeLempelZiv::<u8>::default();
+        assert!(rlz.base_data.is_empty());
+        assert!(rlz.decode().is_empty());
+
+        rlz.append("");
+        assert_eq!(vec![""], rlz.decode());
+    }
+
+    #[test]
+    fn with_reference_and_append() {
+        let strings = vec!["banana", "anaban", "", "nananananabananana"];
+        let mut rlz = RelativeLempelZiv::<u8>::with_reference("banana");
+        assert!(rlz.decode().is_empty());
+
+        for s in strings.iter() {
+            rlz.append(s);
+        }
+        assert_eq!(b"banana".to_vec(), rlz.base_data);
+        assert_eq!(strings, rlz.decode());
+    }
+
+    #[test]
+    fn len_and_string_len() {
+        let test_data = vec!["banana", "", "anaban", "nananananabananana"];
+        let encoded = RelativeLempelZiv::<u8>::encode(&test_data, None, None as Option<&str>);
+
+        assert_eq!(test_data.len(), encoded.len());
+        assert!(!encoded.is_empty());
+        for (i, s) in test_data.iter().enumerate() {
+            assert_eq!(s.len(), encoded.string_len(i as u8));
+        }
+        assert!(RelativeLempelZiv::<u8>::default().is_empty());
+    }
+
+    #[test]
+    fn cached_encode_matches_uncached() {
+        let test_data = vec![
+            "banana",
+            "anaban",
+            "banana",
+            "nananananabananana",
+            "anaban",
+            "banana",
+        ];
+        let plain = RelativeLempelZiv::<u8>::encode(&test_data, None, None as Option<&str>);
+        let cache = SubstringCache::new(16, 4);
+        let cached = RelativeLempelZiv::<u8>::encode_with_cache(
+            &test_data,
+            None,
+            None as Option<&str>,
+            &cache,
+        );
+
+        assert_eq!(plain, cached);
+        assert!(cache.hits() > 0);
+
+        // "ACGT" matches in full and is exactly as long as the key, so it
+        // must not answer the longer query "ACGTT" later on
+        let test_data = vec!["ACGTTTTT", "GGACGT", "ACGTT"];
+        let plain = RelativeLempelZiv::<u8>::encode(&test_data, None, None as Option<&str>);
+        let cache = SubstringCache::new(16, 4);
+        let cached = RelativeLempelZiv::<u8>::encode_with_cache(
+            &test_data,
+            None,
+            None as Option<&str>,
+            &cache,
+        );
+
+        assert_eq!(plain, cached);
+    }
+
+    #[test]
+    fn decode_annotated() {
+        let test_data = vec!["banana", "nanab", "aaa"];
+        let encoded = RelativeLempelZiv::<u8>::encode(&test_data, None, None as Option<&str>);
+
+        for (i, s) in test_data.iter().enumerate() {
+            let annotated = encoded.decode_annotated(i as u8);
+            let bytes = annotated.iter().map(|&(b, _)| b).collect::<Vec<_>>();
+            assert_eq!(s.as_bytes(), &bytes[..]);
+
+            // Within a factor the positions follow each other, and every
+            // position points at the byte it claims to have come from
+            let mut annotated = annotated.iter();
+            for part in &encoded.data[i] {
+                let (start, end) = part.range;
+                for pos in start as usize..end as usize {
+                    let &(b, source) = annotated.next().unwrap();
+                    assert_eq!(pos, source);
+                    assert_eq!(encoded.base_data[source], b);
+                }
+            }
+        }
+
+        // "aaa" has to jump back to the same `a` for every byte
+        let sources = encoded
+            .decode_annotated(2)
+            .iter()
+            .map(|&(_, p)| p)
+            .collect::<Vec<_>>();
+        assert_eq!(3, encoded.data[2].len());
+        assert!(sources.windows(2).all(|w| w[0] == w[1]));
+    }
+
+    #[test]
+    fn encode_chars_missing_from_given_chars() {
+        // Lowercase and IUPAC codes that are not part of the given chars
+        let test_data = vec!["ACGTACGT", "ACGTnnRY", "acgt"];
+        let encoded = RelativeLempelZiv::<u8>::encode(&test_data, None, Some("ACGTN"));
+        assert_eq!(test_data, encoded.decode());
+    }
+
+    #[test]
+    fn encode_timed() {
+        let test_data = vec!["banana", "anaban", "nab"];
+        let chars = None as Option<&str>;
+        let (encoded, stats) = RelativeLempelZiv::<u8>::encode_timed(&test_data, None, chars);
+        assert_eq!(RelativeLempelZiv::encode(&test_data, None, chars), encoded);
+        assert_eq!(SuffixTree::new("banana").node_count(), stats.node_count);
+        // banana, ana|ban and na|b
+        assert_eq!(5, stats.factor_count);
+        assert_eq!(
+            stats.base_selection + stats.tree_build + stats.factorization,
+            stats.total()
+        );
+    }
+
+    #[test]
+    fn encode_with_report() {
+        // A lowercase base and a gap leaked into the strings, where the
+        // N is already among the chars
+        let test_data = vec!["ACGTACGT", "ACGTnnRY", "AC-GN", "n"];
+        let (encoded, report) =
+            RelativeLempelZiv::<u8>::encode_with_report(&test_data, None, Some("ACGTN"));
+        assert_eq!(vec![(1, b'n'), (1, b'R'), (1, b'Y'), (2, b'-')], report);
+        assert_eq!(b"ACGTACGTACGTNnRY-".to_vec(), encoded.base_data);
+        assert_eq!(test_data, encoded.decode());
+
+        let (_, report) = RelativeLempelZiv::<u8>::encode_with_report(
+            &test_data,
+            Some(vec![1, 2]),
+            Some("ACGTN"),
+        );
+        assert!(report.is_empty());
+    }
+
+    #[test]
+    fn base_string_with_chars() {
+        let test_data = vec!["ACGT", "GATTACA"];
+        // Without chars only what the strings need is appended, and
+        // nothing here
+        assert_eq!(
+            b"ACGT".to_vec(),
+            base_string(&test_data, None, None as Option<&str>)
+        );
+        // A protein alphabet is appended as it is, even the bytes that
+        // are already in the reference
+        assert_eq!(
+            b"ACGTACDEFGHIKLMNPQRSTVWY".to_vec(),
+            base_string(&test_data, None, Some("ACDEFGHIKLMNPQRSTVWY"))
+        );
+    }
+
+    #[test]
+    fn text_gets_no_acgtn() {
+        let test_data = vec!["the quick brown fox", "jumps over the lazy dog"];
+        let encoded = RelativeLempelZiv::<u8>::encode(&test_data, None, None as Option<&str>);
+        assert!(!encoded.base_data.iter().any(|b| b"ACGTN".contains(b)));
+        assert_eq!(
+            b"the quick brown foxjmpsvlazydg".to_vec(),
+            encoded.base_data
+        );
+        let built: RelativeLempelZiv<u8> = builder::RlzBuilder::new().quiet().encode(&test_data);
+        assert_eq!(encoded.base_data, built.base_data);
+    }
+
+    #[test]
+    fn base_string_covers_bytes_outside_of_acgtn() {
+        // Gaps and stop codons only show up in the strings that are not
+        // the reference, and no chars are given
+        let test_data = vec!["ACGTACGT", "ACG-T*CG", "#"];
+        let base = base_string(&test_data, None, None as Option<&str>);
+        assert!(test_data
+            .iter()
+            .flat_map(|s| s.bytes())
+            .all(|b| base.contains(&b)));
+
+        let encoded = RelativeLempelZiv::<u8>::encode(&test_data, None, None as Option<&str>);
+        assert_eq!(test_data, encoded.decode());
+    }
+
+    #[test]
+    fn base_string_covers_the_bytes_of_multi_byte_chars() {
+        // "é" is C3 A9 and "©" is C2 A9, so only C2 is missing
+        let test_data = vec!["é", "©", "日本"];
+        let base = base_string(&test_data, None, None as Option<&str>);
+        assert_eq!(&[0xC3, 0xA9, 0xC2], &base[..3]);
+        // 日 is E6 97 A5 and 本 is E6 9C AC, so E6 is only appended once
+        assert_eq!(3 + 5, base.len());
+
+        let encoded = RelativeLempelZiv::<u8>::encode(&test_data, None, None as Option<&str>);
+        assert_eq!(test_data, encoded.decode());
+    }
+
+    #[test]
+    #[cfg(feature = "cli")]
+    fn encode_reference_merge_chars_missing_from_references() {
+        let test_data = vec![("ACGTACGT", "a"), ("acgtacgt", "b"), ("ACGTRYRY", "c")];
+        let encoded = RelativeLempelZiv::<u8>::encode_reference_merge(&test_data, Some("ACGT"));
+        let expected = test_data.iter().map(|(s, _)| *s).collect::<Vec<_>>();
+        assert_eq!(expected, encoded.decode());
+    }
+
+    #[test]
+    fn serial_encode_matches_parallel() {
+        let test_data = vec!["banana", "anaban", "aaa", "nananananabananana"];
+        let serial = EncodeOptions {
+            parallel_threshold: usize::MAX,
+            ..Default::default()
+        };
+        let parallel = EncodeOptions {
+            parallel_threshold: 0,
+            show_progress: false,
+            ..Default::default()
+        };
+        let chars = None as Option<&str>;
+        let s = RelativeLempelZiv::<u8>::encode_with_options(&test_data, None, chars, &serial);
+        let p = RelativeLempelZiv::<u8>::encode_with_options(&test_data, None, chars, &parallel);
+
+        assert_eq!(s, p);
+        assert_eq!(test_data, s.decode());
+    }
+
+    #[test]
+    fn encode_with_threads() {
+        let test_data = vec!["banana", "anaban", "aaa", "nananananabananana"];
+        let options = EncodeOptions {
+            parallel_threshold: 0,
+            threads: Some(2),
+            show_progress: false,
+            ..Default::default()
+        };
+        let chars = None as Option<&str>;
+        let threaded =
+            RelativeLempelZiv::<u8>::encode_with_options(&test_data, None, chars, &options);
+        let built: RelativeLempelZiv<u8> = builder::RlzBuilder::new()
+            .quiet()
+            .threads(1)
+            .encode(&test_data);
+
+        assert_eq!(RelativeLempelZiv::encode(&test_data, None, chars), threaded);
+        assert_eq!(threaded, built);
+    }
+
+    #[test]
+    fn reverse_complement_factors() {
+        let reference = "ACGTTGCAAGGCTAGC";
+        // The reverse complement of "GTTGCAAGGCTA"
+        let test_data = vec![reference, "TAGCCTTGCAAC"];
+        let options = EncodeOptions {
+            reverse_complement: true,
+            ..Default::default()
+        };
+        let forward = RelativeLempelZiv::<u8>::encode(&test_data, None, None as Option<&str>);
+        let rlz = RelativeLempelZiv::<u8>::encode_with_options(
+            &test_data,
+            None,
+            None as Option<&str>,
+            &options,
+        );
+
+        assert!(forward.data[1].len() > 1);
+        assert_eq!(1, rlz.data[1].len());
+        assert_eq!(FactorKind::ReverseComplement, rlz.data[1][0].kind);
+        assert_eq!((2, 14), rlz.data[1][0].range);
+
+        assert_eq!(test_data, rlz.decode());
+        assert_eq!(b'T', rlz.random_access(1, 0));
+        assert_eq!(b"GCCTTG".to_vec(), rlz.random_access_range(1, 2, 8));
+        assert_eq!((b'C', 2), rlz.decode_annotated(1)[11]);
+    }
+
+    #[test]
+    fn collapse_runs() {
+        let gap = "N".repeat(20);
+        let test_data = vec![
+            format!("ACGTNNACGT{}TTGCA", gap),
+            format!("{}ACGT", gap),
+            format!("ACGT{}", "N".repeat(500)),
+        ];
+        let rlz: RelativeLempelZiv<u16> = builder::RlzBuilder::new()
+            .quiet()
+            .collapse_runs()
+            .encode(&test_data);
+        assert_eq!(test_data, rlz.decode());
+        assert_eq!(540, rlz.bytes_in_runs());
+        assert_eq!(FactorKind::Run(b'N'), rlz.data[1][0].kind);
+        // The NN of the first string is too short to be a run
+        let kinds: Vec<FactorKind> = rlz.data[0].iter().map(|p| p.kind).collect();
+        assert_eq!(
+            vec![
+                FactorKind::Forward,
+                FactorKind::Run(b'N'),
+                FactorKind::Forward
+            ],
+            kinds
+        );
+
+        assert_eq!(b'N', rlz.random_access(2, 300));
+        assert_eq!(b"TNNN".to_vec(), rlz.random_access_range(2, 3, 7));
+        assert_eq!((b'N', usize::MAX), rlz.decode_annotated(1)[0]);
+        let mut written = vec![];
+        rlz.decode_to_writer(b"\n", &mut written).unwrap();
+        assert_eq!(format!("{}\n", test_data.join("\n")).into_bytes(), written);
+
+        let plain = RelativeLempelZiv::<u16>::encode(&test_data, None, None as Option<&str>);
+        assert_eq!(0, plain.bytes_in_runs());
+    }
+
+    #[test]
+    fn literals_for_disjoint_alphabets() {
+        let mut rlz = RelativeLempelZiv::<u8>::with_reference("ACGT");
+        rlz.push("xyzACGTzz");
+        rlz.append_many(&["qq", "ACGT", "GT"]);
+        assert_eq!(vec!["xyzACGTzz", "qq", "ACGT", "GT"], rlz.decode());
+        assert_eq!(b'z', rlz.random_access(0, 8));
+        assert_eq!(b"zAC".to_vec(), rlz.random_access_range(0, 2, 5));
+
+        // The bytes before and after the match are literals of their own,
+        // and the reference before them is left as it was
+        let kinds: Vec<FactorKind> = rlz.data[0].iter().map(|p| p.kind).collect();
+        assert_eq!(
+            vec![
+                FactorKind::Literal,
+                FactorKind::Forward,
+                FactorKind::Literal
+            ],
+            kinds
+        );
+        assert_eq!(b"ACGTxyzzzqq".to_vec(), rlz.base_data);
+        assert_eq!(
+            vec![(0, 4, 3, FactorKind::Forward)],
+            vec![rlz.factors(0)[1]]
+        );
+
+        let packed = PackedRelativeLempelZiv::new(&rlz);
+        assert_eq!(rlz.decode(), packed.decode());
+    }
+
+    #[quickcheck]
+    fn quickcheck_push_any_bytes(reference: String, xs: Vec<String>) -> bool {
+        let mut rlz = RelativeLempelZiv::<u32>::with_reference(&reference);
+        for s in &xs {
+            rlz.push(s);
+        }
+        rlz.decode() == xs
+    }
+
+    #[test]
+    fn min_factor_length() {
+        // Strings that only share short bits with the reference, the
+        // way noisy reads do
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut random = |len: usize| -> String {
+            (0..len)
+                .map(|_| *b"ACGT".choose(&mut rng).unwrap() as char)
+                .collect()
+        };
+        let mut test_data = vec![random(2000)];
+        test_data.extend((0..20).map(|_| random(500)));
+
+        let plain: RelativeLempelZiv<u32> = builder::RlzBuilder::new().quiet().encode(&test_data);
+        let literal: RelativeLempelZiv<u32> = builder::RlzBuilder::new()
+            .quiet()
+            .min_factor_length(8)
+            .encode(&test_data);
+        assert_eq!(test_data, literal.decode());
+        assert!(literal.data[1]
+            .iter()
+            .any(|p| p.kind == FactorKind::Literal));
+        assert!(literal.data[1].len() < plain.data[1].len());
+        assert!(
+            literal.memory_footprint(None).total_memory()
+                < plain.memory_footprint(None).total_memory()
+        );
+
+        let packed = PackedRelativeLempelZiv::new(&literal);
+        assert_eq!(test_data, packed.decode());
+        assert_eq!(test_data[3].as_bytes()[123], literal.random_access(3, 123));
+    }
+
+    #[quickcheck]
+    fn quickcheck_min_factor_length(xs: Vec<String>, min_len: u8) -> TestResult {
+        if xs.is_empty() {
+            return TestResult::discard();
+        }
+        let options = EncodeOptions {
+            min_factor_length: min_len as usize,
+            show_progress: false,
+            ..Default::default()
+        };
+        let rlz = RelativeLempelZiv::<u32>::encode_with_options(
+            &xs,
+            None,
+            None as Option<&str>,
+            &options,
+        );
+        TestResult::from_bool(rlz.decode() == xs)
+    }
+
+    #[quickcheck]
+    fn quickcheck_collapse_runs(xs: Vec<(String, u8)>) -> TestResult {
+        if xs.is_empty() {
+            return TestResult::discard();
+        }
+        let strings: Vec<String> = xs
+            .iter()
+            .map(|(s, run)| format!("{}{}{}", s, "N".repeat(*run as usize), s))
+            .collect();
+        let options = EncodeOptions {
+            runs: Some(b'N'),
+            show_progress: false,
+            ..Default::default()
+        };
+        let rlz = RelativeLempelZiv::<u32>::encode_with_options(
+            &strings,
+            None,
+            None as Option<&str>,
+            &options,
+        );
+        let lengths = strings
+            .iter()
+            .enumerate()
+            .all(|(i, s)| rlz.string_len(i as u32) == s.len());
+        TestResult::from_bool(rlz.decode() == strings && lengths)
+    }
+
+    #[test]
+    fn store_raw_strings() {
+        let test_data = vec!["ACGTTGCAACGT", "AZCYGXTW", "TTGCAACG"];
+        let options = EncodeOptions {
+            store_raw: true,
+            ..Default::default()
+        };
+        let rlz = RelativeLempelZiv::<u8>::encode_with_options(
+            &test_data,
+            None,
+            None as Option<&str>,
+            &options,
+        );
+
+        // Only the string of nothing but single byte factors is stored
+        // raw, after the reference with the missing bytes
+        assert_eq!(1, rlz.data[1].len());
+        assert_eq!(FactorKind::StoredRaw, rlz.data[1][0].kind);
+        assert_eq!((16, 24), rlz.data[1][0].range);
+        assert_eq!(b"ACGTTGCAACGTZYXWAZCYGXTW", &rlz.base_data[..]);
+        assert_eq!(FactorKind::Forward, rlz.data[2][0].kind);
+
+        assert_eq!(test_data, rlz.decode());
+        assert_eq!(b'Y', rlz.random_access(1, 3));
+        assert_eq!(b"CYG".to_vec(), rlz.random_access_range(1, 2, 5));
+        assert_eq!(test_data, rlz.pack().decode());
+    }
+
+    #[quickcheck]
+    fn quickcheck_store_raw_is_never_larger(strings: Vec<String>) -> bool {
+        let options = EncodeOptions {
+            store_raw: true,
+            show_progress: false,
+            ..Default::default()
+        };
+        let rlz = RelativeLempelZiv::<usize>::encode_with_options(
+            &strings,
+            None,
+            None as Option<&str>,
+            &options,
+        );
+        let factor_size = mem::size_of::<EncodePart<usize>>();
+        rlz.decode() == strings
+            && rlz
+                .data
+                .iter()
+                .zip(&strings)
+                .all(|(e, s)| e.len() * factor_size <= s.len() + factor_size)
+    }
+
+    #[test]
+    fn encode_with_reference() {
+        let test_data = vec!["ACGTTGCA", "TTGCAACG", "GCAX"];
+        let reference = "TTACGTTGCAACGG";
+        let encoded = RelativeLempelZiv::<u8>::encode_with_reference(&test_data, reference);
+
+        // Only the missing `X` is added to the reference
+        assert_eq!(b"TTACGTTGCAACGGX".to_vec(), encoded.base_data);
+        assert_eq!(
+            vec![1, 1, 2],
+            encoded.data.iter().map(|e| e.len()).collect::<Vec<_>>()
+        );
+        assert_eq!(test_data, encoded.decode());
+    }
+
+    #[test]
+    fn encode_multi_reference() {
+        let test_data = vec!["ACGTAC", "TTGGG", "ACGTTTTG", "GX"];
+        let references = ["ACGTACGT", "TTTTGGGG"];
+        let encoded = RelativeLempelZiv::<u8>::encode_multi_reference(&references, &test_data);
+        assert_eq!(test_data, encoded.decode());
+        assert_eq!(
+            vec![b"ACGTACGT".as_ref(), b"TTTTGGGG".as_ref()],
+            encoded.references()
+        );
+        assert_eq!(b'G', encoded.random_access(1, 2));
+
+        // The third string is a literal `AC` and two factors of the second
+        // reference, but five factors of the first
+        assert_eq!(
+            vec![Some(0), Some(1), Some(1), Some(0)],
+            (0..4).map(|i| encoded.reference_of(i)).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![1, 1, 3, 2],
+            encoded.data.iter().map(|e| e.len()).collect::<Vec<_>>()
+        );
+        assert_eq!(b"ACGTACGTTTTTGGGGACX".to_vec(), encoded.base_data);
+    }
+
+    #[quickcheck]
+    fn quickcheck_encode_multi_reference(
+        references: Vec<String>,
+        strings: Vec<String>,
+    ) -> TestResult {
+        if references.is_empty() {
+            return TestResult::discard();
+        }
+        let references: Vec<&str> = references.iter().map(|r| r.as_str()).collect();
+        let encoded = RelativeLempelZiv::<usize>::encode_multi_reference(&references, &strings);
+        TestResult::from_bool(encoded.decode() == strings)
+    }
+
+    #[test]
+    fn encode_streaming() {
+        let test_data = vec!["ACGTTGCA", "GCAX", "XXYAC", "TTGCAACG"];
+        let reference = "TTACGTTGCAACGG";
+        let encoded = RelativeLempelZiv::<u8>::encode_streaming(
+            reference,
+            test_data.iter().map(|s| s.to_string()),
+        );
+
+        // The missing bytes are added as they are first seen, and the
+        // factors of the strings before them still decode
+        assert_eq!(b"TTACGTTGCAACGGXY".to_vec(), encoded.base_data);
+        assert_eq!(test_data, encoded.decode());
+        assert_eq!(
+            vec![1, 2, 3, 1],
+            encoded.data.iter().map(|e| e.len()).collect::<Vec<_>>()
+        );
+        assert!(encoded.names().is_empty());
+    }
+
+    #[test]
+    fn encode_streaming_with_names() {
+        let strings = [("ACGTTGCA", "ecoli"), ("ACGNAGCA", "yeast")];
+        let encoded = RelativeLempelZiv::<u8>::encode_streaming_with_names(
+            "ACGT",
+            strings.iter().map(|(s, n)| (s.to_string(), n.to_string())),
+        );
+        assert_eq!(vec!["ecoli", "yeast"], encoded.names());
+        assert_eq!(
+            Some("ACGNAGCA".to_string()),
+            encoded.extract_by_name("yeast")
+        );
+    }
+
+    #[quickcheck]
+    fn quickcheck_encode_streaming(reference: String, strings: Vec<String>) -> bool {
+        let encoded =
+            RelativeLempelZiv::<usize>::encode_streaming(&reference, strings.iter().cloned());
+        encoded.decode() == strings
+    }
+
+    #[test]
+    fn reference_coverage() {
+        let test_data = vec!["ACG", "CGT", "AAAAAAAAAANN"];
+        let mut encoded = RelativeLempelZiv::<u8>::encode_with_reference(&test_data, "ACGTTTTTNA");
+        // Only the bytes of the first two strings and the N are copied,
+        // the As match the first byte of the reference
+        assert_eq!(
+            vec![true, true, true, true, false, false, false, false, true, false],
+            encoded.reference_coverage()
+        );
+
+        // A run covers nothing of the base data
+        encoded.data[2] = vec![EncodePart {
+            len: 0,
+            range: (0, 12),
+            kind: FactorKind::Run(b'A'),
+        }];
+        assert_eq!(
+            vec![true, true, true, true, false, false, false, false, false, false],
+            encoded.reference_coverage()
+        );
+    }
+
+    #[test]
+    fn trim_reference() {
+        let test_data = vec!["ACG", "CGT", "AAAANN", "GTTX"];
+        let mut encoded = RelativeLempelZiv::<u8>::encode_with_reference(&test_data, "ACGTTTTTNA");
+        encoded.build_index();
+        encoded.trim_reference();
+        assert_eq!(b"ACGTTNX".to_vec(), encoded.base_data);
+        assert!(encoded.reference_coverage().iter().all(|&c| c));
+        assert!(!encoded.is_indexed());
+        assert_eq!(test_data, encoded.decode());
+
+        // The new base data is matched against from here on
+        encoded.push("TTN");
+        assert_eq!(1, encoded.data[4].len());
+        assert_eq!("TTN", encoded.extract(4));
+    }
+
+    #[test]
+    fn trim_multi_reference() {
+        let test_data = vec!["CCGG", "TTAA"];
+        let references = ["AAACCGGAAA", "CCCTTAACCC"];
+        let mut encoded = RelativeLempelZiv::<u8>::encode_multi_reference(&references, &test_data);
+        encoded.trim_reference();
+        assert_eq!(
+            vec![b"CCGG".as_ref(), b"TTAA".as_ref()],
+            encoded.references()
+        );
+        assert_eq!(test_data, encoded.decode());
+    }
+
+    #[quickcheck]
+    fn quickcheck_trim_reference(reference: String, strings: Vec<String>) -> bool {
+        let mut encoded = RelativeLempelZiv::<usize>::encode_with_reference(&strings, &reference);
+        encoded.trim_reference();
+        encoded.decode() == strings
+    }
+
+    #[test]
+    fn factor_ranges() {
+        let test_data = vec!["banana", "anaban", "NNNNNNNNNNba"];
+        let encoded: RelativeLempelZiv<u8> = builder::RlzBuilder::new()
+            .quiet()
+            .reference_indices(vec![0])
+            .collapse_runs()
+            .encode(&test_data);
+        assert_eq!(b"bananaN".to_vec(), encoded.base_data);
+        assert_eq!(
+            vec![1..4, 0..3],
+            encoded.factor_ranges(1).collect::<Vec<_>>()
+        );
+        // The run of Ns is left out
+        assert_eq!(2, encoded.factors(2).len());
+        assert_eq!(vec![0..2], encoded.factor_ranges(2).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn validate() {
+        let test_data = vec!["banana", "anaban", "NNNNNNNNNNba"];
+        let mut encoded: RelativeLempelZiv<u8> = builder::RlzBuilder::new()
+            .quiet()
+            .collapse_runs()
+            .encode(&test_data);
+        assert_eq!(Ok(()), encoded.validate());
+
+        encoded.data[1][1].len = 2;
+        assert_eq!(
+            Err(ValidationError::WrongOffset {
+                string: 1,
+                factor: 1,
+                len: 2,
+                expected: 3,
+            }),
+            encoded.validate()
+        );
+
+        encoded.data[1][1].len = 3;
+        encoded.data[1][0].range = (4, 9);
+        assert!(matches!(
+            encoded.validate(),
+            Err(ValidationError::FactorOutOfBounds {
+                string: 1,
+                factor: 0,
+                ..
+            })
+        ));
+
+        encoded.data[1][0].range = (4, 1);
+        assert!(matches!(
+            encoded.validate(),
+            Err(ValidationError::ReversedRange { string: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn validate_aliases() {
+        let test_data = vec!["banana", "banana"];
+        let mut encoded: RelativeLempelZiv<u8> = builder::RlzBuilder::new()
+            .quiet()
+            .dedup()
+            .encode(&test_data);
+        assert_eq!(Ok(()), encoded.validate());
+        encoded.aliases[1] = 1;
+        assert_eq!(
+            Err(ValidationError::AliasOutOfBounds {
+                string: 1,
+                alias: 1,
+                entries: 1,
+            }),
+            encoded.validate()
+        );
+    }
+
+    #[quickcheck]
+    fn quickcheck_validate(xs: Vec<String>) -> TestResult {
+        if xs.is_empty() {
+            return TestResult::discard();
+        }
+        let encoded = RelativeLempelZiv::<u32>::encode(&xs, None, None as Option<&str>);
+        TestResult::from_bool(encoded.validate().is_ok())
+    }
+
+    #[test]
+    fn merge_shards() {
+        let reference = "ACGTTGCAACGTAGCTAGCT";
+        let first = [("ACGTTGCA", "a"), ("TAGCTAGC", "b")];
+        let second = vec!["GCAACGTA", "CTAGCTAC"];
+        let shard = |strings: Vec<&str>| {
+            RelativeLempelZiv::<u8>::encode_streaming(
+                reference,
+                strings.iter().map(|s| s.to_string()),
+            )
+        };
+        let named = RelativeLempelZiv::<u8>::encode_streaming_with_names(
+            reference,
+            first.iter().map(|(s, n)| (s.to_string(), n.to_string())),
+        );
+
+        let merged = named.merge(shard(second.clone())).unwrap();
+        assert_eq!(
+            vec!["ACGTTGCA", "TAGCTAGC", "GCAACGTA", "CTAGCTAC"],
+            merged.decode()
+        );
+        assert_eq!(vec!["a", "b", "", ""], merged.names());
+        assert_eq!(Some("TAGCTAGC".to_string()), merged.extract_by_name("b"));
+
+        let merged = shard(second.clone()).merge(shard(vec!["ACGT"])).unwrap();
+        assert_eq!(3, merged.len());
+        assert!(merged.names().is_empty());
+
+        // The X gets appended to the base of this shard only
+        let result = shard(second).merge(shard(vec!["ACGX"]));
+        assert_eq!(
+            Some(MergeError::DifferentBaseData {
+                len: 20,
+                other_len: 21
+            }),
+            result.err()
+        );
+    }
+
+    #[test]
+    fn merge_shards_with_appended_bytes() {
+        let reference = "ACGTTGCAACGT";
+        let raw_shard = |s: &str| -> RelativeLempelZiv<u8> {
+            builder::RlzBuilder::new()
+                .quiet()
+                .raw_fallback()
+                .encode(&[reference, s])
+        };
+        let first = raw_shard("GAGAGAGA");
+        let second = raw_shard("TATATATA");
+        assert_eq!(FactorKind::StoredRaw, first.data[1][0].kind);
+        assert_eq!(FactorKind::StoredRaw, second.data[1][0].kind);
+
+        let merged = first.merge(second).unwrap();
+        assert_eq!(
+            vec![reference, "GAGAGAGA", reference, "TATATATA"],
+            merged.decode()
+        );
+        assert_eq!(b"ACGTTGCAACGTGAGAGAGATATATATA", &merged.base_data[..]);
+        assert!(merged.validate().is_ok());
+
+        // Bytes missing from the reference become literals when pushed
+        let mut literal = RelativeLempelZiv::<u8>::with_reference(reference);
+        literal.push("TTGCAX");
+        assert!(literal.data[0]
+            .iter()
+            .any(|p| p.kind == FactorKind::Literal));
+        let merged = merged.merge(literal).unwrap();
+        assert_eq!("TTGCAX", merged.extract(4));
+        assert_eq!("TATATATA", merged.extract(3));
+        assert!(merged.validate().is_ok());
+    }
+
+    #[test]
+    fn push_onto_named_collection() {
+        let mut rlz = RelativeLempelZiv::<u8>::from_collection_with_names(
+            &[("ACGT", "a"), ("ACG", "b")],
+            None,
+            None as Option<&str>,
+        );
+        rlz.push("GTA");
+        rlz.append_many(&["CGT", "TTA"]);
+        assert_eq!(5, rlz.len());
+        assert_eq!(vec!["a", "b", "", "", ""], rlz.names());
+        assert_eq!(Some(2), rlz.index_of(""));
+        assert_eq!(Some("ACG".to_string()), rlz.extract_by_name("b"));
+
+        let mut multi =
+            RelativeLempelZiv::<u8>::encode_multi_reference(&["ACGT", "TTGC"], &["ACG"]);
+        multi.push("TTG");
+        assert_eq!(vec![Some(0), None], multi.reference_ids);
+        assert_eq!(None, multi.reference_of(1));
+    }
+
+    #[test]
+    fn dedup() {
+        let test_data = vec![
+            "ACGTTGCA",
+            "GATTACAGATTACA",
+            "TTGCAACG",
+            "GATTACAGATTACA",
+            "",
+            "GATTACAGATTACA",
+            "",
+        ];
+        let options = EncodeOptions {
+            dedup: true,
+            show_progress: false,
+            ..Default::default()
+        };
+        let chars = None as Option<&str>;
+        let mut rlz =
+            RelativeLempelZiv::<u8>::encode_with_options(&test_data, None, chars, &options);
+        let plain = RelativeLempelZiv::<u8>::encode(&test_data, None, chars);
+
+        assert_eq!(4, rlz.data.len());
+        assert_eq!(3, rlz.dedup_stats());
+        assert_eq!(0, plain.dedup_stats());
+        assert_eq!(test_data, rlz.decode());
+        assert_eq!(b'T', rlz.random_access(5, 2));
+        assert_eq!(14, rlz.string_len(3));
+        assert_eq!(test_data, rlz.pack().decode());
+        assert!(
+            rlz.memory_footprint(None).factorizations_size()
+                < plain.memory_footprint(None).factorizations_size()
+        );
+
+        // What comes after gets its own factorization
+        rlz.push("GCAA");
+        assert_eq!("GCAA", rlz.extract(7));
+        let rlz = rlz.merge(plain).unwrap();
+        assert_eq!(15, rlz.len());
+        assert_eq!("TTGCAACG", rlz.extract(10));
+        assert_eq!(3, rlz.dedup_stats());
+
+        let distinct = vec!["ACGT", "TTGC"];
+        let rlz = RelativeLempelZiv::<u8>::encode_with_options(&distinct, None, chars, &options);
+        assert!(rlz.aliases.is_empty());
+    }
 
-    // Create hash of all current characters
-    let mut found_chars = HashSet::new();
-    for c in s.chars() {
-        found_chars.insert(c);
+    #[test]
+    fn names_lookup() {
+        let strings = vec![
+            ("ACGTTGCA", "ecoli"),
+            ("ACGTAGCA", "yeast"),
+            ("TTGCAACG", "human"),
+            ("ACGT", "ecoli"),
+        ];
+        let rlz = RelativeLempelZiv::<u8>::from_collection_with_names(
+            &strings,
+            None,
+            None as Option<&str>,
+        );
+
+        assert_eq!(Some(1), rlz.index_of("yeast"));
+        assert_eq!("TTGCAACG", rlz.extract(rlz.index_of("human").unwrap()));
+        // The first string wins for duplicated names
+        assert_eq!("ACGTTGCA", rlz.extract(rlz.index_of("ecoli").unwrap()));
+        assert_eq!(None, rlz.index_of("mouse"));
     }
 
-    // Iterate through all strings to ensure all characters are covered
-    let mut chars_to_add = Vec::new();
-    for string in strings {
-        for c in string.as_ref().chars() {
-            if !found_chars.contains(&c) {
-                chars_to_add.push(c);
-                found_chars.insert(c);
-            }
-        }
+    #[test]
+    fn names_survive_serialization() {
+        let strings = vec![("ACGTTGCA", "ecoli"), ("TTGCAACG", "human")];
+        let rlz = RelativeLempelZiv::<u8>::from_collection_with_names(
+            &strings,
+            None,
+            None as Option<&str>,
+        );
+
+        let bytes = bincode::serialize(&rlz).unwrap();
+        let loaded: RelativeLempelZiv<u8> = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(rlz, loaded);
+        let rlz = loaded;
+        assert_eq!(&["ecoli", "human"], rlz.names());
+        assert_eq!(Some(String::from("TTGCAACG")), rlz.extract_by_name("human"));
+        assert_eq!(None, rlz.extract_by_name("mouse"));
     }
 
-    let mut return_string = String::with_capacity(s.len() + chars_to_add.len());
-    return_string.push_str(&s);
-    for c in chars_to_add {
-        return_string.push(c);
+    #[test]
+    #[cfg(feature = "cli")]
+    fn every_named_encode_keeps_the_names() {
+        let strings = vec![
+            ("ACGTTGCA", "ecoli"),
+            ("TTGCAACG", "human"),
+            ("ACGTACGA", "yeast"),
+        ];
+        let named = vec!["ecoli", "human", "yeast"];
+
+        let (rlz, _) =
+            RelativeLempelZiv::<u8>::encode_analysis(&strings, None, None as Option<&str>);
+        assert_eq!(named, rlz.names());
+        assert_eq!(Some(2), rlz.index_of("yeast"));
+
+        let rlz = RelativeLempelZiv::<u8>::encode_reference_merge_seeded(
+            &strings,
+            None as Option<&str>,
+            1793,
+        );
+        assert_eq!(named, rlz.names());
+
+        let bytes = bincode::serialize(&rlz).unwrap();
+        let rlz: RelativeLempelZiv<u8> = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(Some(String::from("TTGCAACG")), rlz.extract_by_name("human"));
     }
 
-    return_string
-}
+    #[test]
+    fn factors() {
+        let test_data = vec!["banana", "anaban", ""];
+        let rlz = RelativeLempelZiv::<u8>::encode(&test_data, None, None as Option<&str>);
+        assert_eq!(vec![(0, 6, 0, FactorKind::Forward)], rlz.factors(0));
+        assert_eq!(
+            vec![
+                (1, 4, 0, FactorKind::Forward),
+                (0, 3, 3, FactorKind::Forward)
+            ],
+            rlz.factors(1)
+        );
+        assert!(rlz.factors(2).is_empty());
 
-fn create_suffix_tree<T: AsRef<str>>(s: T) -> SuffixTree {
-    SuffixTree::new(s)
-}
+        // A run stores its length and not a range of the base
+        let runs: RelativeLempelZiv<u8> = builder::RlzBuilder::new()
+            .quiet()
+            .collapse_runs()
+            .encode(&[format!("ACGT{}", "N".repeat(20))]);
+        assert_eq!(
+            vec![
+                (0, 4, 0, FactorKind::Forward),
+                (0, 20, 4, FactorKind::Run(b'N'))
+            ],
+            runs.factors(0)
+        );
+    }
 
-// fn encode_parts<U, T>(strings: &[T], suffix_tree: &SuffixTree) -> RelativeLempelZiv<U>
-// where
-//     U: TryFrom<usize>,
-//     <U as TryFrom<usize>>::Error: fmt::Debug,
-//     T: AsRef<str>,
-// {
-//     // For io::stderr output of progress
-//     let pb = ProgressBar::new(strings.len() as u64);
+    #[test]
+    fn factor_length_histogram() {
+        let test_data = vec!["banana", "anaban", "nnn", ""];
+        let rlz = RelativeLempelZiv::<u8>::encode(&test_data, None, None as Option<&str>);
+        let histogram: Vec<(usize, usize)> = rlz.factor_length_histogram().into_iter().collect();
+        assert_eq!(vec![(1, 3), (3, 2), (6, 1)], histogram);
 
-//     let mut data = vec![];
-//     for s in strings {
-//         pb.inc(1);
+        assert_eq!(Some(3), rlz.min_factor_length(1));
+        assert_eq!(Some(6), rlz.max_factor_length(0));
+        assert_eq!(Some(1.0), rlz.mean_factor_length(2));
+        assert_eq!(None, rlz.min_factor_length(3));
+        assert_eq!(None, rlz.mean_factor_length(3));
+    }
 
-//         let mut encoded_string_list: Vec<EncodePart<U>> = vec![];
-//         let mut len = 0;
+    #[test]
+    fn common_factors() {
+        let test_data = vec!["banana", "anaban", "nnn"];
+        let rlz = RelativeLempelZiv::<u8>::encode(&test_data, None, None as Option<&str>);
 
-//         let base_bytes = s.as_ref().as_bytes();
-//         let mut index = 0;
-//         while index < base_bytes.len() {
-//             let len_converted = U::try_from(len).unwrap();
-//             let (start, end) = suffix_tree
-//                 .longest_substring(&base_bytes[index..])
-//                 .expect("Reference string did not contain substring");
-//             index += end - start;
-//             let start_converted = U::try_from(start).unwrap();
-//             let end_converted = U::try_from(end).unwrap();
-//             let next = EncodePart {
-//                 len: len_converted,
-//                 range: (start_converted, end_converted),
-//             };
-//             len += end - start;
-//             encoded_string_list.push(next);
-//         }
-//         encoded_string_list.shrink_to_fit();
-//         data.push(encoded_string_list);
-//     }
+        // "anaban" is "ana" (1, 4) and "ban" (0, 3) of "banana"
+        assert_eq!(vec![(0, 4)], rlz.common_factors(0, 1));
+        assert_eq!(rlz.common_factors(0, 1), rlz.common_factors(1, 0));
+        // "nnn" only ever copies "n" (2, 3), which "anaban" also covers
+        assert_eq!(vec![(2, 3)], rlz.common_factors(1, 2));
+        assert_eq!(vec![(0, 6)], rlz.common_factors(0, 0));
 
-//     pb.finish_and_clear();
+        let with_empty =
+            RelativeLempelZiv::<u8>::encode(&["banana", ""], None, None as Option<&str>);
+        assert!(with_empty.common_factors(0, 1).is_empty());
+    }
 
-//     data.shrink_to_fit();
-//     RelativeLempelZiv {
-//         base_data: suffix_tree.string().as_bytes().to_vec(),
-//         data,
-//     }
-// }
+    #[test]
+    fn common_factors_ignore_runs() {
+        let test_data = vec![
+            format!("ACGT{}", "N".repeat(20)),
+            format!("{}TT", "N".repeat(30)),
+            "N".repeat(25),
+        ];
+        let rlz: RelativeLempelZiv<u16> = builder::RlzBuilder::new()
+            .quiet()
+            .collapse_runs()
+            .encode(&test_data);
+        assert!(rlz.data[2]
+            .iter()
+            .all(|p| matches!(p.kind, FactorKind::Run(_))));
 
-fn encode_parts<U, T>(strings: &[T], suffix_tree: &SuffixTree) -> RelativeLempelZiv<U>
-where
-    U: TryFrom<usize> + Send,
-    <U as TryFrom<usize>>::Error: fmt::Debug,
-    T: AsRef<str> + Sync,
-{
-    // For io::stderr output of progress
-    let pb = ProgressBar::new(strings.len() as u64);
-
-    // Prep result list
-    // Need to insert all empty elements in the list, since
-    // with_capacity only ensures that the capacity is there,
-    // not that we can insert at position i in the vector.
-    let mut mutex_list = Vec::with_capacity(strings.len());
-    for _ in strings {
-        mutex_list.push(vec![]);
-    }
-
-    let data = Mutex::new(mutex_list);
-
-    strings.par_iter().enumerate().for_each(|(i, s)| {
-        pb.inc(1);
-
-        let mut encoded_string_list: Vec<EncodePart<U>> = vec![];
-        let mut len = 0;
-
-        let base_bytes = s.as_ref().as_bytes();
-        let mut index = 0;
-        while index < base_bytes.len() {
-            let len_converted = U::try_from(len).unwrap();
-            let (start, end) = suffix_tree
-                .longest_substring(&base_bytes[index..])
-                .expect("Reference string did not contain substring");
-            index += end - start;
-            let start_converted = U::try_from(start).unwrap();
-            let end_converted = U::try_from(end).unwrap();
-            let next = EncodePart {
-                len: len_converted,
-                range: (start_converted, end_converted),
-            };
-            len += end - start;
-            encoded_string_list.push(next);
+        // The runs are not ranges of the base, so only copied bytes count
+        assert!(rlz.common_factors(1, 2).is_empty());
+        assert!(rlz.common_factors(2, 2).is_empty());
+    }
+
+    #[test]
+    fn append_many_matches_appends() {
+        let strings = ["banana", "", "snabs"];
+        let mut many = RelativeLempelZiv::<u8>::with_reference("nabs");
+        many.append_many(&strings);
+        let mut single = RelativeLempelZiv::<u8>::with_reference("nabs");
+        for s in &strings {
+            single.append(s);
         }
-        encoded_string_list.shrink_to_fit();
-        let mut list = data.lock().unwrap();
-        list[i] = encoded_string_list;
-    });
 
-    pb.finish_and_clear();
+        assert_eq!(single, many);
+        assert_eq!(strings.to_vec(), many.decode());
+    }
 
-    let list = data.into_inner().unwrap();
-    RelativeLempelZiv {
-        base_data: suffix_tree.string().as_bytes().to_vec(),
-        data: list,
+    #[test]
+    #[cfg(feature = "parallel")]
+    fn par_decode() {
+        let test_data: Vec<String> = (0..100)
+            .map(|i| format!("ACGT{}", "TG".repeat(i)))
+            .collect();
+        let rlz =
+            RelativeLempelZiv::<u32>::encode(&test_data, Some(vec![99]), None as Option<&str>);
+        assert_eq!(test_data, rlz.par_decode());
     }
-}
 
-fn internal_decode<U>(encoded_data: &RelativeLempelZiv<U>) -> Vec<String>
-where
-    U: Copy + TryInto<usize>,
-    <U as TryInto<usize>>::Error: fmt::Debug,
-{
-    let mut data = Vec::with_capacity(encoded_data.data.len());
+    #[test]
+    #[cfg(feature = "parallel")]
+    #[should_panic(expected = "is not valid UTF-8")]
+    fn par_decode_invalid_utf8() {
+        let test_data: Vec<&[u8]> = vec![b"ACGT", &[0xFF]];
+        RelativeLempelZiv::<u32>::encode_bytes(&test_data, None).par_decode();
+    }
 
-    for encoded_string in &encoded_data.data {
-        let mut string_parts = vec![];
+    #[test]
+    fn decode_to_writer() {
+        let test_data = vec!["banana", "", "anaban"];
+        let rlz = RelativeLempelZiv::<u8>::encode(&test_data, None, None as Option<&str>);
+        let mut out = vec![];
+        rlz.decode_to_writer(b"\n", &mut out).unwrap();
+        assert_eq!(b"banana\n\nanaban\n".to_vec(), out);
 
-        for part in encoded_string {
-            let (start, end) = part.range;
-            let start_as_u = start.try_into().unwrap();
-            let end_as_u = end.try_into().unwrap();
-            let mut c = encoded_data.base_data[start_as_u..end_as_u].to_vec();
-            string_parts.append(&mut c);
-        }
+        let mut out = vec![];
+        rlz.extract_to_writer(2, &mut out).unwrap();
+        assert_eq!(b"anaban".to_vec(), out);
 
-        data.push(String::from_utf8(string_parts).unwrap());
+        // Invalid UTF-8 is written as it is
+        let invalid = RelativeLempelZiv::<u8> {
+            base_data: vec![0xC0, 0x80],
+            data: vec![vec![EncodePart {
+                len: 0,
+                range: (0, 2),
+                kind: FactorKind::Forward,
+            }]],
+            ..Default::default()
+        };
+        let mut out = vec![];
+        invalid.decode_to_writer(b"", &mut out).unwrap();
+        assert_eq!(vec![0xC0, 0x80], out);
     }
 
-    data.shrink_to_fit();
-    data
-}
+    #[test]
+    fn encode_bytes() {
+        let test_data: Vec<&[u8]> = vec![&[0, 1, 2, 0xFF], &[0xFF, 0, 1], &[], &[0xC0, 0x80, 2]];
+        let rlz = RelativeLempelZiv::<u8>::encode_bytes(&test_data, None);
+        assert_eq!(test_data, rlz.decode_bytes());
+        assert!(rlz.try_decode().is_err());
+    }
 
-// This function could use the `internal_memory_single_list` function, but doesn't
-// because there's no easy way to split up the part of len and range...
-// So instead this is done a bit manually.
-fn internal_memory_footprint<U: Copy>(
-    encoded: &RelativeLempelZiv<U>,
-    raw_size: Option<usize>,
-) -> MemoryUsage {
-    let factorizations: usize = encoded.data.iter().map(|v| v.capacity()).sum();
+    #[test]
+    fn decode_to_writer_out_of_bounds() {
+        let rlz = RelativeLempelZiv::<u8> {
+            base_data: vec![],
+            data: vec![vec![EncodePart {
+                len: 0,
+                range: (0, 3),
+                kind: FactorKind::Forward,
+            }]],
+            ..Default::default()
+        };
+        let err = rlz.decode_to_writer(b"\n", &mut vec![]).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, err.kind());
+    }
 
-    let size_of_u = mem::size_of::<U>();
-    let size_of_reference = internal_memory_single_list(&encoded.base_data);
-    let factorizations_size = size_of_u * factorizations;
-    let randon_access_size = size_of_u * factorizations;
+    #[test]
+    fn encode_auto_picks_the_best_covering_reference() {
+        // Only the third string contains both of the others
+        let test_data = vec!["AAAA", "CCCC", "AAAACCCC", "CCAA", "ACAC"];
+        let (rlz, chosen) = RelativeLempelZiv::<u8>::encode_auto(&test_data, test_data.len());
+        assert_eq!(vec![2], chosen);
+        assert_eq!(test_data, rlz.decode());
 
-    MemoryUsage::new(
-        size_of_reference,
-        factorizations_size,
-        randon_access_size,
-        raw_size,
-    )
-}
+        // With a sample of one, only the first string is tried
+        let (_, chosen) = RelativeLempelZiv::<u8>::encode_auto(&test_data, 1);
+        assert_eq!(vec![0], chosen);
 
-// --- Memory consumption functions ---
-// Computes the memory consumption of a slice of strings
-fn internal_memory_string_list<T: AsRef<str>>(v: &[T]) -> u64 {
-    v.iter().fold(0, |acc, s| acc + s.as_ref().len() as u64)
-}
+        let (rlz, chosen) = RelativeLempelZiv::<u8>::encode_auto(&[] as &[&str], 3);
+        assert!(rlz.is_empty() && chosen.is_empty());
+    }
 
-// Computes the memory consumption of a Vector of stack-allocated elements
-fn internal_memory_single_list<T: Copy>(v: &Vec<T>) -> usize {
-    v.capacity() * mem::size_of::<T>()
-}
+    #[test]
+    #[cfg(feature = "cli")]
+    fn encode_reference_merge_stops_when_everything_is_a_reference() {
+        // The single string is the reference from the first iteration,
+        // so there is nothing left to add to it.
+        let strings = vec![("ACGTTGCA", "only")];
+        let rlz = RelativeLempelZiv::<u8>::encode_reference_merge(&strings, None as Option<&str>);
+        assert_eq!(b"ACGTTGCA".to_vec(), rlz.base_data);
+        assert_eq!(vec!["ACGTTGCA"], rlz.decode());
+    }
 
-// Computes the memory consumption of a Vector of Vectors of stack-allocated elements
-// fn internal_memory_double_list<T: Copy>(vv: &Vec<Vec<T>>) -> usize {
-//     vv.iter().map(|v| internal_memory_single_list(v)).sum()
-// }
+    #[test]
+    #[cfg(feature = "cli")]
+    fn encode_reference_merge_seeded_is_deterministic() {
+        let strings: Vec<(String, String)> = (0..10)
+            .map(|i| {
+                (
+                    format!("ACGT{}TTGCA{}", "G".repeat(i), "C".repeat(10 - i)),
+                    format!("s{}", i),
+                )
+            })
+            .collect();
 
-fn internal_random_access<U>(rlt: &RelativeLempelZiv<U>, i: U, x: U) -> u8
-where
-    U: Copy + Ord + TryInto<usize>,
-    <U as TryInto<usize>>::Error: fmt::Debug,
-{
-    // Converts i and x into usize for later
-    let i_usize = i.try_into().unwrap();
-    let x_usize = x.try_into().unwrap();
+        let a = RelativeLempelZiv::<u32>::encode_reference_merge_seeded(
+            &strings,
+            None as Option<&str>,
+            1771,
+        );
+        let b = RelativeLempelZiv::<u32>::encode_reference_merge_seeded(
+            &strings,
+            None as Option<&str>,
+            1771,
+        );
+        assert_eq!(a, b);
+    }
 
-    let encoded_string: &EncodedString<U> = &rlt.data[i_usize];
+    #[test]
+    #[cfg(feature = "cli")]
+    fn encode_reference_merge_reports_to_the_options() {
+        use progress::ProgressCallback;
+        use std::sync::{Arc, Mutex};
 
-    // Binary search on the string to find the corresponding
-    // encode part that encompasses the x'th byte
-    let matching_element = encoded_string.binary_search_by(|probe| probe.len.cmp(&x));
+        let strings: Vec<(String, String)> = (0..10)
+            .map(|i| {
+                (
+                    format!("ACGT{}TTGCA{}", "G".repeat(i), "C".repeat(10 - i)),
+                    format!("s{}", i),
+                )
+            })
+            .collect();
+        let calls = Arc::new(Mutex::new(0));
+        let counter = calls.clone();
+        let options = EncodeOptions {
+            progress: Some(ProgressCallback::new(move |_| {
+                *counter.lock().unwrap() += 1
+            })),
+            ..Default::default()
+        };
 
-    // If the binary search does not find the exact element,
-    // it returns the next position, where it could be inserted.
-    // So because we want the previous one, we can just cover
-    // this use-case via a match.
-    let index = match matching_element {
-        Ok(i) => i,
-        Err(i) => i - 1,
-    };
+        let rlz = RelativeLempelZiv::<u32>::encode_reference_merge_with_options(
+            &strings,
+            None as Option<&str>,
+            1771,
+            &options,
+        );
+        assert!(*calls.lock().unwrap() > 0);
+        assert_eq!(
+            rlz,
+            RelativeLempelZiv::<u32>::encode_reference_merge_seeded(
+                &strings,
+                None as Option<&str>,
+                1771
+            )
+        );
+    }
 
-    let encode_part = encoded_string[index];
-    let (start, _) = encode_part.range;
+    #[test]
+    fn iter_decoded() {
+        let test_data = vec!["banana", "", "anaban", "nab"];
+        let rlz = RelativeLempelZiv::<u8>::encode(&test_data, None, None as Option<&str>);
 
-    let len_usize = encode_part.len.try_into().unwrap();
-    let start_usize = start.try_into().unwrap();
-    let pos = start_usize + (x_usize - len_usize);
-    rlt.base_data[pos]
-}
+        let mut iter = rlz.iter_decoded();
+        assert_eq!(Some(String::from("banana")), iter.next());
+        // The collection can still be queried while iterating
+        assert_eq!(4, rlz.len());
+        assert_eq!("nab", rlz.extract(3));
+        assert_eq!(vec!["", "anaban", "nab"], iter.collect::<Vec<_>>());
+    }
 
-#[cfg(test)]
-#[macro_use(quickcheck)]
-extern crate quickcheck_macros;
+    #[test]
+    fn push_and_extend() {
+        let mut rlz = RelativeLempelZiv::<u8>::with_reference("nabana");
+        rlz.push("banana");
+        rlz.extend(&["anaban", "", "bab"]);
+        rlz.append("nan");
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use quickcheck::{quickcheck, TestResult};
-    use rand::Rng;
+        assert_eq!(b"nabana".to_vec(), rlz.base_data);
+        assert_eq!(vec!["banana", "anaban", "", "bab", "nan"], rlz.decode());
+    }
 
     #[test]
-    fn basic() {
-        let test_data = vec!["banana", "anaban", "aaa", "nananananabananana"];
-        println!("Original: {:?}", test_data);
-        let encoded = RelativeLempelZiv::<u8>::encode(&test_data, None, None as Option<&str>);
-        println!("Encoded: {:?}", encoded);
+    fn encode_drops_the_suffix_tree() {
+        let strings = vec!["banana", "ananas", "nab"];
+        assert!(
+            !RelativeLempelZiv::<u8>::encode(&strings, None, None as Option<&str>).is_indexed()
+        );
+        assert!(!RelativeLempelZiv::<u8>::encode_with_reference(&strings, "nab").is_indexed());
 
-        let decoded = encoded.decode();
-        println!("Decoded:  {:?}", decoded);
+        let mut rlz = RelativeLempelZiv::<u8>::with_reference("nabs");
+        assert!(!rlz.is_indexed());
+        rlz.push("banana");
+        assert!(rlz.is_indexed());
+    }
+
+    #[test]
+    fn build_and_drop_index() {
+        let mut rlz = RelativeLempelZiv::<u8>::with_reference("nabs");
+        rlz.build_index();
+        assert!(rlz.is_indexed());
+        rlz.push("banana");
+        rlz.drop_index();
+        assert!(!rlz.is_indexed());
+
+        // Pushing after dropping the index rebuilds it
+        rlz.push("snab");
+        assert!(rlz.is_indexed());
+        assert_eq!(vec!["banana", "snab"], rlz.decode());
     }
 
     #[test]
@@ -551,6 +4161,69 @@ mod tests {
         assert_eq!(b"n"[0], encoded.random_access(2, 10));
     }
 
+    #[test]
+    fn random_access_char() {
+        let test_data = vec!["東京と京都", "京都から東京へ", "a東b"];
+        let rlz = RelativeLempelZiv::<u8>::encode(&test_data, None, None as Option<&str>);
+        for (i, s) in test_data.iter().enumerate() {
+            for (j, c) in s.chars().enumerate() {
+                assert_eq!(c, rlz.random_access_char(i as u8, j as u8));
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Char index 3 is outside of string 1 of 3 chars")]
+    fn random_access_char_past_end() {
+        let test_data = vec!["東京と京都", "a東b"];
+        let rlz = RelativeLempelZiv::<u8>::encode(&test_data, None, None as Option<&str>);
+        rlz.random_access_char(1, 3);
+    }
+
+    #[test]
+    fn random_access_range() {
+        let test_data = vec!["banana", "ananan", "nananananananv"];
+        let encoded = RelativeLempelZiv::<u8>::encode(&test_data, None, None as Option<&str>);
+
+        // The last string is made up of several factors, so this covers
+        // ranges within a single factor, spanning several factors and
+        // ending exactly at the end of the string.
+        for (i, s) in test_data.iter().enumerate() {
+            for start in 0..=s.len() {
+                for end in start..=s.len() {
+                    let range = encoded.random_access_range(i as u8, start as u8, end as u8);
+                    assert_eq!(&s.as_bytes()[start..end], &range[..]);
+                }
+            }
+        }
+        assert!(encoded.data[2].len() > 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "past the end of string 0")]
+    fn random_access_range_past_end() {
+        let test_data = vec!["banana"];
+        let encoded = RelativeLempelZiv::<u8>::encode(&test_data, None, None as Option<&str>);
+        encoded.random_access_range(0, 2, 7);
+    }
+
+    #[test]
+    #[should_panic(expected = "Range end 8 is past the end of string 0 of length 6")]
+    fn random_access_range_start_past_end() {
+        let test_data = vec!["banana"];
+        let encoded = RelativeLempelZiv::<u8>::encode(&test_data, None, None as Option<&str>);
+        encoded.random_access_range(0, 7, 8);
+    }
+
+    #[test]
+    #[should_panic(expected = "past the end of string 1 of length 0")]
+    fn random_access_range_on_empty_string() {
+        let test_data = vec!["banana", ""];
+        let encoded = RelativeLempelZiv::<u8>::encode(&test_data, None, None as Option<&str>);
+        assert!(encoded.random_access_range(1, 0, 0).is_empty());
+        encoded.random_access_range(1, 0, 1);
+    }
+
     // If this test fails, just ensure that the part about
     // the base string includes every character. This should
     // ensure that this test passes, since it generates completely
@@ -559,7 +4232,7 @@ mod tests {
     fn quickcheck_encode_decode(xs: Vec<String>) -> TestResult {
         // No point in encoding an empty list, so we discard those
         // test inputs
-        if xs.len() == 0 {
+        if xs.is_empty() {
             return TestResult::discard();
         }
 
@@ -567,10 +4240,20 @@ mod tests {
         TestResult::from_bool(res)
     }
 
+    #[quickcheck]
+    fn quickcheck_encode_decode_bytes(xs: Vec<Vec<u8>>) -> TestResult {
+        if xs.is_empty() {
+            return TestResult::discard();
+        }
+
+        let res = xs == RelativeLempelZiv::<u32>::encode_bytes(&xs, None).decode_bytes();
+        TestResult::from_bool(res)
+    }
+
     #[quickcheck]
     #[ignore] // Temp
     fn quickcheck_random_access(xs: Vec<String>) -> TestResult {
-        if xs.len() == 0 {
+        if xs.is_empty() {
             return TestResult::discard();
         }
 
@@ -579,7 +4262,7 @@ mod tests {
 
         // If the chosen string is an empty string, it
         // has no bytes to validate against, so we skip it
-        if xs[index].len() == 0 {
+        if xs[index].is_empty() {
             return TestResult::discard();
         }
 
@@ -590,11 +4273,29 @@ mod tests {
         TestResult::from_bool(res)
     }
 
+    #[quickcheck]
+    fn quickcheck_random_access_range(
+        xs: Vec<String>,
+        i: usize,
+        start: usize,
+        end: usize,
+    ) -> TestResult {
+        // Only windows inside of one of the strings can be checked
+        if i >= xs.len() || start > end || end > xs[i].len() {
+            return TestResult::discard();
+        }
+
+        let encoded = RelativeLempelZiv::<usize>::encode(&xs, None, None as Option<&str>);
+        let res = encoded.extract(i).as_bytes()[start..end]
+            == encoded.random_access_range(i, start, end)[..];
+        TestResult::from_bool(res)
+    }
+
     #[quickcheck]
     fn quickcheck_analysis_encode_decode(xs: Vec<(String, String)>) -> TestResult {
         // No point in encoding an empty list, so we discard those
         // test inputs
-        if xs.len() == 0 || xs.iter().any(|(s, _)| s.is_empty()) {
+        if xs.is_empty() || xs.iter().any(|(s, _)| s.is_empty()) {
             return TestResult::discard();
         }
 
@@ -606,6 +4307,187 @@ mod tests {
         TestResult::from_bool(res)
     }
 
+    // Strings that run to the very end of the reference, where the
+    // separator of the suffix tree is
+    #[test]
+    fn factors_ending_at_the_end_of_the_reference() {
+        let strings = vec!["ACGTTGCA", "TGCA", "GCAGCA", "A", "ACGTTGCAACGTTGCA"];
+        let rlz = RelativeLempelZiv::<u32>::encode(&strings, Some(vec![0]), None as Option<&str>);
+        let base_len = rlz.base_data.len() as u32;
+        assert!(rlz.data.iter().flatten().all(|p| p.range.1 <= base_len));
+        assert!(rlz.data.iter().flatten().any(|p| p.range.1 == base_len));
+        assert_eq!(strings, rlz.decode());
+    }
+
+    #[test]
+    fn memory_footprint_splits_the_factors() {
+        let strings = vec!["abcabc", "cbacba", "aaaa"];
+        let rlz = RelativeLempelZiv::<u32>::encode(&strings, None, None as Option<&str>);
+        let memory = rlz.memory_footprint(Some(16));
+        let factors: usize = rlz.data.iter().map(|e| e.capacity()).sum();
+
+        assert_eq!(rlz.base_data.capacity(), memory.reference_size());
+        assert_eq!(factors * 4, memory.random_access_size());
+        assert_eq!(
+            factors * mem::size_of::<EncodePart<u32>>(),
+            memory.factorizations_size() + memory.random_access_size()
+        );
+        assert_eq!(
+            memory.reference_size() + memory.factorizations_size(),
+            memory.compressed_size()
+        );
+    }
+
+    #[test]
+    fn estimate_compression() {
+        let reference = "ACGTTGCAAGCTTGACCATG".repeat(20);
+        let strings: Vec<String> = (0..8)
+            .map(|i| format!("{}T{}", &reference[..i * 40], &reference[i * 40 + 1..]))
+            .collect();
+
+        // With the whole collection as the sample, it is the actual rate
+        let rlz = RelativeLempelZiv::<u32>::encode_with_reference(&strings, &reference);
+        let raw_size = strings.iter().map(|s| s.len()).sum();
+        let actual = rlz
+            .memory_footprint(Some(raw_size))
+            .compression_rate()
+            .unwrap();
+        let estimate = RelativeLempelZiv::<u32>::estimate_compression(&reference, &strings);
+        assert!(
+            (actual - estimate).abs() < 0.01,
+            "{} vs {}",
+            actual,
+            estimate
+        );
+
+        // The reference takes up less of a bigger collection
+        let sample: Vec<&str> = strings.iter().take(2).map(|s| s.as_str()).collect();
+        let small = RelativeLempelZiv::<u32>::estimate_compression(&reference, &sample);
+        let large = RelativeLempelZiv::<u32>::estimate_compression_of_size(
+            &reference,
+            &sample,
+            raw_size * 100,
+        );
+        assert!(large < small);
+    }
+
+    #[test]
+    fn encode_with_u16() {
+        let strings = vec!["ACGTACGTTTGA", "TTGAACGT", "GGGACGTACG"];
+        let rlz = RelativeLempelZiv::<u16>::encode(&strings, None, None as Option<&str>);
+        assert_eq!(strings, rlz.decode());
+        assert!(mem::size_of::<EncodePart<u16>>() < mem::size_of::<EncodePart<u32>>());
+    }
+
+    #[test]
+    #[should_panic(expected = "too large for the index type")]
+    fn encode_with_u16_panics_on_a_large_reference() {
+        let reference = "ACGT".repeat(20_000);
+        let strings = vec![reference.clone(), reference];
+        RelativeLempelZiv::<u16>::encode(&strings, Some(vec![0]), None as Option<&str>);
+    }
+
+    #[test]
+    #[should_panic(expected = "Reference of 400 bytes is too large")]
+    fn encode_with_reference_checks_the_index_width() {
+        let reference = "ACGT".repeat(100);
+        RelativeLempelZiv::<u8>::encode_with_reference(&["ACGT"], &reference);
+    }
+
+    #[test]
+    #[should_panic(expected = "Reference of 320 bytes is too large")]
+    fn encode_multi_reference_checks_the_index_width() {
+        let reference = "ACGT".repeat(40);
+        RelativeLempelZiv::<u8>::encode_multi_reference(&[&reference, &reference], &["ACGT"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Reference of 400 bytes is too large")]
+    fn encode_streaming_checks_the_index_width() {
+        let reference = "ACGT".repeat(100);
+        RelativeLempelZiv::<u8>::encode_streaming(&reference, vec![String::from("ACGT")]);
+    }
+
+    #[test]
+    #[should_panic(expected = "String 1 of 300 bytes is too large")]
+    fn encode_streaming_checks_every_string() {
+        RelativeLempelZiv::<u8>::encode_streaming(
+            "ACGT",
+            vec![String::from("ACGT"), "A".repeat(300)],
+        );
+    }
+
+    #[test]
+    fn try_encode_checks_the_index_width() {
+        assert_eq!(255, max_index::<u8>());
+        assert_eq!(65_535, max_index::<u16>());
+        assert_eq!(usize::MAX, max_index::<usize>());
+
+        let reference = "ACGT".repeat(100);
+        let strings = vec![reference.clone(), String::from("ACGT")];
+        let err =
+            RelativeLempelZiv::<u8>::try_encode(&strings, Some(vec![0]), None as Option<&str>)
+                .unwrap_err();
+        assert_eq!(EncodeError::ReferenceTooLarge { len: 400, max: 255 }, err);
+
+        let err =
+            RelativeLempelZiv::<u8>::try_encode(&strings, Some(vec![1]), None as Option<&str>)
+                .unwrap_err();
+        assert_eq!(
+            EncodeError::StringTooLarge {
+                string: 0,
+                len: 400,
+                max: 255
+            },
+            err
+        );
+
+        let rlz =
+            RelativeLempelZiv::<u16>::try_encode(&strings, Some(vec![0]), None as Option<&str>)
+                .unwrap();
+        assert_eq!(strings, rlz.decode());
+    }
+
+    #[test]
+    fn analysis_of_plain_strings() {
+        let strings = vec!["abcabc", "abcabcabc", "xyz"];
+        let rlz = RelativeLempelZiv::<u32>::encode(&strings, None, None as Option<&str>);
+        let result = rlz.analysis();
+
+        assert_eq!(3, result.list().len());
+        for a in result.list() {
+            let i: usize = a.name().parse().unwrap();
+            assert_eq!(strings[i].len(), a.r_size());
+            assert_eq!(rlz.data[i].len(), a.len());
+            assert_eq!(a.c_size() as f64 / a.r_size() as f64, a.compressed_rate());
+        }
+        // Sorted from the worst compression rate
+        assert!(result
+            .list()
+            .windows(2)
+            .all(|w| w[0].compressed_rate() >= w[1].compressed_rate()));
+    }
+
+    #[test]
+    fn analysis_uses_the_names() {
+        let strings = vec![("abcabc", "first"), ("xyz", "second")];
+        let rlz = RelativeLempelZiv::<u32>::from_collection_with_names(
+            &strings,
+            None,
+            None as Option<&str>,
+        );
+        let (_, expected) =
+            RelativeLempelZiv::<u32>::encode_analysis(&strings, None, None as Option<&str>);
+
+        let names = |r: &AnalysisResult| {
+            r.list()
+                .iter()
+                .map(|a| a.name().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(&expected), names(&rlz.analysis()));
+    }
+
     #[test]
     fn testing() {
         println!("Analysis size: {}", mem::size_of::<Analysis>());
@@ -46,16 +46,14 @@ impl MemoryUsage {
   }
 
   pub fn compression_rate(&self) -> Option<f64> {
-    match self.raw_size {
-      None => None,
-      Some(raw_size) => Some(self.total_memory() as f64 / raw_size as f64),
-    }
+    self
+      .raw_size
+      .map(|raw_size| self.total_memory() as f64 / raw_size as f64)
   }
 
   pub fn compression_rate_without_ra(&self) -> Option<f64> {
-    match self.raw_size {
-      None => None,
-      Some(raw_size) => Some(self.compressed_size() as f64 / raw_size as f64),
-    }
+    self
+      .raw_size
+      .map(|raw_size| self.compressed_size() as f64 / raw_size as f64)
   }
 }
@@ -0,0 +1,61 @@
+// Knobs for how the encoding is carried out. Apart from
+// `reverse_complement`, `store_raw`, `dedup`, `runs` and
+// `min_factor_length`, none of these change the result, only how it is
+// computed.
+use super::progress::ProgressCallback;
+
+#[derive(Debug, Clone)]
+pub struct EncodeOptions {
+  // Collections with fewer strings than this are encoded serially,
+  // since spinning up rayon's thread pool costs more than it saves
+  // for a handful of strings. 0 always encodes in parallel.
+  pub parallel_threshold: usize,
+  // Runs the parallel encoding in a pool of its own with this many
+  // threads instead of rayon's global pool, which uses every core. For
+  // when the encoding shares the machine with other work. Without the
+  // `parallel` feature everything is encoded serially anyway.
+  pub threads: Option<usize>,
+  // Shows the progress of the encoding on stderr. Turn it off when
+  // using the library from something that is not a terminal. Has no
+  // effect when there is a `progress` callback.
+  pub show_progress: bool,
+  // Gets the progress of the encoding instead of the bar on stderr
+  pub progress: Option<ProgressCallback>,
+  // Also matches against the reverse complement of the base data, for
+  // DNA collections where sequences can come from either strand. This
+  // builds a second suffix tree, doubling the memory while encoding.
+  pub reverse_complement: bool,
+  // Stores a string as it is when its factors would take more space
+  // than its bytes, see `FactorKind::StoredRaw`. For mixed collections
+  // where some strings have little to do with the reference.
+  pub store_raw: bool,
+  // Encodes strings that are byte for byte identical only once, and
+  // keeps the rest as aliases of the first, see `dedup_stats`.
+  // `data` then only has the distinct factorizations.
+  pub dedup: bool,
+  // Stores the runs of this byte as single run factors instead of
+  // matching them against the base, like the long runs of N in DNA
+  // assemblies. Only runs of at least MIN_RUN_LEN bytes are, see
+  // `FactorKind::Run` and `bytes_in_runs`.
+  pub runs: Option<u8>,
+  // Stretches of factors shorter than this are stored as the bytes
+  // they copy when that is smaller, see `FactorKind::Literal`. For noisy
+  // strings that only match in bits and pieces. 0 leaves them all be.
+  pub min_factor_length: usize,
+}
+
+impl Default for EncodeOptions {
+  fn default() -> Self {
+    EncodeOptions {
+      parallel_threshold: 16,
+      threads: None,
+      show_progress: true,
+      progress: None,
+      reverse_complement: false,
+      store_raw: false,
+      dedup: false,
+      runs: None,
+      min_factor_length: 0,
+    }
+  }
+}
@@ -0,0 +1,584 @@
+// A more compact form of the factorizations, for collections that are
+// done being encoded and only need to be decoded and accessed.
+//
+// Every factor in `RelativeLempelZiv` is three U's, but the offset into
+// the string is the sum of the lengths before it and the length is
+// usually small. Here the starts in the base data are bit-packed with
+// just enough bits for the base, and the lengths are variable-byte
+// encoded. The offsets needed for random access are only kept for every
+// SAMPLE_RATE'th factor, so finding a byte is a binary search over those
+// followed by a scan over at most SAMPLE_RATE factors.
+//
+// For collections of very similar strings there is also a delta mode,
+// where the start of a factor is stored as the zig-zag varint distance
+// from where the previous factor ended. After a mismatch the next match
+// usually continues right where the previous one left off, so most of
+// them take a single byte. The decoder keeps track of the previous end
+// anyway, which makes this smaller than the delta between the starts.
+//
+// The samples are only needed for random access, so they can be dropped
+// with `without_random_access` when the strings are mostly decoded in
+// full. That is a separate type, where random access has to scan the
+// factors from the start of the string to find the offsets, which is
+// O(factors) per byte instead of O(log(factors / SAMPLE_RATE) + SAMPLE_RATE).
+use serde::{Deserialize, Serialize};
+use std::convert::TryInto;
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem;
+
+use super::error::DecodeError;
+use super::memory_usage::MemoryUsage;
+use super::options::EncodeOptions;
+use super::{
+  factor_byte, factor_bytes, internal_encode, internal_len, str_bytes, string_factors, FactorKind,
+  RelativeLempelZiv,
+};
+
+const SAMPLE_RATE: usize = 32;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct PackedRelativeLempelZiv<A = WithRandomAccess> {
+  pub base_data: Vec<u8>,
+  strings: Vec<PackedString>,
+  access: PhantomData<A>,
+}
+
+// Whether the samples for random access are kept
+#[derive(Debug)]
+pub struct WithRandomAccess;
+#[derive(Debug)]
+pub struct WithoutRandomAccess;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PackedString {
+  factors: usize,
+  len: usize,
+  starts: Starts,
+  // The length of every factor shifted left by two, with the kind in
+  // the lowest two bits, as varints. In delta mode every length is
+  // followed by the delta of the start. The start of a run is its byte.
+  varints: Vec<u8>,
+  // For every SAMPLE_RATE'th factor, the offset in the string where it
+  // starts and where its length is in `varints`
+  samples: Vec<(usize, usize)>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum Starts {
+  Packed(BitPacked),
+  // The deltas are in `varints`, and the first factor after a sample
+  // is relative to 0 so the scan can begin there
+  Delta,
+}
+
+impl PackedRelativeLempelZiv {
+  pub fn new<U>(rlz: &RelativeLempelZiv<U>) -> Self
+  where
+    U: Copy + TryInto<usize>,
+    <U as TryInto<usize>>::Error: fmt::Debug,
+  {
+    internal_new(rlz, false)
+  }
+
+  // Same as `new`, but with the starts stored as deltas, see above
+  pub fn new_delta<U>(rlz: &RelativeLempelZiv<U>) -> Self
+  where
+    U: Copy + TryInto<usize>,
+    <U as TryInto<usize>>::Error: fmt::Debug,
+  {
+    internal_new(rlz, true)
+  }
+
+  // Encodes the strings like `RelativeLempelZiv::encode` and packs the
+  // result in delta mode
+  pub fn encode_delta<T: AsRef<str> + Sync>(
+    strings: &[T],
+    n: Option<Vec<usize>>,
+    chars: Option<impl AsRef<str>>,
+  ) -> Self {
+    let rlz: RelativeLempelZiv<usize> = internal_encode(
+      &str_bytes(strings),
+      n,
+      chars,
+      None,
+      &EncodeOptions::default(),
+    );
+    Self::new_delta(&rlz)
+  }
+
+  // Drops the samples, after which random access scans from the start
+  // of the string
+  pub fn without_random_access(self) -> PackedRelativeLempelZiv<WithoutRandomAccess> {
+    let strings = self
+      .strings
+      .into_iter()
+      .map(|s| PackedString {
+        samples: vec![],
+        ..s
+      })
+      .collect();
+    PackedRelativeLempelZiv {
+      base_data: self.base_data,
+      strings,
+      access: PhantomData,
+    }
+  }
+
+  // Gets the x'th byte from the i'th string
+  pub fn random_access(&self, i: usize, x: usize) -> u8 {
+    self.check_offset(i, x);
+
+    // The last sample at or before x, from where the factor covering x
+    // is at most SAMPLE_RATE factors away
+    let string = &self.strings[i];
+    let sample = string.samples.partition_point(|&(offset, _)| offset <= x) - 1;
+    self.byte_from(i, x, sample, string.samples[sample].0)
+  }
+}
+
+impl PackedRelativeLempelZiv<WithoutRandomAccess> {
+  // Gets the x'th byte from the i'th string, by going through the
+  // factors from the first one since there are no samples to jump from
+  pub fn random_access(&self, i: usize, x: usize) -> u8 {
+    self.check_offset(i, x);
+    self.byte_from(i, x, 0, 0)
+  }
+}
+
+impl<A> PackedRelativeLempelZiv<A> {
+  fn check_offset(&self, i: usize, x: usize) {
+    let len = self.strings[i].len;
+    assert!(
+      x < len,
+      "Offset {} is outside of string {} of length {}",
+      x,
+      i,
+      len
+    );
+  }
+
+  // The x'th byte of the i'th string, scanning from the factor of the
+  // sample, which starts at the offset
+  fn byte_from(&self, i: usize, x: usize, sample: usize, mut offset: usize) -> u8 {
+    for f in self.strings[i].factors_from(sample) {
+      if x < offset + f.len {
+        let (byte, _) = factor_byte(&self.base_data, f.kind, f.range(), x - offset);
+        return byte;
+      }
+      offset += f.len;
+    }
+    unreachable!("The factors cover the whole string")
+  }
+
+  pub fn len(&self) -> usize {
+    self.strings.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.strings.is_empty()
+  }
+
+  pub fn string_len(&self, i: usize) -> usize {
+    self.strings[i].len
+  }
+
+  // Convenience wrapper around `try_extract` for when the
+  // string is known to be valid UTF-8, panics otherwise.
+  pub fn extract(&self, i: usize) -> String {
+    self.try_extract(i).unwrap_or_else(|e| panic!("{}", e))
+  }
+
+  pub fn try_extract(&self, i: usize) -> Result<String, DecodeError> {
+    let bytes = self.strings[i]
+      .factors_from(0)
+      .flat_map(|f| factor_bytes(&self.base_data, f.kind, f.range(), 0, f.len))
+      .collect();
+    String::from_utf8(bytes).map_err(|e| DecodeError::InvalidUtf8 {
+      string: i,
+      offset: e.utf8_error().valid_up_to(),
+    })
+  }
+
+  pub fn decode(&self) -> Vec<String> {
+    (0..self.len()).map(|i| self.extract(i)).collect()
+  }
+
+  pub fn memory_footprint(&self, raw_size: Option<usize>) -> MemoryUsage {
+    let factorizations_size = self
+      .strings
+      .iter()
+      .map(|s| {
+        let starts = match &s.starts {
+          Starts::Packed(packed) => packed.words.capacity() * mem::size_of::<u64>(),
+          Starts::Delta => 0,
+        };
+        starts + s.varints.capacity()
+      })
+      .sum();
+    let random_access_size = self
+      .strings
+      .iter()
+      .map(|s| s.samples.capacity() * mem::size_of::<(usize, usize)>())
+      .sum();
+    MemoryUsage::new(
+      self.base_data.capacity(),
+      factorizations_size,
+      random_access_size,
+      raw_size,
+    )
+  }
+}
+
+fn internal_new<U>(
+  rlz: &RelativeLempelZiv<U>,
+  delta: bool,
+) -> PackedRelativeLempelZiv<WithRandomAccess>
+where
+  U: Copy + TryInto<usize>,
+  <U as TryInto<usize>>::Error: fmt::Debug,
+{
+  // The bytes of runs are stored as their start
+  let has_runs = (0..internal_len(rlz))
+    .flat_map(|i| string_factors(rlz, i).iter())
+    .any(|part| matches!(part.kind, FactorKind::Run(_)));
+  let width = bits_needed(if has_runs {
+    rlz.base_data.len().max(u8::MAX as usize)
+  } else {
+    rlz.base_data.len()
+  });
+  // Strings that are aliases of each other are packed once for each,
+  // so the packed form does not keep the dedup
+  let strings = (0..internal_len(rlz))
+    .map(|i| string_factors(rlz, i))
+    .map(|encoded| {
+      let mut starts = if delta {
+        Starts::Delta
+      } else {
+        Starts::Packed(BitPacked::new(width))
+      };
+      let mut varints = vec![];
+      let mut samples = vec![];
+      let mut len = 0;
+      let mut previous_end = 0;
+      for (k, part) in encoded.iter().enumerate() {
+        let start: usize = part.range.0.try_into().unwrap();
+        let end: usize = part.range.1.try_into().unwrap();
+        if k % SAMPLE_RATE == 0 {
+          samples.push((len, varints.len()));
+          previous_end = 0;
+        }
+        // Stored raw strings are read from the base data like forward
+        // factors, so they do not need a kind of their own
+        let (kind, start, end) = match part.kind {
+          FactorKind::Forward | FactorKind::StoredRaw | FactorKind::Literal => (0, start, end),
+          FactorKind::ReverseComplement => (1, start, end),
+          FactorKind::Run(b) => (2, b as usize, b as usize + end - start),
+        };
+        write_varint(&mut varints, ((end - start) << 2 | kind) as u64);
+        match &mut starts {
+          Starts::Packed(packed) => packed.push(start),
+          Starts::Delta => write_varint(&mut varints, zigzag(start as i64 - previous_end as i64)),
+        }
+        previous_end = end;
+        len += end - start;
+      }
+      varints.shrink_to_fit();
+      if let Starts::Packed(packed) = &mut starts {
+        packed.words.shrink_to_fit();
+      }
+      PackedString {
+        factors: encoded.len(),
+        len,
+        starts,
+        varints,
+        samples,
+      }
+    })
+    .collect();
+
+  PackedRelativeLempelZiv {
+    base_data: rlz.base_data.clone(),
+    strings,
+    access: PhantomData,
+  }
+}
+
+struct Factor {
+  kind: FactorKind,
+  start: usize,
+  len: usize,
+}
+
+impl Factor {
+  fn range(&self) -> (usize, usize) {
+    (self.start, self.start + self.len)
+  }
+}
+
+impl PackedString {
+  // The factors from the given sample and on
+  fn factors_from(&self, sample: usize) -> impl Iterator<Item = Factor> + '_ {
+    let mut pos = self.samples.get(sample).map_or(0, |&(_, pos)| pos);
+    let mut previous_end = 0;
+    (sample * SAMPLE_RATE..self.factors).map(move |k| {
+      let value = read_varint(&self.varints, &mut pos) as usize;
+      let len = value >> 2;
+      let start = match &self.starts {
+        Starts::Packed(packed) => packed.get(k),
+        Starts::Delta => {
+          if k % SAMPLE_RATE == 0 {
+            previous_end = 0;
+          }
+          (previous_end as i64 + unzigzag(read_varint(&self.varints, &mut pos))) as usize
+        }
+      };
+      previous_end = start + len;
+      let kind = match value & 3 {
+        0 => FactorKind::Forward,
+        1 => FactorKind::ReverseComplement,
+        _ => FactorKind::Run(start as u8),
+      };
+      Factor { kind, start, len }
+    })
+  }
+}
+
+// Fixed width integers packed back to back into 64 bit words
+#[derive(Debug, Serialize, Deserialize)]
+struct BitPacked {
+  width: u32,
+  len: usize,
+  words: Vec<u64>,
+}
+
+impl BitPacked {
+  fn new(width: u32) -> Self {
+    BitPacked {
+      width,
+      len: 0,
+      words: vec![],
+    }
+  }
+
+  fn push(&mut self, value: usize) {
+    let bit = self.len * self.width as usize;
+    let (word, shift) = (bit / 64, bit % 64);
+    let value = value as u64;
+    let words = (bit + self.width as usize).div_ceil(64);
+    if self.words.len() < words {
+      self.words.resize(words, 0);
+    }
+    self.words[word] |= value << shift;
+    // The value continues in the next word
+    if shift > 0 && shift + self.width as usize > 64 {
+      self.words[word + 1] |= value >> (64 - shift);
+    }
+    self.len += 1;
+  }
+
+  fn get(&self, i: usize) -> usize {
+    if self.width == 0 {
+      return 0;
+    }
+    let bit = i * self.width as usize;
+    let (word, shift) = (bit / 64, bit % 64);
+    let mut value = self.words[word] >> shift;
+    if shift > 0 && shift + self.width as usize > 64 {
+      value |= self.words[word + 1] << (64 - shift);
+    }
+    (value & (u64::MAX >> (64 - self.width))) as usize
+  }
+}
+
+// The bits needed for every value below n
+fn bits_needed(n: usize) -> u32 {
+  usize::BITS - n.saturating_sub(1).leading_zeros()
+}
+
+// Little endian base 128, 7 bits per byte with the high bit set on all
+// but the last byte
+pub(crate) fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+  while value >= 0x80 {
+    bytes.push(value as u8 | 0x80);
+    value >>= 7;
+  }
+  bytes.push(value as u8);
+}
+
+// Maps signed to unsigned so small negative numbers stay small varints:
+// 0, -1, 1, -2, 2, ... become 0, 1, 2, 3, 4, ...
+fn zigzag(value: i64) -> u64 {
+  ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+  (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+pub(crate) fn read_varint(bytes: &[u8], pos: &mut usize) -> u64 {
+  let mut value = 0;
+  let mut shift = 0;
+  loop {
+    let byte = bytes[*pos];
+    *pos += 1;
+    value |= ((byte & 0x7f) as u64) << shift;
+    if byte & 0x80 == 0 {
+      return value;
+    }
+    shift += 7;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use quickcheck::TestResult;
+
+  #[test]
+  fn bit_packed_across_words() {
+    let mut packed = BitPacked::new(13);
+    let values: Vec<usize> = (0..100).map(|i| (i * 4099) % 8192).collect();
+    values.iter().for_each(|&v| packed.push(v));
+    assert!(values.iter().enumerate().all(|(i, &v)| packed.get(i) == v));
+    assert_eq!(3, bits_needed(8));
+    assert_eq!(4, bits_needed(9));
+    assert_eq!(0, bits_needed(1));
+  }
+
+  #[test]
+  fn varint_roundtrip() {
+    let mut bytes = vec![];
+    let values = [0, 1, 127, 128, 300, u32::MAX as u64, u64::MAX];
+    values.iter().for_each(|&v| write_varint(&mut bytes, v));
+    let mut pos = 0;
+    assert!(values.iter().all(|&v| read_varint(&bytes, &mut pos) == v));
+    assert_eq!(bytes.len(), pos);
+  }
+
+  #[test]
+  fn zigzag_roundtrip() {
+    assert_eq!(
+      vec![0, 1, 2, 3, 4],
+      [0, -1, 1, -2, 2]
+        .iter()
+        .map(|&v| zigzag(v))
+        .collect::<Vec<_>>()
+    );
+    assert!([0, 1, -1, i64::MAX, i64::MIN]
+      .iter()
+      .all(|&v| unzigzag(zigzag(v)) == v));
+  }
+
+  #[test]
+  fn packed_is_smaller() {
+    // Sequences with a mutation every ~20 bytes, so there are plenty of
+    // factors for the per-string overhead to not matter
+    let mut seed: u64 = 1777;
+    let mut next = || {
+      seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+      (seed >> 33) as usize
+    };
+    let base: Vec<u8> = (0..2000).map(|_| b"ACGT"[next() % 4]).collect();
+    let strings: Vec<String> = (0..20)
+      .map(|_| {
+        let mutated = base
+          .iter()
+          .map(|&b| {
+            if next() % 20 == 0 {
+              b"ACGT"[next() % 4]
+            } else {
+              b
+            }
+          })
+          .collect();
+        String::from_utf8(mutated).unwrap()
+      })
+      .collect();
+    let rlz = RelativeLempelZiv::<u32>::encode(&strings, Some(vec![0]), None as Option<&str>);
+    let packed = PackedRelativeLempelZiv::new(&rlz);
+
+    assert_eq!(strings, packed.decode());
+    assert!(
+      packed.memory_footprint(None).total_memory() < rlz.memory_footprint(None).total_memory()
+    );
+
+    let delta = PackedRelativeLempelZiv::new_delta(&rlz);
+    assert_eq!(strings, delta.decode());
+
+    let with = packed.memory_footprint(None);
+    let without = packed.without_random_access().memory_footprint(None);
+    assert_eq!(0, without.random_access_size());
+    assert!(with.random_access_size() > 0);
+    assert_eq!(with.compressed_size(), without.compressed_size());
+    assert!(without.total_memory() < with.total_memory());
+  }
+
+  #[test]
+  fn packed_runs() {
+    // A base this short would otherwise pack its starts in 3 bits,
+    // which the byte of the run does not fit in
+    let test_data = vec![format!("ACGT{}ACGT", "N".repeat(100)), "ACGTN".to_string()];
+    let options = EncodeOptions {
+      runs: Some(b'N'),
+      show_progress: false,
+      ..Default::default()
+    };
+    let rlz = RelativeLempelZiv::<u32>::encode_with_options(
+      &test_data,
+      None,
+      None as Option<&str>,
+      &options,
+    );
+    for packed in [
+      PackedRelativeLempelZiv::new(&rlz),
+      PackedRelativeLempelZiv::new_delta(&rlz),
+    ] {
+      assert_eq!(test_data, packed.decode());
+      assert_eq!(b'N', packed.random_access(0, 50));
+      assert_eq!(b'A', packed.random_access(0, 104));
+    }
+  }
+
+  #[test]
+  fn try_extract_invalid_utf8() {
+    let test_data = vec![b"ACGT".to_vec(), vec![b'A', 0xff, b'C']];
+    let rlz = RelativeLempelZiv::<u32>::encode_bytes(&test_data, None);
+    let packed = PackedRelativeLempelZiv::new(&rlz);
+
+    assert_eq!(Ok(String::from("ACGT")), packed.try_extract(0));
+    assert_eq!(
+      Err(DecodeError::InvalidUtf8 {
+        string: 1,
+        offset: 1
+      }),
+      packed.try_extract(1)
+    );
+  }
+
+  #[quickcheck]
+  fn quickcheck_packed_matches_rlz(xs: Vec<String>) -> TestResult {
+    if xs.is_empty() {
+      return TestResult::discard();
+    }
+    let rlz = RelativeLempelZiv::<u32>::encode(&xs, None, None as Option<&str>);
+    let matches = |packed: PackedRelativeLempelZiv| {
+      let random_access = xs.iter().enumerate().all(|(i, s)| {
+        (0..s.len()).all(|x| packed.random_access(i, x) == s.as_bytes()[x])
+          && packed.string_len(i) == s.len()
+      });
+      let decoded = packed.decode() == xs;
+      let without = packed.without_random_access();
+      let scanned = xs
+        .iter()
+        .enumerate()
+        .all(|(i, s)| (0..s.len()).all(|x| without.random_access(i, x) == s.as_bytes()[x]));
+      decoded && random_access && without.decode() == xs && scanned
+    };
+    TestResult::from_bool(
+      matches(PackedRelativeLempelZiv::new(&rlz))
+        && matches(PackedRelativeLempelZiv::new_delta(&rlz)),
+    )
+  }
+}
@@ -0,0 +1,149 @@
+// Progress of an encoding, handed to a callback in `EncodeOptions`, so
+// the progress can go to a GUI or a log instead of the terminal. When no
+// callback is given, `show_progress` draws an indicatif bar on stderr
+// through the same callback. Without the `progress` feature there is no
+// bar, so the library can be used without indicatif or terminal output.
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "progress")]
+use indicatif::{ProgressBar, ProgressStyle};
+
+use super::options::EncodeOptions;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+  FindingBase,
+  BuildingTree,
+  Encoding,
+}
+
+impl fmt::Display for Phase {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Phase::FindingBase => write!(f, "finding base"),
+      Phase::BuildingTree => write!(f, "building tree"),
+      Phase::Encoding => write!(f, "encoding"),
+    }
+  }
+}
+
+// Where the encoding is. Only the encoding phase moves the fraction from
+// 0 up to 1, one string at a time, the others are reported once as they
+// start.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+  pub phase: Phase,
+  pub fraction: f64,
+}
+
+// The callback for `EncodeOptions::progress`. The strings are encoded in
+// parallel, so it is called from the threads of the pool, one at a time.
+#[derive(Clone)]
+pub struct ProgressCallback(Arc<Mutex<dyn FnMut(Progress) + Send>>);
+
+impl ProgressCallback {
+  pub fn new<F: FnMut(Progress) + Send + 'static>(f: F) -> Self {
+    ProgressCallback(Arc::new(Mutex::new(f)))
+  }
+
+  fn call(&self, progress: Progress) {
+    // A callback that panicked before gets no more calls
+    if let Ok(mut f) = self.0.lock() {
+      f(progress);
+    }
+  }
+}
+
+impl fmt::Debug for ProgressCallback {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "ProgressCallback")
+  }
+}
+
+// Reports to the callback of the options, counting the encoded strings
+pub(crate) struct Reporter {
+  callback: Option<ProgressCallback>,
+  total: usize,
+  done: AtomicUsize,
+}
+
+impl Reporter {
+  pub(crate) fn new(options: &EncodeOptions) -> Self {
+    Self::strings(options, 0)
+  }
+
+  // For encoding that many strings
+  pub(crate) fn strings(options: &EncodeOptions, total: usize) -> Self {
+    let callback = match &options.progress {
+      Some(callback) => Some(callback.clone()),
+      None => terminal_bar(options.show_progress),
+    };
+    Reporter {
+      callback,
+      total,
+      done: AtomicUsize::new(0),
+    }
+  }
+
+  pub(crate) fn phase(&self, phase: Phase) {
+    self.report(phase, 0.0);
+  }
+
+  // One more string has been encoded
+  pub(crate) fn inc(&self) {
+    let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+    self.report(Phase::Encoding, done as f64 / self.total as f64);
+  }
+
+  fn report(&self, phase: Phase, fraction: f64) {
+    if let Some(callback) = &self.callback {
+      callback.call(Progress { phase, fraction });
+    }
+  }
+}
+
+// A line on stderr next to the bar, for the strategies that encode the
+// collection several times, like the reference merge. Follows the bar:
+// nothing is written with a callback, without `show_progress` or without
+// the `progress` feature.
+#[cfg(all(feature = "cli", feature = "progress"))]
+pub(crate) fn message(options: &EncodeOptions, message: fmt::Arguments) {
+  if options.show_progress && options.progress.is_none() {
+    eprintln!("{}", message);
+  }
+}
+
+#[cfg(all(feature = "cli", not(feature = "progress")))]
+pub(crate) fn message(_options: &EncodeOptions, _message: fmt::Arguments) {}
+
+// The bar is cleared when it is dropped along with the reporter
+#[cfg(feature = "progress")]
+struct TerminalBar(ProgressBar);
+
+#[cfg(feature = "progress")]
+impl Drop for TerminalBar {
+  fn drop(&mut self) {
+    self.0.finish_and_clear();
+  }
+}
+
+#[cfg(feature = "progress")]
+fn terminal_bar(show: bool) -> Option<ProgressCallback> {
+  if !show {
+    return None;
+  }
+  let pb = ProgressBar::new(100);
+  pb.set_style(ProgressStyle::default_bar().template("{msg} {wide_bar} {percent}%"));
+  let bar = TerminalBar(pb);
+  Some(ProgressCallback::new(move |progress: Progress| {
+    bar.0.set_message(&progress.phase.to_string());
+    bar.0.set_position((progress.fraction * 100.0) as u64);
+  }))
+}
+
+#[cfg(not(feature = "progress"))]
+fn terminal_bar(_show: bool) -> Option<ProgressCallback> {
+  None
+}
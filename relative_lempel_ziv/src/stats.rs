@@ -0,0 +1,22 @@
+// Where the time of an encoding went, see `RelativeLempelZiv::encode_timed`.
+// The phases scale very differently with the input: the base selection
+// with the size of the reference strings, the tree with the base, and the
+// factorization with the whole collection.
+use std::time::Duration;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EncodeStats {
+  pub base_selection: Duration,
+  pub tree_build: Duration,
+  pub factorization: Duration,
+  // Of the suffix tree of the base data
+  pub node_count: usize,
+  // Over all of the strings
+  pub factor_count: usize,
+}
+
+impl EncodeStats {
+  pub fn total(&self) -> Duration {
+    self.base_selection + self.tree_build + self.factorization
+  }
+}
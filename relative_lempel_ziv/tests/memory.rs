@@ -0,0 +1,134 @@
+// Checks that encoding does not hold on to memory after it returns,
+// in particular the suffix tree of the base string. A counting global
+// allocator is used as a proxy for the RSS, since the tree is by far
+// the biggest allocation of an encoding.
+use relative_lempel_ziv::RelativeLempelZiv;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+struct CountingAllocator;
+
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let live = LIVE.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+            PEAK.fetch_max(live, Ordering::SeqCst);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        LIVE.fetch_sub(layout.size(), Ordering::SeqCst);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+// The counters are global, so the tests must not run at the same time
+static SERIAL: Mutex<()> = Mutex::new(());
+
+fn collection(count: usize, len: usize) -> Vec<String> {
+    common::dna_collection(count, len, 0.01)
+}
+
+#[cfg(feature = "cli")]
+fn named_collection(count: usize, len: usize) -> Vec<(String, String)> {
+    collection(count, len)
+        .into_iter()
+        .enumerate()
+        .map(|(i, s)| (s, format!("seq{}", i)))
+        .collect()
+}
+
+// Runs `f` and returns its result, along with how many bytes are still
+// allocated after it returned and how far above the starting point the
+// allocations peaked while it ran.
+fn measure<R, F: FnOnce() -> R>(f: F) -> (R, usize, usize) {
+    let before = LIVE.load(Ordering::SeqCst);
+    PEAK.store(before, Ordering::SeqCst);
+    let res = f();
+    let after = LIVE.load(Ordering::SeqCst);
+    let peak = PEAK.load(Ordering::SeqCst);
+    (res, after.saturating_sub(before), peak - before)
+}
+
+#[test]
+fn encode_releases_the_suffix_tree() {
+    let _guard = SERIAL.lock().unwrap();
+    let strings = collection(20, 5_000);
+
+    // Warms up rayon's thread pool and the like, which do stay allocated
+    drop(RelativeLempelZiv::<u32>::encode(
+        &strings,
+        None,
+        None as Option<&str>,
+    ));
+
+    // What is left after encode is only the encoding itself, which is
+    // a lot smaller than the tree that was needed while encoding
+    let (rlz, kept, peak) =
+        measure(|| RelativeLempelZiv::<u32>::encode(&strings, None, None as Option<&str>));
+    assert!(!rlz.is_indexed());
+    assert!(kept * 2 < peak, "kept {} of a peak of {}", kept, peak);
+    drop(rlz);
+
+    let (_, leaked, _) = measure(|| {
+        for _ in 0..5 {
+            drop(RelativeLempelZiv::<u32>::encode(
+                &strings,
+                None,
+                None as Option<&str>,
+            ));
+        }
+    });
+    assert!(leaked < 64 * 1024, "leaked {} bytes over 5 encodes", leaked);
+}
+
+#[test]
+#[cfg(feature = "cli")]
+fn reference_merge_does_not_accumulate_trees() {
+    let _guard = SERIAL.lock().unwrap();
+    let strings = named_collection(10, 2_000);
+    drop(RelativeLempelZiv::<u32>::encode_reference_merge(
+        &strings,
+        None as Option<&str>,
+    ));
+
+    // The merge encodes the collection once per iteration with a growing
+    // base string, at most all of the strings. If the trees of earlier
+    // iterations were kept around, the peak would go past that of a
+    // single encode against the largest possible base string.
+    let raw: Vec<&str> = strings.iter().map(|s| s.0.as_str()).collect();
+    let all = (0..raw.len()).collect();
+    let (_, _, single) = measure(|| {
+        drop(RelativeLempelZiv::<u32>::encode(
+            &raw,
+            Some(all),
+            None as Option<&str>,
+        ));
+    });
+    let (_, leaked, merge) = measure(|| {
+        drop(RelativeLempelZiv::<u32>::encode_reference_merge(
+            &strings,
+            None as Option<&str>,
+        ));
+    });
+
+    assert!(
+        merge < single * 2,
+        "merge peaked at {} vs {} for a single encode",
+        merge,
+        single
+    );
+    assert!(leaked < 64 * 1024, "leaked {} bytes", leaked);
+}
@@ -0,0 +1,111 @@
+// The whole pipeline from a FASTA file to a compressed file on disk and
+// back: encode, serialize, deserialize and decode.
+use relative_lempel_ziv::fasta;
+use relative_lempel_ziv::packed::PackedRelativeLempelZiv;
+use relative_lempel_ziv::RelativeLempelZiv;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+// Width of the sequence lines in the fixtures
+const LINE_WIDTH: usize = 60;
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name)
+}
+
+// (sequence, header) pairs, read with the parser the crate ships
+fn read_fasta(path: &Path) -> Vec<(String, String)> {
+    fasta::read_records(BufReader::new(File::open(path).unwrap())).unwrap()
+}
+
+fn write_fasta(records: &[(String, &str)]) -> String {
+    let mut contents = String::new();
+    for (sequence, header) in records {
+        contents.push_str(&format!(">{}\n", header));
+        for line in sequence.as_bytes().chunks(LINE_WIDTH) {
+            contents.push_str(std::str::from_utf8(line).unwrap());
+            contents.push('\n');
+        }
+    }
+    contents
+}
+
+#[test]
+fn similar_sequences_round_trip_through_a_file() {
+    let input = fixture("similar.fa");
+    let contents = fs::read_to_string(&input).unwrap();
+    let records = read_fasta(&input);
+    assert!(records.len() > 1);
+
+    let rlz =
+        RelativeLempelZiv::<u32>::from_collection_with_names(&records, None, None as Option<&str>);
+
+    let compressed = std::env::temp_dir().join(format!("rlz_roundtrip_{}.bin", std::process::id()));
+    bincode::serialize_into(BufWriter::new(File::create(&compressed).unwrap()), &rlz).unwrap();
+    let loaded: RelativeLempelZiv<u32> =
+        bincode::deserialize_from(BufReader::new(File::open(&compressed).unwrap())).unwrap();
+    assert_eq!(rlz, loaded);
+    let rlz = loaded;
+
+    let decoded: Vec<(String, &str)> = rlz
+        .decode()
+        .into_iter()
+        .zip(rlz.names().iter().map(|n| n.as_str()))
+        .collect();
+    assert_eq!(contents, write_fasta(&decoded));
+
+    // The sequences are near-identical, so only the first one really
+    // takes up space in the compressed file
+    let input_size = fs::metadata(&input).unwrap().len();
+    let compressed_size = fs::metadata(&compressed).unwrap().len();
+    fs::remove_file(&compressed).unwrap();
+    assert!(
+        compressed_size < input_size,
+        "compressed to {} bytes from {}",
+        compressed_size,
+        input_size
+    );
+}
+
+#[test]
+fn delta_packing_of_similar_sequences() {
+    let sequences: Vec<String> = read_fasta(&fixture("similar.fa"))
+        .into_iter()
+        .map(|(sequence, _)| sequence)
+        .collect();
+
+    let rlz = RelativeLempelZiv::<u32>::encode(&sequences, Some(vec![0]), None as Option<&str>);
+    let packed = rlz.pack();
+    let delta =
+        PackedRelativeLempelZiv::encode_delta(&sequences, Some(vec![0]), None as Option<&str>);
+    assert_eq!(sequences, delta.decode());
+
+    let plain = rlz.memory_footprint(None).factorizations_size();
+    let packed = packed.memory_footprint(None).factorizations_size();
+    let delta = delta.memory_footprint(None).factorizations_size();
+    eprintln!(
+        "Factorizations: {} plain, {} packed, {} delta",
+        plain, packed, delta
+    );
+    assert!(delta < packed && packed < plain);
+}
+
+#[test]
+fn encode_fasta_reads_the_fixture() {
+    let input = fixture("similar.fa");
+    let records = read_fasta(&input);
+
+    let (rlz, names) =
+        RelativeLempelZiv::<u32>::encode_fasta(BufReader::new(File::open(&input).unwrap()), None)
+            .unwrap();
+    let expected_names: Vec<String> = records.iter().map(|(_, h)| h.clone()).collect();
+    assert_eq!(expected_names, names);
+    assert_eq!(names, rlz.names());
+
+    let sequences: Vec<String> = records.into_iter().map(|(s, _)| s).collect();
+    assert_eq!(sequences, rlz.decode());
+}
@@ -0,0 +1,92 @@
+// The streaming merge strategy, with a temp file as the backing store
+// that is read again on every pass.
+#![cfg(feature = "cli")]
+use relative_lempel_ziv::RelativeLempelZiv;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+// Every line is a name and a string separated by a tab
+fn read_collection(path: &Path) -> io::Result<impl Iterator<Item = io::Result<(String, String)>>> {
+    let reader = BufReader::new(File::open(path)?);
+    Ok(reader.lines().map(|line| {
+        let line = line?;
+        let (name, s) = line.split_once('\t').unwrap();
+        Ok((String::from(s), String::from(name)))
+    }))
+}
+
+#[test]
+fn streaming_merge_reads_from_disk() {
+    let path = std::env::temp_dir().join(format!("rlz_streaming_{}.tsv", std::process::id()));
+    let strings = vec![
+        "ACGTTGCAACGTAGCTAGCTTTGACC",
+        "ACGTTGCAACGTAGCTAGCTTTGACA",
+        "TTTTGGGGCCCCAAAATTTTGGGGCCCC",
+        "ACGTTGCAACGTAGCTAGCTTTGACC",
+        "",
+        "TTTTGGGGCCCCAAAATTTTGGGGCCCA",
+    ];
+    let contents: String = strings
+        .iter()
+        .enumerate()
+        .map(|(i, s)| format!("seq{}\t{}\n", i, s))
+        .collect();
+    fs::write(&path, contents).unwrap();
+
+    let mut passes = 0;
+    let rlz = RelativeLempelZiv::<u32>::encode_reference_merge_streaming(
+        || {
+            passes += 1;
+            read_collection(&path)
+        },
+        None as Option<&str>,
+    )
+    .unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(strings, rlz.decode());
+    assert_eq!(Some(strings[2].to_string()), rlz.extract_by_name("seq2"));
+    // One pass to pick the first reference, and the encoding passes
+    assert!(passes >= 3);
+}
+
+#[test]
+fn streaming_merge_seeded_is_deterministic() {
+    let path = std::env::temp_dir().join(format!("rlz_streaming_seeded_{}.tsv", std::process::id()));
+    let contents: String = (0..10)
+        .map(|i| {
+            format!(
+                "s{}\tACGT{}TTGCA{}\n",
+                i,
+                "G".repeat(i),
+                "C".repeat(10 - i)
+            )
+        })
+        .collect();
+    fs::write(&path, contents).unwrap();
+
+    let encode = || {
+        RelativeLempelZiv::<u32>::encode_reference_merge_streaming_seeded(
+            || read_collection(&path),
+            None as Option<&str>,
+            1767,
+        )
+        .unwrap()
+    };
+    let a = encode();
+    let b = encode();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(a, b);
+}
+
+#[test]
+fn streaming_merge_passes_on_read_errors() {
+    let path = std::env::temp_dir().join("rlz_streaming_does_not_exist.tsv");
+    let res = RelativeLempelZiv::<u32>::encode_reference_merge_streaming(
+        || read_collection(&path),
+        None as Option<&str>,
+    );
+    assert_eq!(io::ErrorKind::NotFound, res.unwrap_err().kind());
+}
@@ -0,0 +1,103 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use suffix_tree::matching_statistics::MatchingStatistics;
+use suffix_tree::SuffixTree;
+
+// Pseudo random DNA, so the bench does not need a random crate
+fn dna(len: usize) -> Vec<u8> {
+    let mut state: u64 = 1764;
+    (0..len)
+        .map(|_| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            b"ACGT"[(state >> 62) as usize]
+        })
+        .collect()
+}
+
+fn build(c: &mut Criterion) {
+    let mut group = c.benchmark_group("build");
+    group.sample_size(10);
+    for &len in &[100_000, 1_000_000] {
+        let bytes = dna(len);
+        group.bench_with_input(BenchmarkId::new("ukkonen", len), &bytes, |b, bytes| {
+            b.iter(|| SuffixTree::new_bytes(bytes))
+        });
+        group.bench_with_input(BenchmarkId::new("suffix array", len), &bytes, |b, bytes| {
+            b.iter(|| SuffixTree::from_suffix_array(bytes))
+        });
+    }
+    group.finish();
+}
+
+// The strings of the unit tests, which only have a handful of children
+// per node
+fn small(c: &mut Criterion) {
+    let mut group = c.benchmark_group("small");
+    for s in &["banana", "mississippi"] {
+        group.bench_with_input(BenchmarkId::new("build", s), s, |b, s| {
+            b.iter(|| SuffixTree::new(s))
+        });
+        let st = SuffixTree::new(s);
+        group.bench_with_input(BenchmarkId::new("longest substring", s), s, |b, s| {
+            b.iter(|| {
+                (0..s.len())
+                    .filter_map(|i| st.longest_substring(&s.as_bytes()[i..]))
+                    .count()
+            })
+        });
+    }
+    group.finish();
+}
+
+// Walks down the tree of a large DNA input with windows of another
+// sequence, which is what encoding spends its time on
+fn query(c: &mut Criterion) {
+    let bytes = dna(1_000_000);
+    let st = SuffixTree::from_suffix_array(&bytes);
+    let queries = dna(1_001_000);
+    let mut group = c.benchmark_group("query");
+    group.bench_function("longest substring", |b| {
+        b.iter(|| {
+            queries[1_000..]
+                .chunks(100)
+                .take(1_000)
+                .filter_map(|q| st.longest_substring(q))
+                .count()
+        })
+    });
+    group.finish();
+}
+
+// A long repetitive input: copies of the reference with a substitution
+// every 500 bytes, factorized greedily by walking from the root for every
+// factor, and through the matching statistics
+fn factorize(c: &mut Criterion) {
+    let reference = dna(100_000);
+    let st = SuffixTree::from_suffix_array(&reference);
+    let mut input = reference.repeat(10);
+    for i in (0..input.len()).step_by(500) {
+        input[i] = if input[i] == b'A' { b'C' } else { b'A' };
+    }
+
+    let mut group = c.benchmark_group("factorize");
+    group.sample_size(10);
+    group.bench_function("from the root", |b| {
+        b.iter(|| {
+            let mut factors = 0;
+            let mut i = 0;
+            while let Some((start, end)) = st.longest_substring(&input[i..]) {
+                factors += 1;
+                i += end - start;
+            }
+            factors
+        })
+    });
+    group.bench_function("matching statistics", |b| {
+        b.iter(|| MatchingStatistics::new(&st).factors(&input).unwrap().len())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, build, small, query, factorize);
+criterion_main!(benches);
@@ -0,0 +1,45 @@
+// Builds the tree of the input with both builders, and with Ukkonen's
+// algorithm in two goes through `extend`, and checks the same invariants
+// as the quickcheck properties of the crate. Run it with
+//
+//   cargo +nightly fuzz run builders
+//
+// from the suffix_tree directory.
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use suffix_tree::SuffixTree;
+
+fn check(st: &SuffixTree, data: &[u8]) {
+    // A leaf for every suffix, plus the one for the separator
+    let leaves = st.depth_first().filter(|n| n.is_leaf()).count();
+    assert_eq!(data.len() + 1, leaves);
+    assert_eq!(st.node_count(), st.depth_first().count());
+
+    for i in 0..data.len() {
+        assert!(st.contains_suffix(&data[i..]), "missing suffix {}", i);
+    }
+
+    for node in st.depth_first() {
+        if node.is_internal_node() {
+            assert!(st.children_of(node).count() >= 2, "node {}", node.id);
+        }
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let ukkonen = SuffixTree::new_bytes(data);
+    check(&ukkonen, data);
+    check(&SuffixTree::from_suffix_array(data), data);
+
+    let (head, tail) = data.split_at(data.len() / 2);
+    let mut extended = SuffixTree::new_bytes(head);
+    extended.extend(tail);
+    check(&extended, data);
+
+    let labels = |st: &SuffixTree| {
+        st.depth_first()
+            .map(|n| (n.start, n.end(), n.suffix_index, n.children().len()))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(labels(&ukkonen), labels(&extended));
+});
@@ -6,7 +6,7 @@
 // Archived via web.archive.org on 14/09/2020
 
 use std::fmt;
-use std::iter;
+use std::io::{self, Read, Write};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
@@ -14,16 +14,26 @@ use std::sync::Arc;
 // mod.rs file in types/mod.rs or a types.rs in src, essentially saving
 // a file that just declares the modules.
 mod types {
+    pub mod children;
     pub mod label_data;
     pub mod node;
 }
 use types::label_data::LabelData;
 use types::node::{Node, NodeId};
 
+pub mod matching_statistics;
+mod serialize;
+mod suffix_array;
+
 pub struct SuffixTree {
-    raw_string: String,
+    // The bytes the tree was built from, without the separator.
+    // These are not necessarily valid UTF-8 when the tree is
+    // built via `new_bytes`, so use `string` to get a &str view.
+    raw_bytes: Vec<u8>,
     nodes: Vec<Node>,
     string: Vec<LabelData>,
+    // Only there for trees built with Ukkonen's algorithm, see `extend`
+    online: Option<OnlineState>,
 }
 
 impl SuffixTree {
@@ -35,15 +45,48 @@ impl SuffixTree {
         internal_to_suffix_tree_bytes(s)
     }
 
+    // Builds the same tree as `new_bytes`, but from the suffix array and
+    // LCP array of the bytes rather than with Ukkonen's algorithm, which
+    // is faster for large inputs. Only the node ids differ.
+    pub fn from_suffix_array(s: &[u8]) -> Self {
+        suffix_array::to_suffix_tree(s)
+    }
+
+    // Appends the bytes to the string of the tree, carrying on with
+    // Ukkonen's algorithm from where the tree was left instead of building
+    // it again. The tree ends up the same as one built from the whole
+    // string, so there is still a single separator, at the very end, and
+    // matches can go across where the bytes were appended. Trees built
+    // from the suffix array or with `load` have nothing to carry on from,
+    // so they are built again the first time they are extended.
+    pub fn extend<T: AsRef<[u8]>>(&mut self, s: T) {
+        internal_extend(self, s.as_ref())
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.raw_bytes
+    }
+
+    // The string of the tree, for trees built from valid UTF-8, which is
+    // always the case for `new`. Panics otherwise, see `try_string`.
     pub fn string(&self) -> &str {
-        &self.raw_string
+        self.try_string().expect("The suffix tree was not built from valid UTF-8")
+    }
+
+    // Only returns the string if the tree was built from valid UTF-8
+    pub fn try_string(&self) -> Option<&str> {
+        std::str::from_utf8(&self.raw_bytes).ok()
     }
 
-    // Gets the byte label going into the node
+    // Gets the byte label going into the node. The separator is its own
+    // LabelData::Sep here, so it can never be mistaken for input bytes.
+    // This is the one to use for anything but printing.
     pub fn label_of_node(&self, node: &Node) -> &[LabelData] {
         &self.string[node.start..node.end()]
     }
 
+    // Only for printing, since the separator is written as "<$>" and
+    // input with those very bytes in it gets the same output
     pub fn label_of_node_formatted(&self, node: &Node) -> String {
         // Turns the LabelData into a readable format
         // i.t. the LabelData::Sep is made into the
@@ -52,9 +95,8 @@ impl SuffixTree {
         // and then collected back into a single vector.
         let label_data = self
             .label_of_node(node)
-            .into_iter()
-            .map(|l| l.prettify())
-            .flatten()
+            .iter()
+            .flat_map(|l| l.prettify())
             .collect::<Vec<_>>();
 
         // We need to clone the label_data because
@@ -71,14 +113,51 @@ impl SuffixTree {
         internal_contains_suffix(self, suffix)
     }
 
-    // pub fn contains_substring(&self, substr: &[u8]) -> bool {
-    //     internal_contains_substring(self, substr)
-    // }
+    // Whether substr appears anywhere in the string. The empty slice is
+    // a substring of every string, so it is always contained, unlike for
+    // contains_suffix and longest_substring.
+    pub fn contains_substring(&self, substr: &[u8]) -> bool {
+        internal_contains_substring(self, substr)
+    }
+
+    // Every position where pattern starts in the string, sorted. The
+    // empty pattern is found at every position.
+    pub fn find_all(&self, pattern: &[u8]) -> Vec<usize> {
+        internal_find_all(self, pattern)
+    }
 
+    // The longest prefix of substr found anywhere in the tree, as the
+    // (start, end) range of its first occurrence in the string. None if
+    // not even the first byte is there, or if substr is empty. The range
+    // never covers the separator, so end is at most the string length
+    // and the range can always be used to slice `bytes()`.
     pub fn longest_substring(&self, substr: &[u8]) -> Option<(usize, usize)> {
         internal_longest_substring(self, substr)
     }
 
+    // How many bytes from the start of the query can be matched somewhere
+    // in the string, i.e. the length of what `longest_substring` finds.
+    pub fn prefix_match_len(&self, query: &[u8]) -> usize {
+        internal_longest_substring(self, query).map_or(0, |(start, end)| end - start)
+    }
+
+    // Same as `longest_substring`, but also returns where the match ended,
+    // which can be given back as the hint for the bytes right after the
+    // first one, i.e. `bytes[1..]`. The first len - 1 bytes of that are
+    // known to match already, so they are skipped over via the suffix
+    // link instead of being compared again, which is what makes matching
+    // statistics (the longest match at every position) linear in time.
+    // It does not help the greedy factorization, since the next factor
+    // starts where the last one ended, not one byte after where it
+    // started. The hint has to come from this tree.
+    pub fn longest_substring_from(
+        &self,
+        bytes: &[u8],
+        hint: Option<MatchState>,
+    ) -> Option<(usize, usize, MatchState)> {
+        internal_longest_substring_from(self, bytes, hint)
+    }
+
     pub fn root(&self) -> &Node {
         &self.nodes[0]
     }
@@ -86,12 +165,77 @@ impl SuffixTree {
     pub fn node(&self, id: NodeId) -> &Node {
         &self.nodes[id]
     }
+
+    // The suffix link of an internal node, which goes to the node whose
+    // path is the path of this one without its first byte. The root and
+    // the leaves have none.
+    pub fn suffix_link_of(&self, id: NodeId) -> Option<NodeId> {
+        let node = &self.nodes[id];
+        if node.is_internal_node() {
+            node.suffix_link
+        } else {
+            None
+        }
+    }
+
+    // The number of nodes, including the root and the leaf of the
+    // separator. There are at most twice as many as the bytes.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    // The length in bytes of the longest path from the root to a leaf,
+    // not counting the separator
+    pub fn max_depth(&self) -> usize {
+        self.nodes
+            .iter()
+            .filter(|n| n.is_leaf())
+            .map(|n| node_depth(n) - 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    // Writes the tree in a binary format that `load` reads back, so it
+    // does not have to be built again
+    pub fn save<W: Write>(&self, w: W) -> io::Result<()> {
+        serialize::save(self, w)
+    }
+
+    // Reads a tree written by `save`. Fails with InvalidData if it is
+    // not one.
+    pub fn load<R: Read>(r: R) -> io::Result<Self> {
+        serialize::load(r)
+    }
+
+    // The children of the node, in the order of the first byte of their
+    // labels. The node has to be from this tree, since it is looked up by id.
+    pub fn children_of<'a>(&'a self, node: &Node) -> impl Iterator<Item = &'a Node> {
+        let nodes = &self.nodes;
+        nodes[node.id]
+            .children()
+            .values()
+            .map(move |&id| &nodes[id])
+    }
+
+    // Every node of the tree, parents before their children, starting
+    // with the root and going through the children in byte order
+    pub fn depth_first(&self) -> impl Iterator<Item = &Node> {
+        let mut stack = vec![self.root()];
+        std::iter::from_fn(move || {
+            let node = stack.pop()?;
+            // Pushed in reverse so the smallest child comes out first
+            let next = stack.len();
+            stack.extend(self.children_of(node));
+            stack[next..].reverse();
+            Some(node)
+        })
+    }
 }
 
 impl fmt::Debug for SuffixTree {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         fn fmt(f: &mut fmt::Formatter, st: &SuffixTree, node: &Node, depth: usize) -> fmt::Result {
-            let indent: String = iter::repeat(' ').take(depth * 2).collect();
+            let indent: String = std::iter::repeat_n(' ', depth * 2).collect();
             if node.is_root() {
                 writeln!(f, "ROOT")?;
             } else {
@@ -103,7 +247,7 @@ impl fmt::Debug for SuffixTree {
                     node.suffix_index.unwrap(),
                     node.start,
                     node.end(),
-                    &st.raw_string[node.suffix_range()]
+                    String::from_utf8_lossy(&st.raw_bytes[node.suffix_range()])
                 )?;
             }
             for child in node.children().values() {
@@ -113,7 +257,11 @@ impl fmt::Debug for SuffixTree {
         }
         writeln!(f, "\n-----------------------------------------")?;
         writeln!(f, "SUFFIX TREE")?;
-        writeln!(f, "raw string: {}", self.raw_string)?;
+        writeln!(
+            f,
+            "raw string: {}",
+            String::from_utf8_lossy(&self.raw_bytes)
+        )?;
         fmt(f, self, self.root(), 0)?;
         writeln!(f, "-----------------------------------------")
     }
@@ -131,43 +279,171 @@ fn internal_to_suffix_tree_bytes(s: &[u8]) -> SuffixTree {
     // Root always has id 0, no parent, start is 0 and
     // a reference to the global end
     let root = Node::new(0, None, None, 0, &global_end);
+
     // The nodes vector contains all of the nodes,
     // where they just have the Id's of the referenced
     // nodes in accordance to this list.
-    let mut nodes = vec![root];
+    let mut suffix_tree = SuffixTree {
+        raw_bytes: vec![],
+        nodes: vec![root],
+        string: vec![],
+        online: Some(OnlineState {
+            active_node: 0,
+            active_edge: 0,
+            active_length: 0,
+            remaining_suffix_count: 0,
+            global_end,
+            nodes_before_sep: 1,
+        }),
+    };
+    internal_extend(&mut suffix_tree, s);
+    suffix_tree
+}
+
+// Where a match of `longest_substring_from` ended: the deepest node
+// that the match went all the way through, and the length of the match
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatchState {
+    node: NodeId,
+    len: usize,
+}
+
+// The length of the string spelled out by the path to the node, which
+// for a leaf includes the separator
+pub(crate) fn node_depth(node: &Node) -> usize {
+    match node.suffix_index {
+        Some(suffix_index) if !node.is_root() => node.end() - suffix_index,
+        _ => 0,
+    }
+}
+
+// Everything Ukkonen's algorithm needs to carry on where it left off.
+// The active point is the one from right before the separator was added,
+// since adding it makes every suffix explicit, and the separator would
+// not be unique anymore if more bytes came after it. Every node from
+// `nodes_before_sep` and on was only created for the separator.
+#[derive(Clone)]
+struct OnlineState {
+    active_node: NodeId,
+    // 'active_edge' is the index of the actual LabelData
+    // in the string. string[active_edge] would give the
+    // current LabelData
+    active_edge: usize,
+    active_length: usize,
+    remaining_suffix_count: usize,
+    global_end: Arc<AtomicUsize>,
+    nodes_before_sep: usize,
+}
+
+fn internal_extend(st: &mut SuffixTree, s: &[u8]) {
+    // Trees from the suffix array or from `load` have no state to
+    // continue from, so they are built again with Ukkonen's algorithm,
+    // which leaves the state for the next time.
+    let mut state = match st.online.take() {
+        Some(state) => state,
+        None => {
+            *st = internal_to_suffix_tree_bytes(&[&st.raw_bytes[..], s].concat());
+            return;
+        }
+    };
+    if !st.string.is_empty() {
+        remove_separator(st, &state);
+    }
 
     // Transforms the input string into a list of
     // bytes, wrapped into the LabelData enum
     // and lastly appends the separator at the
     // end of this list. This ensures a unique
     // last byte to finish up the suffix tree.
-    let mut bytes_and_sep = s
-        .into_iter()
-        .map(|&b| LabelData::new(b))
-        .collect::<Vec<_>>();
-    bytes_and_sep.push(LabelData::Sep);
+    let first = st.string.len();
+    st.raw_bytes.extend_from_slice(s);
+    st.string.extend(s.iter().map(|&b| LabelData::new(b)));
+    for i in first..st.string.len() {
+        ukkonen_step(&mut st.nodes, &st.string, i, &mut state);
+    }
 
-    let mut suffix_tree = SuffixTree {
-        raw_string: String::from_utf8(s.to_vec()).unwrap(),
-        nodes: vec![],
-        string: vec![],
-    };
+    state.nodes_before_sep = st.nodes.len();
+    st.string.push(LabelData::Sep);
+    ukkonen_step(
+        &mut st.nodes,
+        &st.string,
+        st.string.len() - 1,
+        &mut state.clone(),
+    );
 
-    // Various control variables
-    let mut last_new_node: Option<NodeId>;
-    // Root node is always the first in the list
-    let root_id = 0;
-    let mut active_node: NodeId = root_id;
-    // 'active_edge' is the index of the actual LabelData
-    // in 'bytes_and_sep'. bytes_and_sep[active_edge]
-    // would give the current LabelData
-    let mut active_edge: usize = 0;
-    let mut active_length: usize = 0;
-    let mut remaining_suffix_count: usize = 0;
+    // Now to actually be able to find the suffix
+    // index for a given node, we need to run a
+    // traversal on the tree, and the index is then
+    // found by `s.len() - label_height`.
+    let nodes = &mut st.nodes;
+    let mut stack = vec![(0, 0)];
+    while let Some((node_id, label_height)) = stack.pop() {
+        let new_height;
+        if node_id != 0 {
+            nodes[node_id].suffix_index = Some(nodes[node_id].start - label_height);
+            new_height = label_height + nodes[node_id].length();
+        } else {
+            new_height = label_height;
+        }
+        for n in nodes[node_id].children().values() {
+            stack.push((*n, new_height));
+        }
+    }
+
+    leftmost_suffix_indices(nodes);
+    st.online = Some(state);
+}
 
+// Takes the tree back to how it was right before the separator was
+// added. Every node that was made for the separator is either a leaf
+// with only the separator, which is removed, or an edge split by it.
+// The node that was split off is joined back with the one it was split
+// from, which is always a node from before the separator, since the
+// later suffixes are shorter and never get below the earlier splits.
+fn remove_separator(st: &mut SuffixTree, state: &OnlineState) {
+    let nodes = &mut st.nodes;
+    let first_sep_node = state.nodes_before_sep;
+    for id in 0..first_sep_node {
+        let sep_children: Vec<_> = nodes[id]
+            .children()
+            .iter()
+            .filter(|(_, &child)| child >= first_sep_node)
+            .map(|(&label, &child)| (label, child))
+            .collect();
+        for (label, mut child) in sep_children {
+            if nodes[child].is_leaf() {
+                nodes[id].children.remove(&label);
+                continue;
+            }
+
+            // Only the labels are moved when the internal nodes go to
+            // their leftmost occurrence, so the split labels are right
+            // in front of the label of the node below them
+            let mut split_length = 0;
+            while child >= first_sep_node {
+                split_length += nodes[child].length();
+                child = *nodes[child]
+                    .children()
+                    .iter()
+                    .find(|(&l, _)| l != LabelData::Sep)
+                    .unwrap()
+                    .1;
+            }
+            nodes[child].start -= split_length;
+            nodes[child].parent = Some(id);
+            nodes[id].children.insert(label, child);
+        }
+    }
+    nodes.truncate(first_sep_node);
+    state.global_end.fetch_sub(1, Ordering::SeqCst);
+    st.string.pop();
+}
+
+// Adds the byte at i to the tree, following the rules of Ukkonen's algorithm
+fn ukkonen_step(nodes: &mut Vec<Node>, string: &[LabelData], i: usize, state: &mut OnlineState) {
     // Returns Option<(NodeId, Length, Edge)>
     fn walk_down(
-        nodes: &Vec<Node>,
+        nodes: &[Node],
         node_id: NodeId,
         act_l: usize,
         act_e: usize,
@@ -182,156 +458,159 @@ fn internal_to_suffix_tree_bytes(s: &[u8]) -> SuffixTree {
         None
     }
 
-    // Build up the entire node tree with all the relations
-    for (i, &b) in bytes_and_sep.iter().enumerate() {
-        // Update global_end and increment remaining suffix
-        // Extension rule 1 for global_end
-        global_end.fetch_add(1, Ordering::SeqCst);
-        remaining_suffix_count += 1;
-
-        // Clear last new node
-        last_new_node = None;
+    // Root node is always the first in the list
+    let root_id = 0;
+    let b = string[i];
 
-        // Need to create these many suffixes, or short-circuit
-        // them for next byte.
-        while remaining_suffix_count > 0 {
-            if active_length == 0 {
-                active_edge = i;
-            }
+    // Update global_end and increment remaining suffix
+    // Extension rule 1 for global_end
+    state.global_end.fetch_add(1, Ordering::SeqCst);
+    state.remaining_suffix_count += 1;
 
-            if !nodes[active_node].has_child(&bytes_and_sep[active_edge]) {
-                // Rule 2 extension
-                let new_node = Node::new(
-                    nodes.len(),
-                    Some(active_node),
-                    Some(root_id),
-                    i,
-                    &global_end,
-                );
-                let node = &mut nodes[active_node];
-                node.children
-                    .insert(bytes_and_sep[active_edge], new_node.id);
-                nodes.push(new_node);
-
-                // If a node was created in the last iteration,
-                // then we need to set the suffix link of that
-                // to the current active node.
-                if let Some(last_new_node_id) = last_new_node {
-                    nodes[last_new_node_id].suffix_link = Some(active_node);
-                    last_new_node = None;
-                }
-            } else {
-                let next = *nodes[active_node]
-                    .child(&bytes_and_sep[active_edge])
-                    .unwrap();
-                if let Some((n, l, e)) = walk_down(&nodes, next, active_length, active_edge) {
-                    active_node = n;
-                    active_length = l;
-                    active_edge = e;
-                    continue; // Need to continue walkdown from next node
-                }
+    // Clear last new node
+    let mut last_new_node: Option<NodeId> = None;
 
-                // Extension rule 3
-                if bytes_and_sep[nodes[next].start + active_length] == b {
-                    // Check if suffix link needs to be set
-                    // Apparently Rust does not yet allow "if let X &&"
-                    // expressions, so will have to live with this nested if
-                    if let Some(last_new_node_id) = last_new_node {
-                        if active_node != root_id {
-                            nodes[last_new_node_id].suffix_link = Some(active_node);
-                        }
-                    }
+    // Need to create these many suffixes, or short-circuit
+    // them for next byte.
+    while state.remaining_suffix_count > 0 {
+        if state.active_length == 0 {
+            state.active_edge = i;
+        }
+        let active_node = state.active_node;
+        let active_edge = state.active_edge;
+        let active_length = state.active_length;
 
-                    // Increment active_length and break, show stopper
-                    active_length += 1;
-                    break;
-                }
+        if !nodes[active_node].has_child(&string[active_edge]) {
+            // Rule 2 extension
+            let new_node = Node::new(
+                nodes.len(),
+                Some(active_node),
+                Some(root_id),
+                i,
+                &state.global_end,
+            );
+            let node = &mut nodes[active_node];
+            node.children.insert(string[active_edge], new_node.id);
+            nodes.push(new_node);
 
-                // Extension rule 2
-                // New character is currently not in the label
-                // so will have to create a new internal node,
-                // and a new leaf node.
-                let split_end = Arc::new(AtomicUsize::new(nodes[next].start + active_length));
-                let mut split_node = Node::new(
-                    nodes.len(),
-                    nodes[next].parent,
-                    Some(root_id),
-                    nodes[next].start,
-                    &split_end,
-                );
-                nodes[active_node]
-                    .children
-                    .insert(bytes_and_sep[active_edge], split_node.id);
-                let new_leaf = Node::new(
-                    nodes.len() + 1,
-                    Some(split_node.id),
-                    Some(root_id),
-                    i,
-                    &global_end,
-                );
-                split_node.children.insert(bytes_and_sep[i], new_leaf.id);
-                nodes[next].start += active_length;
-                nodes[next].parent = Some(split_node.id);
-
-                split_node
-                    .children
-                    .insert(bytes_and_sep[nodes[next].start], next);
+            // If a node was created in the last iteration,
+            // then we need to set the suffix link of that
+            // to the current active node.
+            if let Some(last_new_node_id) = last_new_node {
+                nodes[last_new_node_id].suffix_link = Some(active_node);
+                last_new_node = None;
+            }
+        } else {
+            let next = *nodes[active_node].child(&string[active_edge]).unwrap();
+            if let Some((n, l, e)) = walk_down(nodes, next, active_length, active_edge) {
+                state.active_node = n;
+                state.active_length = l;
+                state.active_edge = e;
+                continue; // Need to continue walkdown from next node
+            }
 
+            // Extension rule 3
+            if string[nodes[next].start + active_length] == b {
+                // Check if suffix link needs to be set
+                // Apparently Rust does not yet allow "if let X &&"
+                // expressions, so will have to live with this nested if
                 if let Some(last_new_node_id) = last_new_node {
-                    nodes[last_new_node_id].suffix_link = Some(split_node.id);
+                    if active_node != root_id {
+                        nodes[last_new_node_id].suffix_link = Some(active_node);
+                    }
                 }
-                last_new_node = Some(split_node.id);
-                nodes.push(split_node);
-                nodes.push(new_leaf);
+
+                // Increment active_length and break, show stopper
+                state.active_length += 1;
+                break;
             }
 
-            remaining_suffix_count -= 1;
-            if active_node == root_id && active_length > 0 {
-                active_length -= 1;
-                active_edge = i - remaining_suffix_count + 1;
-            } else if active_node != root_id {
-                active_node = nodes[active_node].suffix_link.unwrap();
+            // Extension rule 2
+            // New character is currently not in the label
+            // so will have to create a new internal node,
+            // and a new leaf node.
+            let split_end = Arc::new(AtomicUsize::new(nodes[next].start + active_length));
+            let mut split_node = Node::new(
+                nodes.len(),
+                nodes[next].parent,
+                Some(root_id),
+                nodes[next].start,
+                &split_end,
+            );
+            nodes[active_node]
+                .children
+                .insert(string[active_edge], split_node.id);
+            let new_leaf = Node::new(
+                nodes.len() + 1,
+                Some(split_node.id),
+                Some(root_id),
+                i,
+                &state.global_end,
+            );
+            split_node.children.insert(string[i], new_leaf.id);
+            nodes[next].start += active_length;
+            nodes[next].parent = Some(split_node.id);
+
+            split_node.children.insert(string[nodes[next].start], next);
+
+            if let Some(last_new_node_id) = last_new_node {
+                nodes[last_new_node_id].suffix_link = Some(split_node.id);
             }
+            last_new_node = Some(split_node.id);
+            nodes.push(split_node);
+            nodes.push(new_leaf);
         }
-    }
 
-    // Now to actually be able to find the suffix
-    // index for a given node, we need to run a
-    // traversal on the tree, and the index is then
-    // found by `s.len() - label_height`.
-    let mut stack = vec![(root_id, 0)];
-    while let Some((node_id, label_height)) = stack.pop() {
-        let new_height;
-        if node_id != root_id {
-            nodes[node_id].suffix_index = Some(nodes[node_id].start - label_height);
-            new_height = label_height + nodes[node_id].length();
-        } else {
-            new_height = label_height;
-        }
-        for n in nodes[node_id].children().values() {
-            stack.push((*n, new_height));
+        state.remaining_suffix_count -= 1;
+        if state.active_node == root_id && state.active_length > 0 {
+            state.active_length -= 1;
+            state.active_edge = i - state.remaining_suffix_count + 1;
+        } else if state.active_node != root_id {
+            state.active_node = nodes[state.active_node].suffix_link.unwrap();
         }
     }
+}
 
-    // Shrinks down nodes and bytes_and_sep to smallest
-    // possible capacity, since no more elements are
-    // added or removed from them now.
-    nodes.shrink_to_fit();
-    bytes_and_sep.shrink_to_fit();
+// An internal node stands for a substring that occurs at every suffix
+// index of the leaves below it, but the builders just store the index
+// of whichever suffix created the node. To make `longest_substring`
+// deterministic, every internal node is moved to the leftmost of them,
+// so a match always starts at the first occurrence in the string.
+// The label itself stays the same, only where it is taken from moves.
+fn leftmost_suffix_indices(nodes: &mut [Node]) {
+    let mut order = Vec::with_capacity(nodes.len());
+    let mut stack = vec![0];
+    while let Some(node_id) = stack.pop() {
+        order.push(node_id);
+        stack.extend(nodes[node_id].children().values());
+    }
 
-    // Move ownership of nodes and the LabelData
-    suffix_tree.nodes = nodes;
-    suffix_tree.string = bytes_and_sep;
+    // Goes through the children before their parents
+    for &node_id in order.iter().rev() {
+        if !nodes[node_id].is_internal_node() {
+            continue;
+        }
+        let leftmost = nodes[node_id]
+            .children()
+            .values()
+            .map(|&c| nodes[c].suffix_index.unwrap())
+            .min()
+            .unwrap();
 
-    // And finally return the suffix tree
-    suffix_tree
+        let node = &mut nodes[node_id];
+        let depth = node.end() - node.suffix_index.unwrap();
+        let length = node.length();
+        node.suffix_index = Some(leftmost);
+        node.start = leftmost + depth - length;
+        node.end.store(leftmost + depth, Ordering::SeqCst);
+    }
 }
 
 fn internal_contains_suffix(st: &SuffixTree, suffix: &[u8]) -> bool {
     // While the empty string is strictly a
     // suffix, I'm not sure if it makes sense
     // in practice, so for now just discard it
-    if suffix.len() == 0 {
+    if suffix.is_empty() {
         return false;
     }
 
@@ -366,13 +645,60 @@ fn internal_contains_suffix(st: &SuffixTree, suffix: &[u8]) -> bool {
     cur_node.is_leaf()
 }
 
+fn internal_contains_substring(st: &SuffixTree, bytes: &[u8]) -> bool {
+    internal_locate(st, bytes).is_some()
+}
+
+// Positions of every occurrence, which are the suffix indices of the
+// leaves below where the pattern ends, in increasing order
+fn internal_find_all(st: &SuffixTree, bytes: &[u8]) -> Vec<usize> {
+    let mut positions = vec![];
+    let mut stack: Vec<NodeId> = internal_locate(st, bytes).into_iter().collect();
+    while let Some(node_id) = stack.pop() {
+        let node = &st.nodes[node_id];
+        if node.is_leaf() {
+            positions.push(node.suffix_index.unwrap());
+        }
+        stack.extend(node.children().values());
+    }
+
+    // The leaf of the separator alone is the empty suffix, which only
+    // the empty pattern reaches
+    positions.retain(|&p| p < st.raw_bytes.len());
+    positions.sort_unstable();
+    positions
+}
+
+// The node at the end of the edge where the pattern ends, or None if the
+// pattern falls off the tree. The root for the empty pattern.
+fn internal_locate(st: &SuffixTree, bytes: &[u8]) -> Option<NodeId> {
+    // Always starts from root, and every byte has to be
+    // consumed before falling off an edge
+    let nodes = &st.nodes;
+    let mut cur_node = st.root();
+    let mut i = 0;
+    while i < bytes.len() {
+        let next_node_id = cur_node.child(&LabelData::new(bytes[i]))?;
+        let label = st.label_of_node(&nodes[*next_node_id]);
+        // The rest of the bytes may end in the middle of the label
+        for j in 1..label.len().min(bytes.len() - i) {
+            if bytes[i + j] != label[j] {
+                return None;
+            }
+        }
+        i += label.len();
+        cur_node = &nodes[*next_node_id];
+    }
+    Some(cur_node.id)
+}
+
 // Returns the starting index of the substring, and the ending index (not inclusive)
 // if one exists, otherwise returns None
 fn internal_longest_substring(st: &SuffixTree, bytes: &[u8]) -> Option<(usize, usize)> {
-    if bytes.len() == 0 {
-        // Todo: Panic or return None?
-        panic!("No bytes left to find substring on");
-        // return None;
+    // There is nothing to match for an empty slice, so no
+    // substring exists, which callers can treat as the end.
+    if bytes.is_empty() {
+        return None;
     }
 
     // Todo: Need to find the last node,
@@ -389,9 +715,12 @@ fn internal_longest_substring(st: &SuffixTree, bytes: &[u8]) -> Option<(usize, u
                 // in suffix, to make sure that they all match.
                 for j in 1..label.len() {
                     // If it runs out of bytes to check, or a byte does not
-                    // match, then the longest substring is found
+                    // match, then the longest substring is found. No byte
+                    // matches the separator, so a match running into it
+                    // ends right before it, at the end of the string.
                     if i + j >= bytes.len() || bytes[i + j] != label[j] {
                         let start = nodes[*next_node_id].suffix_index.unwrap();
+                        debug_assert!(start + i + j <= st.raw_bytes.len());
                         return Some((start, start + i + j));
                     }
                 }
@@ -416,11 +745,82 @@ fn internal_longest_substring(st: &SuffixTree, bytes: &[u8]) -> Option<(usize, u
     // If this point is ever reached, it is because
     // it did not break early (in the middle of a label)
     // so we can return the suffix_range of the current
-    // node.
+    // node. That is never a leaf, since every leaf label
+    // ends with the separator, which is always a break.
+    debug_assert!(!cur_node.is_leaf());
     let range = cur_node.suffix_range();
+    debug_assert!(range.end <= st.raw_bytes.len());
     Some((range.start, range.end))
 }
 
+fn internal_longest_substring_from(
+    st: &SuffixTree,
+    bytes: &[u8],
+    hint: Option<MatchState>,
+) -> Option<(usize, usize, MatchState)> {
+    if bytes.is_empty() {
+        return None;
+    }
+    let nodes = &st.nodes;
+
+    // Goes to where the first len - 1 bytes end, jumping over whole
+    // edges by their first byte, since they are known to be there. The
+    // suffix link of the node gets there with one byte less to skip, and
+    // without the link (from a `load`ed tree) it is skipped from the root.
+    let (mut node, known) = match hint {
+        Some(MatchState { node, len }) => {
+            let start = match nodes[node].suffix_link {
+                Some(link) if node != 0 => link,
+                _ => 0,
+            };
+            (start, (len - 1).min(bytes.len()))
+        }
+        None => (0, 0),
+    };
+    let mut depth = node_depth(&nodes[node]);
+    while depth < known {
+        let child = &nodes[*nodes[node].child(&LabelData::new(bytes[depth])).unwrap()];
+        if node_depth(child) > known {
+            break;
+        }
+        node = child.id;
+        depth = node_depth(child);
+    }
+
+    // From there on it is the usual match, one byte at a time, except
+    // that the edge it is in might be partly matched already
+    let mut len = known;
+    loop {
+        let child = match bytes.get(depth) {
+            Some(&b) => nodes[node].child(&LabelData::new(b)),
+            None => None,
+        };
+        let child = match child {
+            Some(&child) => &nodes[child],
+            // The match ends right at the node, like in longest_substring
+            None if node == 0 => return None,
+            None => {
+                let start = nodes[node].suffix_index.unwrap();
+                return Some((start, start + len, MatchState { node, len }));
+            }
+        };
+
+        let label = st.label_of_node(child);
+        let mut j = len - depth;
+        while j < label.len() && len < bytes.len() && bytes[len] == label[j] {
+            j += 1;
+            len += 1;
+        }
+        if j < label.len() {
+            let start = child.suffix_index.unwrap();
+            debug_assert!(start + len <= st.raw_bytes.len());
+            return Some((start, start + len, MatchState { node, len }));
+        }
+        node = child.id;
+        depth = node_depth(child);
+    }
+}
+
 #[cfg(test)]
 #[macro_use(quickcheck)]
 extern crate quickcheck_macros;
@@ -455,6 +855,40 @@ mod tests {
         SuffixTree::new("橡皮鸭");
     }
 
+    #[test]
+    fn invalid_utf8_bytes() {
+        let bytes = [0xC0, 0x80];
+        let st = SuffixTree::new_bytes(&bytes);
+        assert_eq!(&bytes, st.bytes());
+        assert_eq!(None, st.try_string());
+        assert!(st.contains_suffix(&bytes));
+        assert!(st.contains_suffix(&[0x80]));
+        assert!(!st.contains_suffix(&[0xC0]));
+        assert_eq!(Some((0, 2)), st.longest_substring(&[0xC0, 0x80, 0x00]));
+        assert_eq!(Some((1, 2)), st.longest_substring(&[0x80]));
+    }
+
+    #[test]
+    fn invalid_utf8_bytes_reversed_bom() {
+        let bytes = [0xFF, 0xFE, 0xFF];
+        let st = SuffixTree::new_bytes(&bytes);
+        assert_eq!(None, st.try_string());
+        assert!(st.contains_suffix(&[0xFE, 0xFF]));
+        assert_eq!(Some((0, 2)), st.longest_substring(&[0xFF, 0xFE, 0xFE]));
+        assert_eq!(Some((1, 3)), st.longest_substring(&[0xFE, 0xFF]));
+        assert_eq!(None, st.longest_substring(&[0x00]));
+        // Debug formatting must not assume the bytes are UTF-8
+        assert!(!format!("{:?}", st).is_empty());
+    }
+
+    #[test]
+    fn string_of_utf8_tree() {
+        let st = SuffixTree::new("ゴム製のアヒル");
+        assert_eq!(Some("ゴム製のアヒル"), st.try_string());
+        assert_eq!("ゴム製のアヒル", st.string());
+        assert!(!format!("{:?}", st).is_empty());
+    }
+
     #[test]
     fn longest_substring1() {
         let tree = SuffixTree::new("banana");
@@ -495,6 +929,28 @@ mod tests {
         assert_eq!((1, 4), result);
     }
 
+    // Matches that run to the end of a leaf edge stop right before the
+    // separator, whether they end in the middle of the label or not
+    #[test]
+    fn longest_substring_ending_at_a_leaf() {
+        let tree = SuffixTree::new("banana");
+        assert_eq!(Some((2, 6)), tree.longest_substring(b"nana"));
+        assert_eq!(Some((1, 2)), tree.longest_substring(b"a"));
+        assert_eq!(Some((0, 6)), tree.longest_substring(b"banana"));
+        assert_eq!(Some((0, 6)), tree.longest_substring(b"bananas"));
+        assert_eq!(Some((1, 2)), tree.longest_substring(b"ax"));
+    }
+
+    #[test]
+    fn suffix_range_of_leaves_excludes_the_separator() {
+        let tree = SuffixTree::new("banana");
+        for leaf in tree.nodes.iter().filter(|n| n.is_leaf()) {
+            let range = leaf.suffix_range();
+            assert_eq!(leaf.suffix_index.unwrap(), range.start);
+            assert_eq!(6, range.end);
+        }
+    }
+
     #[test]
     fn longest_substring_none() {
         let tree = SuffixTree::new("banana");
@@ -503,6 +959,92 @@ mod tests {
         assert_eq!(None, result);
     }
 
+    #[test]
+    fn longest_substring_empty() {
+        let tree = SuffixTree::new("banana");
+        assert_eq!(None, tree.longest_substring(&[]));
+    }
+
+    #[test]
+    fn contains_substring() {
+        let tree = SuffixTree::new("banana");
+        assert!(tree.contains_substring(b"ban"));
+        assert!(tree.contains_substring(b"anana"));
+        assert!(tree.contains_substring(b"nan"));
+        assert!(!tree.contains_substring(b"anab"));
+        assert!(!tree.contains_substring(b"bananas"));
+        assert!(!tree.contains_substring(b"xqr"));
+
+        let tree = SuffixTree::new("mississippi");
+        assert!(tree.contains_substring(b"issi"));
+        assert!(tree.contains_substring(b"issip"));
+        assert!(!tree.contains_substring(b"issipi"));
+    }
+
+    #[test]
+    fn contains_empty_substring() {
+        assert!(SuffixTree::new("banana").contains_substring(&[]));
+        assert!(SuffixTree::new("").contains_substring(&[]));
+    }
+
+    #[test]
+    fn depth_first() {
+        let tree = SuffixTree::new("banana");
+        let labels: Vec<String> = tree
+            .depth_first()
+            .skip(1)
+            .map(|n| tree.label_of_node_formatted(n))
+            .collect();
+        assert_eq!(tree.nodes.len(), labels.len() + 1);
+        assert_eq!("a", labels[1]);
+        assert!(tree.depth_first().next().unwrap().is_root());
+
+        // Every node is visited once, after its parent
+        let order: Vec<NodeId> = tree.depth_first().map(|n| n.id).collect();
+        let mut seen = std::collections::HashSet::new();
+        assert!(order.iter().all(|&id| {
+            let parent_seen = tree.node(id).parent.is_none_or(|p| seen.contains(&p));
+            parent_seen && seen.insert(id)
+        }));
+        assert_eq!(tree.nodes.len(), seen.len());
+
+        let root_children: Vec<NodeId> = tree.children_of(tree.root()).map(|n| n.id).collect();
+        assert_eq!(4, root_children.len());
+        assert!(root_children
+            .iter()
+            .all(|&c| tree.node(c).parent == Some(0)));
+    }
+
+    #[test]
+    fn find_all() {
+        let tree = SuffixTree::new("mississippi");
+        assert_eq!(vec![1, 4], tree.find_all(b"issi"));
+        assert_eq!(vec![2, 5], tree.find_all(b"ss"));
+        assert_eq!(vec![1, 4, 7, 10], tree.find_all(b"i"));
+        assert_eq!(vec![0], tree.find_all(b"mississippi"));
+        assert!(tree.find_all(b"issipi").is_empty());
+        assert_eq!((0..11).collect::<Vec<_>>(), tree.find_all(&[]));
+    }
+
+    #[quickcheck]
+    fn quickcheck_find_all_matches_naive(bytes: Vec<u8>, query: Vec<u8>) -> bool {
+        let st = SuffixTree::from_suffix_array(&bytes);
+        let naive: Vec<usize> = (0..bytes.len())
+            .filter(|&i| bytes[i..].starts_with(&query))
+            .collect();
+        st.find_all(&query) == naive
+            && (0..bytes.len()).all(|i| st.find_all(&bytes[i..i + 1]).contains(&i))
+    }
+
+    #[quickcheck]
+    fn quickcheck_contains_substring_matches_naive(bytes: Vec<u8>, query: Vec<u8>) -> bool {
+        let st = SuffixTree::new_bytes(&bytes);
+        let naive = query.is_empty() || bytes.windows(query.len()).any(|w| w == &query[..]);
+        let all_substrings = (0..bytes.len())
+            .all(|i| (i..=bytes.len()).all(|j| st.contains_substring(&bytes[i..j])));
+        st.contains_substring(&query) == naive && all_substrings
+    }
+
     #[test]
     fn does_not_contain_empty_string_as_suffix() {
         let st = SuffixTree::new("banana");
@@ -533,6 +1075,268 @@ mod tests {
         true
     }
 
+    #[quickcheck]
+    fn quickcheck_contains_all_suffixes_bytes(bytes: Vec<u8>) -> bool {
+        let st = SuffixTree::new_bytes(&bytes);
+        (0..bytes.len()).all(|i| st.contains_suffix(&bytes[i..]))
+    }
+
+    // Both builders should make the same tree, so every query gives
+    // the same match, and the match really is there.
+    #[quickcheck]
+    fn quickcheck_suffix_array_tree_matches_ukkonen(bytes: Vec<u8>, queries: Vec<Vec<u8>>) -> bool {
+        let ukkonen = SuffixTree::new_bytes(&bytes);
+        let sa_tree = SuffixTree::from_suffix_array(&bytes);
+
+        sa_tree.nodes.len() == ukkonen.nodes.len()
+            && (0..bytes.len()).all(|i| sa_tree.contains_suffix(&bytes[i..]))
+            && queries.iter().chain(std::iter::once(&bytes)).all(|q| {
+                let m = sa_tree.longest_substring(q);
+                m == ukkonen.longest_substring(q)
+                    && m.is_none_or(|(start, end)| bytes[start..end] == q[..end - start])
+            })
+    }
+
+    #[test]
+    fn extend() {
+        let mut st = SuffixTree::new("missi");
+        st.extend("ssippi");
+        assert_eq!(b"mississippi", st.bytes());
+        assert_eq!(12, st.nodes.iter().filter(|n| n.is_leaf()).count());
+        assert_eq!(Some((2, 5)), st.longest_substring(b"ssi"));
+        assert_eq!(vec![2, 5], st.find_all(b"ssi"));
+        assert_eq!(
+            format!("{:?}", SuffixTree::new("mississippi")),
+            format!("{:?}", st)
+        );
+    }
+
+    #[test]
+    fn extend_a_tree_from_the_suffix_array() {
+        let mut st = SuffixTree::from_suffix_array(b"banana");
+        st.extend("band");
+        st.extend("");
+        assert_eq!(
+            format!("{:?}", SuffixTree::new("bananaband")),
+            format!("{:?}", st)
+        );
+    }
+
+    // Extending piece by piece makes the same tree as building it from
+    // the whole string at once. Compared by the labels and not with the
+    // Debug output, which is quadratic in the length of the string.
+    #[quickcheck]
+    fn quickcheck_extend_matches_new(pieces: Vec<Vec<u8>>) -> bool {
+        let mut st = SuffixTree::new_bytes(&[]);
+        for piece in pieces.iter() {
+            st.extend(piece);
+        }
+        let labels = |st: &SuffixTree| {
+            st.depth_first()
+                .map(|n| (n.start, n.end(), n.suffix_index, n.children().len()))
+                .collect::<Vec<_>>()
+        };
+        st.bytes() == &pieces.concat()[..]
+            && labels(&SuffixTree::new_bytes(&pieces.concat())) == labels(&st)
+    }
+
+    // For an internal node with the path xa, the suffix link goes to the
+    // node with the path a, which is the root if a is empty
+    fn suffix_links_are_correct(st: &SuffixTree) -> bool {
+        st.depth_first()
+            .all(|node| match st.suffix_link_of(node.id) {
+                Some(link) => {
+                    let path = &st.bytes()[node.suffix_range()];
+                    let link = st.node(link);
+                    let link_path = match link.is_root() {
+                        true => &[][..],
+                        false => &st.bytes()[link.suffix_range()],
+                    };
+                    !link.is_leaf() && link_path == &path[1..]
+                }
+                None => !node.is_internal_node(),
+            })
+    }
+
+    #[quickcheck]
+    fn quickcheck_suffix_links(bytes: Vec<u8>, tail: Vec<u8>) -> bool {
+        let bytes: Vec<u8> = bytes.iter().map(|b| b'a' + b % 3).collect();
+        let mut extended = SuffixTree::new_bytes(&bytes);
+        extended.extend(&tail);
+
+        let mut saved = vec![];
+        SuffixTree::from_suffix_array(&bytes)
+            .save(&mut saved)
+            .unwrap();
+        [
+            SuffixTree::new_bytes(&bytes),
+            SuffixTree::from_suffix_array(&bytes),
+            SuffixTree::load(&saved[..]).unwrap(),
+            extended,
+        ]
+        .iter()
+        .all(suffix_links_are_correct)
+    }
+
+    #[test]
+    fn input_that_looks_like_the_separator() {
+        let st = SuffixTree::new("a<$>");
+        let leaf = |suffix_index| {
+            st.depth_first()
+                .find(|n| n.is_leaf() && n.suffix_index == Some(suffix_index))
+                .unwrap()
+        };
+
+        // Printed, the bytes and the separator look the same...
+        let bytes_leaf = leaf(1);
+        let sep_leaf = leaf(4);
+        assert_eq!("<$><$>", st.label_of_node_formatted(bytes_leaf));
+        assert_eq!("<$>", st.label_of_node_formatted(sep_leaf));
+
+        // ...but not as labels, which is what the matching goes by
+        let bytes: Vec<LabelData> = b"<$>".iter().map(|&b| LabelData::new(b)).collect();
+        assert_eq!(&bytes[..], &st.label_of_node(bytes_leaf)[..3]);
+        assert_eq!(LabelData::Sep, st.label_of_node(bytes_leaf)[3]);
+        assert_eq!(&[LabelData::Sep], st.label_of_node(sep_leaf));
+        assert_eq!(Some((1, 4)), st.longest_substring(b"<$>"));
+        assert!(st.contains_suffix(b"<$>"));
+    }
+
+    #[test]
+    fn prefix_match_len_of_mississippi() {
+        let st = SuffixTree::new("mississippi");
+        assert_eq!(3, st.prefix_match_len(b"issa"));
+        assert_eq!(11, st.prefix_match_len(b"mississippi"));
+        assert_eq!(11, st.prefix_match_len(b"mississippis"));
+        assert_eq!(2, st.prefix_match_len(b"pix"));
+        assert_eq!(0, st.prefix_match_len(b"x"));
+        assert_eq!(0, st.prefix_match_len(b""));
+    }
+
+    #[quickcheck]
+    fn quickcheck_prefix_match_len(bytes: Vec<u8>, query: Vec<u8>) -> bool {
+        let st = SuffixTree::new_bytes(&bytes);
+        let len = st.prefix_match_len(&query);
+        let occurs = |n: usize| n == 0 || bytes.windows(n).any(|w| w == &query[..n]);
+        occurs(len) && (len == query.len() || !occurs(len + 1))
+    }
+
+    #[test]
+    fn node_count_and_max_depth_of_mississippi() {
+        for st in &[
+            SuffixTree::new("mississippi"),
+            SuffixTree::from_suffix_array(b"mississippi"),
+        ] {
+            // The root, 6 internal nodes and a leaf for every suffix
+            assert_eq!(19, st.node_count());
+            assert_eq!(st.depth_first().count(), st.node_count());
+            assert_eq!(11, st.max_depth());
+        }
+
+        let mut st = SuffixTree::new("missi");
+        st.extend("ssippi");
+        assert_eq!(19, st.node_count());
+        assert_eq!(11, st.max_depth());
+
+        assert_eq!(2, SuffixTree::new("").node_count());
+        assert_eq!(0, SuffixTree::new("").max_depth());
+    }
+
+    #[test]
+    fn suffix_links_of_mississippi() {
+        let st = SuffixTree::new("mississippi");
+        assert!(suffix_links_are_correct(&st));
+        assert_eq!(None, st.suffix_link_of(st.root().id));
+        let issi = st
+            .depth_first()
+            .find(|n| n.is_internal_node() && &st.bytes()[n.suffix_range()] == b"issi")
+            .unwrap();
+        let ssi = st.node(st.suffix_link_of(issi.id).unwrap());
+        assert_eq!(b"ssi", &st.bytes()[ssi.suffix_range()]);
+    }
+
+    // The match of every suffix of the query with the hint from the one
+    // before is the same as the match from the root. Over only three
+    // different bytes, so the matches are long enough to skip over edges.
+    #[quickcheck]
+    fn quickcheck_longest_substring_from_matches(bytes: Vec<u8>, query: Vec<u8>) -> bool {
+        let bytes: Vec<u8> = bytes.iter().map(|b| b'a' + b % 3).collect();
+        let query: Vec<u8> = query.iter().map(|b| b'a' + b % 3).collect();
+        [
+            SuffixTree::new_bytes(&bytes),
+            SuffixTree::from_suffix_array(&bytes),
+        ]
+        .iter()
+        .all(|st| {
+            let mut hint = None;
+            (0..query.len()).all(|i| {
+                let m = st.longest_substring_from(&query[i..], hint);
+                hint = m.map(|(_, _, state)| state);
+                m.map(|(start, end, _)| (start, end)) == st.longest_substring(&query[i..])
+            })
+        })
+    }
+
+    #[test]
+    fn matching_statistics_of_mississippi() {
+        let st = SuffixTree::from_suffix_array(b"mississippi");
+        let query = b"sissippis";
+        let mut hint = None;
+        let mut lengths = vec![];
+        for i in 0..query.len() {
+            let (start, end, state) = st.longest_substring_from(&query[i..], hint).unwrap();
+            lengths.push(end - start);
+            hint = Some(state);
+        }
+        assert_eq!(vec![8, 7, 6, 5, 4, 3, 2, 2, 1], lengths);
+    }
+
+    // Runs the queries right into the end of the string, where the
+    // separator would be next
+    #[quickcheck]
+    fn quickcheck_longest_substring_stays_in_bounds(bytes: Vec<u8>, tail: Vec<u8>) -> bool {
+        let st = SuffixTree::from_suffix_array(&bytes);
+        (0..bytes.len()).all(|i| {
+            let query = [&bytes[i..], &tail[..]].concat();
+            match st.longest_substring(&query) {
+                Some((start, end)) => end <= bytes.len() && end - start >= bytes.len() - i,
+                None => false,
+            }
+        })
+    }
+
+    // A match starts at the first occurrence of the matched bytes
+    #[quickcheck]
+    fn quickcheck_longest_substring_is_leftmost(bytes: Vec<u8>, query: Vec<u8>) -> bool {
+        let st = SuffixTree::new_bytes(&bytes);
+        match st.longest_substring(&query) {
+            None => query.is_empty() || !bytes.contains(&query[0]),
+            Some((start, end)) => {
+                let matched = &query[..end - start];
+                bytes.windows(matched.len()).position(|w| w == matched) == Some(start)
+            }
+        }
+    }
+
+    #[test]
+    fn longest_substring_repeated() {
+        let st = SuffixTree::new("abcXabcYabc");
+        assert_eq!(Some((0, 3)), st.longest_substring(b"abc"));
+        assert_eq!(Some((4, 8)), st.longest_substring(b"abcY"));
+        assert_eq!(Some((1, 3)), st.longest_substring(b"bcZ"));
+        // Matches ending at a node boundary are leftmost too
+        assert_eq!(Some((1, 3)), st.longest_substring(b"bc"));
+    }
+
+    #[test]
+    fn suffix_array_tree_of_banana() {
+        let st = SuffixTree::from_suffix_array(b"banana");
+        assert!(st.contains_suffix(b"ana"));
+        assert!(!st.contains_suffix(b"ban"));
+        assert_eq!(Some((1, 4)), st.longest_substring(b"anax"));
+        assert_eq!(Some((0, 6)), st.longest_substring(b"banana"));
+    }
+
     #[quickcheck]
     fn quickcheck_every_internal_node_has_at_least_two_children(s: String) -> bool {
         SuffixTree::new(&s)
@@ -0,0 +1,111 @@
+// Matching statistics of a string against the tree: the longest match
+// starting at every position of the string. The match at i + 1 is at
+// least the one at i minus its first byte, so each one carries on from
+// the last through `longest_substring_from`, and the whole string takes
+// linear time instead of a walk from the root per position.
+use super::SuffixTree;
+
+pub struct MatchingStatistics<'a> {
+  suffix_tree: &'a SuffixTree,
+}
+
+impl<'a> MatchingStatistics<'a> {
+  pub fn new(suffix_tree: &'a SuffixTree) -> Self {
+    MatchingStatistics { suffix_tree }
+  }
+
+  // The longest match at every position of bytes, as its (start, end)
+  // range in the string of the tree, which is empty where not even
+  // the byte itself is in the tree
+  pub fn compute(&self, bytes: &[u8]) -> Vec<(usize, usize)> {
+    let mut hint = None;
+    (0..bytes.len())
+      .map(
+        |i| match self.suffix_tree.longest_substring_from(&bytes[i..], hint) {
+          Some((start, end, state)) => {
+            hint = Some(state);
+            (start, end)
+          }
+          None => {
+            hint = None;
+            (0, 0)
+          }
+        },
+      )
+      .collect()
+  }
+
+  // The greedy Lempel-Ziv factors of bytes against the tree, which are
+  // the matches at the positions where the previous factor ended, found
+  // in one pass over the matching statistics. The same factors as taking
+  // `longest_substring` from where the last one ended, as long as every
+  // byte is in the tree. None if one is not.
+  pub fn factors(&self, bytes: &[u8]) -> Option<Vec<(usize, usize)>> {
+    let mut factors = vec![];
+    let mut next_factor = 0;
+    let mut hint = None;
+    for i in 0..bytes.len() {
+      // Every byte inside of a factor matches at least the rest of the
+      // factor, so this is only None where a factor should start
+      let (start, end, state) = self.suffix_tree.longest_substring_from(&bytes[i..], hint)?;
+      if i == next_factor {
+        factors.push((start, end));
+        next_factor = i + end - start;
+      }
+      hint = Some(state);
+    }
+    Some(factors)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // The factors as the encoder has always found them
+  fn greedy(st: &SuffixTree, bytes: &[u8]) -> Option<Vec<(usize, usize)>> {
+    let mut factors = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+      let (start, end) = st.longest_substring(&bytes[i..])?;
+      factors.push((start, end));
+      i += end - start;
+    }
+    Some(factors)
+  }
+
+  #[test]
+  fn compute() {
+    let st = SuffixTree::from_suffix_array(b"banana");
+    let ms = MatchingStatistics::new(&st);
+    assert_eq!(
+      vec![(0, 3), (1, 3), (2, 3), (0, 0), (1, 2)],
+      ms.compute(b"banxa")
+    );
+    assert!(ms.compute(b"").is_empty());
+  }
+
+  #[test]
+  fn factors_of_a_missing_byte() {
+    let st = SuffixTree::from_suffix_array(b"ACGT");
+    let ms = MatchingStatistics::new(&st);
+    assert_eq!(Some(vec![(0, 3), (1, 2)]), ms.factors(b"ACGC"));
+    assert_eq!(None, ms.factors(b"ACGN"));
+  }
+
+  #[quickcheck]
+  fn quickcheck_factors_match_greedy(reference: Vec<u8>, bytes: Vec<u8>) -> bool {
+    let reference: Vec<u8> = reference.iter().map(|b| b'a' + b % 3).collect();
+    let bytes: Vec<u8> = bytes.iter().map(|b| b'a' + b % 3).collect();
+    let st = SuffixTree::from_suffix_array(&reference);
+    let ms = MatchingStatistics::new(&st);
+    ms.factors(&bytes) == greedy(&st, &bytes)
+      && ms
+        .compute(&bytes)
+        .iter()
+        .enumerate()
+        .all(|(i, &(start, end))| {
+          st.longest_substring(&bytes[i..]).unwrap_or((0, 0)) == (start, end)
+        })
+  }
+}
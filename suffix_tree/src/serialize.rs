@@ -0,0 +1,275 @@
+// A small binary format for saving a built tree, so a big reference
+// does not have to be indexed again. Every number is a little endian
+// u64, and None is u64::MAX:
+//
+//   magic, length of the string, the string,
+//   number of nodes, and for every node:
+//     parent, suffix link, start, end, suffix index,
+//     number of children and for every child its first
+//     "character" (the byte, or 256 for the separator) and id
+//
+// The leaves all share the global end of the tree, which is what lets
+// Ukkonen's algorithm extend them in one go, so they are given a single
+// shared end again when loading.
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
+use std::sync::atomic::AtomicUsize;
+use std::sync::Arc;
+
+use super::types::label_data::LabelData;
+use super::types::node::Node;
+use super::SuffixTree;
+
+const MAGIC: &[u8; 8] = b"SFXTREE1";
+const NONE: u64 = u64::MAX;
+const SEP: u64 = 256;
+
+pub(crate) fn save<W: Write>(st: &SuffixTree, mut w: W) -> io::Result<()> {
+  w.write_all(MAGIC)?;
+  write_u64(&mut w, st.raw_bytes.len() as u64)?;
+  w.write_all(&st.raw_bytes)?;
+
+  write_u64(&mut w, st.nodes.len() as u64)?;
+  for node in st.nodes.iter() {
+    write_option(&mut w, node.parent)?;
+    write_option(&mut w, node.suffix_link)?;
+    write_u64(&mut w, node.start as u64)?;
+    write_u64(&mut w, node.end() as u64)?;
+    write_option(&mut w, node.suffix_index)?;
+
+    write_u64(&mut w, node.children.len() as u64)?;
+    for (label, &child) in node.children.iter() {
+      let label = match label {
+        LabelData::Byte(b) => *b as u64,
+        LabelData::Sep => SEP,
+      };
+      write_u64(&mut w, label)?;
+      write_u64(&mut w, child as u64)?;
+    }
+  }
+  w.flush()
+}
+
+pub(crate) fn load<R: Read>(mut r: R) -> io::Result<SuffixTree> {
+  let mut magic = [0; 8];
+  r.read_exact(&mut magic)?;
+  if &magic != MAGIC {
+    return Err(invalid("not a saved suffix tree"));
+  }
+
+  // Read up to the length instead of allocating it up front, since
+  // it is not to be trusted either
+  let len = read_u64(&mut r)?;
+  let mut raw_bytes = vec![];
+  r.by_ref().take(len).read_to_end(&mut raw_bytes)?;
+  if raw_bytes.len() as u64 != len {
+    return Err(invalid("string is shorter than its length"));
+  }
+  let mut string: Vec<LabelData> = raw_bytes.iter().map(|&b| LabelData::new(b)).collect();
+  string.push(LabelData::Sep);
+
+  let count = read_usize(&mut r)?;
+  let node_id = |id: Option<usize>| match id {
+    Some(id) if id >= count => Err(invalid("node id out of bounds")),
+    _ => Ok(id),
+  };
+  let global_end = Arc::new(AtomicUsize::new(string.len()));
+  // Capped, since the count is not to be trusted before the nodes are read
+  let mut nodes = Vec::with_capacity(count.min(1 << 20));
+  for id in 0..count {
+    let parent = node_id(read_option(&mut r)?)?;
+    let suffix_link = node_id(read_option(&mut r)?)?;
+    let start = read_usize(&mut r)?;
+    let end = read_usize(&mut r)?;
+    let suffix_index = read_option(&mut r)?;
+    if start > end || end > string.len() {
+      return Err(invalid("label out of bounds"));
+    }
+
+    if (id == 0) != parent.is_none() {
+      return Err(invalid("only the first node can be the root"));
+    }
+    match suffix_index {
+      Some(suffix_index) if suffix_index <= end => {}
+      None if id == 0 => {}
+      _ => return Err(invalid("suffix index out of bounds")),
+    }
+
+    let mut node = Node::new(id, parent, suffix_link, start, &global_end);
+    node.suffix_index = suffix_index;
+    for _ in 0..read_usize(&mut r)? {
+      let label = match read_u64(&mut r)? {
+        SEP => LabelData::Sep,
+        b => LabelData::new(u8::try_from(b).map_err(|_| invalid("label is not a byte"))?),
+      };
+      let child = node_id(Some(read_usize(&mut r)?))?.unwrap();
+      node.children.insert(label, child);
+    }
+    if !node.is_leaf() || end != string.len() {
+      node.end = Arc::new(AtomicUsize::new(end));
+    }
+    nodes.push(node);
+  }
+  check_tree(&nodes)?;
+
+  Ok(SuffixTree {
+    raw_bytes,
+    nodes,
+    string,
+    online: None,
+  })
+}
+
+// Every node but the root has to be the child of its parent and of no
+// other node, and be reachable from the root, so walking the tree can
+// neither loop nor miss a node
+fn check_tree(nodes: &[Node]) -> io::Result<()> {
+  if nodes.is_empty() {
+    return Err(invalid("the tree has no root"));
+  }
+  let mut seen = vec![false; nodes.len()];
+  seen[0] = true;
+  let mut stack = vec![0];
+  while let Some(id) = stack.pop() {
+    for (_, &child) in nodes[id].children.iter() {
+      if seen[child] || nodes[child].parent != Some(id) {
+        return Err(invalid("the nodes do not form a tree"));
+      }
+      seen[child] = true;
+      stack.push(child);
+    }
+  }
+  if seen.contains(&false) {
+    return Err(invalid("not every node is in the tree"));
+  }
+  Ok(())
+}
+
+fn invalid(message: &str) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn write_u64<W: Write>(w: &mut W, x: u64) -> io::Result<()> {
+  w.write_all(&x.to_le_bytes())
+}
+
+fn write_option<W: Write>(w: &mut W, x: Option<usize>) -> io::Result<()> {
+  write_u64(w, x.map_or(NONE, |x| x as u64))
+}
+
+fn read_u64<R: Read>(r: &mut R) -> io::Result<u64> {
+  let mut bytes = [0; 8];
+  r.read_exact(&mut bytes)?;
+  Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_usize<R: Read>(r: &mut R) -> io::Result<usize> {
+  usize::try_from(read_u64(r)?).map_err(|_| invalid("number too large for this platform"))
+}
+
+fn read_option<R: Read>(r: &mut R) -> io::Result<Option<usize>> {
+  match read_u64(r)? {
+    NONE => Ok(None),
+    x => usize::try_from(x)
+      .map(Some)
+      .map_err(|_| invalid("number too large for this platform")),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn round_trip(st: &SuffixTree) -> SuffixTree {
+    let mut bytes = vec![];
+    save(st, &mut bytes).unwrap();
+    load(&bytes[..]).unwrap()
+  }
+
+  #[test]
+  fn leaves_share_the_global_end() {
+    let st = round_trip(&SuffixTree::new("mississippi"));
+    let leaves: Vec<&Node> = st.nodes.iter().filter(|n| n.is_leaf()).collect();
+    assert_eq!(12, leaves.len());
+    assert!(leaves.iter().all(|l| Arc::ptr_eq(&l.end, &leaves[0].end)));
+    assert!(st
+      .nodes
+      .iter()
+      .filter(|n| n.is_internal_node())
+      .all(|n| !Arc::ptr_eq(&n.end, &leaves[0].end)));
+  }
+
+  #[test]
+  fn rejects_other_data() {
+    assert!(load(&b"not a tree at all"[..]).is_err());
+
+    let mut bytes = vec![];
+    save(&SuffixTree::new("banana"), &mut bytes).unwrap();
+    assert!(load(&bytes[..bytes.len() - 1]).is_err());
+  }
+
+  #[test]
+  fn rejects_a_huge_length() {
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend_from_slice(&(1u64 << 45).to_le_bytes());
+    assert_eq!(
+      io::ErrorKind::InvalidData,
+      load(&bytes[..]).unwrap_err().kind()
+    );
+  }
+
+  // Saves the tree after breaking it, and loads it back
+  fn load_broken(break_tree: impl Fn(&mut SuffixTree)) -> io::Result<SuffixTree> {
+    let mut st = SuffixTree::new("banana");
+    break_tree(&mut st);
+    let mut bytes = vec![];
+    save(&st, &mut bytes).unwrap();
+    load(&bytes[..])
+  }
+
+  #[test]
+  fn rejects_broken_trees() {
+    let leaf = |st: &SuffixTree| st.nodes.iter().position(|n| n.is_leaf()).unwrap();
+    let broken = [
+      load_broken(|st| {
+        let leaf = leaf(st);
+        st.nodes[leaf].suffix_index = None;
+      }),
+      load_broken(|st| {
+        let leaf = leaf(st);
+        st.nodes[leaf].suffix_index = Some(100);
+      }),
+      // A child that points back at the root makes a cycle
+      load_broken(|st| {
+        let leaf = leaf(st);
+        st.nodes[leaf].children.insert(LabelData::Byte(b'x'), 0);
+      }),
+      // The same node as the child of two nodes
+      load_broken(|st| {
+        let (_, &child) = st.nodes[0].children.iter().next().unwrap();
+        let leaf = leaf(st);
+        st.nodes[leaf].children.insert(LabelData::Byte(b'x'), child);
+      }),
+    ];
+    for result in broken.iter() {
+      assert_eq!(
+        io::ErrorKind::InvalidData,
+        result.as_ref().unwrap_err().kind()
+      );
+    }
+  }
+
+  #[quickcheck]
+  fn quickcheck_loaded_tree_matches(bytes: Vec<u8>, queries: Vec<Vec<u8>>) -> bool {
+    let st = SuffixTree::new_bytes(&bytes);
+    let loaded = round_trip(&st);
+    round_trip(&SuffixTree::from_suffix_array(&bytes));
+    let mut extended = SuffixTree::new_bytes(&bytes);
+    extended.extend(&bytes);
+    round_trip(&extended);
+    format!("{:?}", st) == format!("{:?}", loaded)
+      && queries
+        .iter()
+        .all(|q| st.longest_substring(q) == loaded.longest_substring(q))
+  }
+}
@@ -0,0 +1,317 @@
+// Builds the suffix tree from the suffix array and the LCP array of the
+// string instead of with Ukkonen's algorithm. The suffix array is built
+// with SA-IS (Nong, Zhang & Chan, 2009) and the LCP array with Kasai et
+// al.'s algorithm, both linear. The tree is then made in a single pass
+// over the suffixes in sorted order, which never has to walk down the
+// tree, follow suffix links or split edges more than once.
+use std::sync::atomic::AtomicUsize;
+use std::sync::Arc;
+
+use super::types::label_data::LabelData;
+use super::types::node::{Node, NodeId};
+use super::{leftmost_suffix_indices, node_depth, SuffixTree};
+
+const EMPTY: usize = usize::MAX;
+
+pub(crate) fn to_suffix_tree(s: &[u8]) -> SuffixTree {
+  let mut bytes_and_sep = s.iter().map(|&b| LabelData::new(b)).collect::<Vec<_>>();
+  bytes_and_sep.push(LabelData::Sep);
+
+  // The separator is the smallest "character", and it only
+  // appears once at the end, which SA-IS needs.
+  let text: Vec<usize> = s
+    .iter()
+    .map(|&b| b as usize + 1)
+    .chain(std::iter::once(0))
+    .collect();
+  let sa = suffix_array(&text, 257);
+  let lcp = lcp_array(&text, &sa);
+  drop(text);
+
+  let n = bytes_and_sep.len();
+  let global_end = Arc::new(AtomicUsize::new(n));
+  let root_id = 0;
+  let mut nodes = vec![Node::new(root_id, None, None, 0, &global_end)];
+
+  // The path from the root to the last leaf added, as pairs of
+  // node id and the length of the string spelled out by the path
+  // to that node.
+  let mut stack: Vec<(NodeId, usize)> = vec![(root_id, 0)];
+  for (&suffix, &common) in sa.iter().zip(lcp.iter()) {
+    // Goes up to the deepest node shared with the previous suffix
+    let mut last = None;
+    while stack.last().unwrap().1 > common {
+      last = stack.pop();
+    }
+    let (mut parent, mut parent_depth) = *stack.last().unwrap();
+
+    // The previous suffix branches off in the middle of an edge,
+    // so it has to be split by a new internal node.
+    if parent_depth < common {
+      let (child, _) = last.unwrap();
+      let suffix_index = nodes[child].suffix_index.unwrap();
+      let start = suffix_index + parent_depth;
+      let split_end = Arc::new(AtomicUsize::new(suffix_index + common));
+      let mut split_node = Node::new(nodes.len(), Some(parent), None, start, &split_end);
+      split_node.suffix_index = Some(suffix_index);
+
+      nodes[child].start = suffix_index + common;
+      nodes[child].parent = Some(split_node.id);
+      split_node
+        .children
+        .insert(bytes_and_sep[suffix_index + common], child);
+      nodes[parent]
+        .children
+        .insert(bytes_and_sep[start], split_node.id);
+
+      parent = split_node.id;
+      parent_depth = common;
+      stack.push((split_node.id, common));
+      nodes.push(split_node);
+    }
+
+    let start = suffix + parent_depth;
+    let mut leaf = Node::new(nodes.len(), Some(parent), None, start, &global_end);
+    leaf.suffix_index = Some(suffix);
+    nodes[parent].children.insert(bytes_and_sep[start], leaf.id);
+    stack.push((leaf.id, n - suffix));
+    nodes.push(leaf);
+  }
+
+  leftmost_suffix_indices(&mut nodes);
+  suffix_links(&mut nodes, &bytes_and_sep);
+  nodes.shrink_to_fit();
+  SuffixTree {
+    raw_bytes: s.to_vec(),
+    nodes,
+    string: bytes_and_sep,
+    online: None,
+  }
+}
+
+// The suffix link of an internal node spelling xa goes to the node
+// spelling a, which is always there in a suffix tree. The parents are
+// linked before their children, so the link of a node is found by going
+// down from the link of its parent, jumping over whole edges by their
+// first byte, since the path is known to be there.
+fn suffix_links(nodes: &mut [Node], string: &[LabelData]) {
+  let mut stack = vec![0];
+  while let Some(id) = stack.pop() {
+    stack.extend(nodes[id].children().values());
+    if !nodes[id].is_internal_node() {
+      continue;
+    }
+
+    let parent = nodes[id].parent.unwrap();
+    let mut link = if parent == 0 {
+      0
+    } else {
+      nodes[parent].suffix_link.unwrap()
+    };
+    let start = nodes[id].suffix_index.unwrap() + 1;
+    let depth = node_depth(&nodes[id]) - 1;
+    while node_depth(&nodes[link]) < depth {
+      let depth_of_link = node_depth(&nodes[link]);
+      link = *nodes[link].child(&string[start + depth_of_link]).unwrap();
+    }
+    debug_assert_eq!(depth, node_depth(&nodes[link]));
+    nodes[id].suffix_link = Some(link);
+  }
+}
+
+// The suffix array of the text, where the characters are less than
+// `alphabet` and the last character is a unique smallest one.
+fn suffix_array(text: &[usize], alphabet: usize) -> Vec<usize> {
+  let n = text.len();
+  if n == 1 {
+    return vec![0];
+  }
+
+  // true for S-type positions, where the suffix is smaller than the next
+  let mut s_type = vec![false; n];
+  s_type[n - 1] = true;
+  for i in (0..n - 1).rev() {
+    s_type[i] = text[i] < text[i + 1] || (text[i] == text[i + 1] && s_type[i + 1]);
+  }
+  let is_lms = |i: usize| i > 0 && s_type[i] && !s_type[i - 1];
+
+  let mut bucket_sizes = vec![0; alphabet];
+  for &c in text {
+    bucket_sizes[c] += 1;
+  }
+
+  // Sorts the LMS substrings, which only needs them in any order
+  let lms: Vec<usize> = (1..n).filter(|&i| is_lms(i)).collect();
+  let mut sa = vec![EMPTY; n];
+  induce(text, &s_type, &bucket_sizes, &lms, &mut sa);
+
+  // Names every LMS substring by its rank among them, where equal
+  // substrings get the same name
+  let mut names = vec![EMPTY; n];
+  let mut name = 0;
+  let mut previous: Option<usize> = None;
+  for &i in sa.iter().filter(|&&i| is_lms(i)) {
+    if let Some(p) = previous {
+      if !lms_substrings_equal(text, &s_type, p, i) {
+        name += 1;
+      }
+    }
+    names[i] = name;
+    previous = Some(i);
+  }
+  let name_count = name + 1;
+
+  // The LMS suffixes are sorted by the suffix array of the string of
+  // names, which has to be computed recursively unless they are unique
+  let reduced: Vec<usize> = lms.iter().map(|&i| names[i]).collect();
+  drop(names);
+  let reduced_sa = if name_count == reduced.len() {
+    let mut reduced_sa = vec![0; reduced.len()];
+    for (i, &c) in reduced.iter().enumerate() {
+      reduced_sa[c] = i;
+    }
+    reduced_sa
+  } else {
+    suffix_array(&reduced, name_count)
+  };
+  let sorted_lms: Vec<usize> = reduced_sa.iter().map(|&i| lms[i]).collect();
+
+  sa.iter_mut().for_each(|i| *i = EMPTY);
+  induce(text, &s_type, &bucket_sizes, &sorted_lms, &mut sa);
+  sa
+}
+
+// Places the LMS positions at the end of their buckets, keeping their
+// order, and then induces the L-type and S-type positions from them.
+fn induce(
+  text: &[usize],
+  s_type: &[bool],
+  bucket_sizes: &[usize],
+  lms: &[usize],
+  sa: &mut [usize],
+) {
+  let bucket_heads = || {
+    let mut sum = 0;
+    bucket_sizes
+      .iter()
+      .map(|&size| {
+        sum += size;
+        sum - size
+      })
+      .collect::<Vec<_>>()
+  };
+  let bucket_tails = || {
+    let mut sum = 0;
+    bucket_sizes
+      .iter()
+      .map(|&size| {
+        sum += size;
+        sum
+      })
+      .collect::<Vec<_>>()
+  };
+
+  let mut tails = bucket_tails();
+  for &i in lms.iter().rev() {
+    tails[text[i]] -= 1;
+    sa[tails[text[i]]] = i;
+  }
+
+  let mut heads = bucket_heads();
+  for k in 0..sa.len() {
+    let i = sa[k];
+    if i != EMPTY && i > 0 && !s_type[i - 1] {
+      sa[heads[text[i - 1]]] = i - 1;
+      heads[text[i - 1]] += 1;
+    }
+  }
+
+  let mut tails = bucket_tails();
+  for k in (0..sa.len()).rev() {
+    let i = sa[k];
+    if i != EMPTY && i > 0 && s_type[i - 1] {
+      tails[text[i - 1]] -= 1;
+      sa[tails[text[i - 1]]] = i - 1;
+    }
+  }
+}
+
+// Whether the LMS substrings (from an LMS position up to and including
+// the next one) starting at a and b are the same
+fn lms_substrings_equal(text: &[usize], s_type: &[bool], a: usize, b: usize) -> bool {
+  let n = text.len();
+  // The last character is unique, so its substring equals no other
+  if a == n - 1 || b == n - 1 {
+    return a == b;
+  }
+  let is_lms = |i: usize| i > 0 && s_type[i] && !s_type[i - 1];
+
+  let mut k = 0;
+  loop {
+    if text[a + k] != text[b + k] || s_type[a + k] != s_type[b + k] {
+      return false;
+    }
+    if k > 0 && (is_lms(a + k) || is_lms(b + k)) {
+      return is_lms(a + k) && is_lms(b + k);
+    }
+    k += 1;
+  }
+}
+
+// lcp[i] is the length of the longest common prefix of the suffixes at
+// sa[i - 1] and sa[i], and 0 for the first one.
+fn lcp_array(text: &[usize], sa: &[usize]) -> Vec<usize> {
+  let n = text.len();
+  let mut rank = vec![0; n];
+  for (i, &suffix) in sa.iter().enumerate() {
+    rank[suffix] = i;
+  }
+
+  let mut lcp = vec![0; n];
+  let mut h: usize = 0;
+  for i in 0..n {
+    if rank[i] > 0 {
+      let j = sa[rank[i] - 1];
+      while i + h < n && j + h < n && text[i + h] == text[j + h] {
+        h += 1;
+      }
+      lcp[rank[i]] = h;
+      h = h.saturating_sub(1);
+    } else {
+      h = 0;
+    }
+  }
+  lcp
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn naive_suffix_array(text: &[usize]) -> Vec<usize> {
+    let mut sa: Vec<usize> = (0..text.len()).collect();
+    sa.sort_by(|&a, &b| text[a..].cmp(&text[b..]));
+    sa
+  }
+
+  fn text_of(s: &[u8]) -> Vec<usize> {
+    s.iter()
+      .map(|&b| b as usize + 1)
+      .chain(std::iter::once(0))
+      .collect()
+  }
+
+  #[test]
+  fn suffix_array_of_banana() {
+    let text = text_of(b"banana");
+    let sa = suffix_array(&text, 257);
+    assert_eq!(vec![6, 5, 3, 1, 0, 4, 2], sa);
+    assert_eq!(vec![0, 0, 1, 3, 0, 0, 2], lcp_array(&text, &sa));
+  }
+
+  #[quickcheck]
+  fn quickcheck_suffix_array_matches_naive(bytes: Vec<u8>) -> bool {
+    let text = text_of(&bytes);
+    suffix_array(&text, 257) == naive_suffix_array(&text)
+  }
+}
@@ -0,0 +1,85 @@
+use super::label_data::LabelData;
+use super::node::NodeId;
+
+// The children of a node, as a list of (label data, node) pairs sorted
+// by the label data. Most nodes in a tree over DNA have at most five or
+// six children (ACGTN and the separator), and leaves have none, so a
+// plain sorted vector is both smaller and faster to search than a
+// BTreeMap, which allocates a whole B-tree node for the first child.
+#[derive(Debug, Default)]
+pub struct Children {
+  entries: Vec<(LabelData, NodeId)>,
+}
+
+impl Children {
+  pub fn new() -> Self {
+    Children { entries: vec![] }
+  }
+
+  fn position(&self, b: &LabelData) -> Result<usize, usize> {
+    self.entries.binary_search_by(|(l, _)| l.cmp(b))
+  }
+
+  pub fn get(&self, b: &LabelData) -> Option<&NodeId> {
+    self.position(b).ok().map(|i| &self.entries[i].1)
+  }
+
+  pub fn contains_key(&self, b: &LabelData) -> bool {
+    self.position(b).is_ok()
+  }
+
+  // Inserts the child, replacing (and returning) the existing one
+  // for the same label data, just like a map would.
+  pub fn insert(&mut self, b: LabelData, id: NodeId) -> Option<NodeId> {
+    match self.position(&b) {
+      Ok(i) => Some(std::mem::replace(&mut self.entries[i].1, id)),
+      Err(i) => {
+        self.entries.insert(i, (b, id));
+        None
+      }
+    }
+  }
+
+  pub fn remove(&mut self, b: &LabelData) -> Option<NodeId> {
+    self.position(b).ok().map(|i| self.entries.remove(i).1)
+  }
+
+  pub fn len(&self) -> usize {
+    self.entries.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.entries.is_empty()
+  }
+
+  // The children in the order of their label data
+  pub fn iter(&self) -> impl Iterator<Item = (&LabelData, &NodeId)> {
+    self.entries.iter().map(|(l, id)| (l, id))
+  }
+
+  pub fn values(&self) -> impl Iterator<Item = &NodeId> {
+    self.entries.iter().map(|(_, id)| id)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn insert_keeps_children_sorted() {
+    let mut children = Children::new();
+    assert_eq!(None, children.insert(LabelData::new(b'T'), 1));
+    assert_eq!(None, children.insert(LabelData::Sep, 2));
+    assert_eq!(None, children.insert(LabelData::new(b'A'), 3));
+    assert_eq!(Some(3), children.insert(LabelData::new(b'A'), 4));
+
+    assert_eq!(
+      vec![2, 4, 1],
+      children.values().copied().collect::<Vec<_>>()
+    );
+    assert_eq!(Some(&1), children.get(&LabelData::new(b'T')));
+    assert!(!children.contains_key(&LabelData::new(b'C')));
+    assert_eq!(3, children.len());
+  }
+}
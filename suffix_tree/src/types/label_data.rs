@@ -17,7 +17,7 @@ pub enum LabelData {
 }
 
 // The separator as printed in output
-static SEP: &'static str = "<$>";
+static SEP: &str = "<$>";
 impl LabelData {
   pub fn new(b: u8) -> Self {
     LabelData::Byte(b)
@@ -1,27 +1,19 @@
-use std::collections::BTreeMap;
 use std::ops::Range;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
+use super::children::Children;
 use super::label_data::LabelData;
 
 pub type NodeId = usize;
-pub type Tree = BTreeMap<LabelData, NodeId>;
 
 #[derive(Debug)]
 pub struct Node {
   pub id: NodeId,
   pub parent: Option<NodeId>,
-  // Todo: Verify BTreeMap performance, since the
-  // implementation is O(n) in Rust, but it utilizes
-  // good cache-lookup for speeding up
-  // Initial guess is that it would be pretty bad in
-  // nodes with a large amount of children, but VERY
-  // fast in a node with a limited amount of children
-  // (Todo) Also, there can only ever be 257 children
-  // on a node, so initial guess is that the BTreeMap
-  // will still perform incredibly fast.
-  pub children: Tree,
+  // Sorted by the label data, see Children for why this is
+  // not a map.
+  pub children: Children,
   pub suffix_link: Option<NodeId>,
 
   pub start: usize,
@@ -49,7 +41,7 @@ impl Node {
     Node {
       id,
       parent,
-      children: BTreeMap::new(),
+      children: Children::new(),
       suffix_link,
       start,
       end: Arc::clone(global_end),
@@ -69,16 +61,13 @@ impl Node {
     self.children.get(b)
   }
 
-  pub fn children(&self) -> &Tree {
+  pub fn children(&self) -> &Children {
     &self.children
   }
 
   // A node is the root if it has no parent
   pub fn is_root(&self) -> bool {
-    match self.parent {
-      None => true,
-      Some(_) => false,
-    }
+    self.parent.is_none()
   }
 
   // A node is a leaf if it has no children